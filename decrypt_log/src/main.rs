@@ -1,30 +1,82 @@
+use chrono::DateTime;
 use clap::Parser;
-use logger::encrypt_util::decrypt_line;
-use std::fs::{File, OpenOptions};
+use logger::encrypt_util::{generate_key_pair, parse_key, CipherParams, LogCipher, KEY_SIZE};
+use logger::keyring::Keyring;
+use logger::log_file::{
+    read_log_header, LogHeader, LogRecord, RecordReader, RECORD_FLAG_ENCRYPTED,
+};
+use logger::naming::NamingTemplate;
+use logger::record::{ExportFormat, LineFormat, Record};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
 use std::io::Write;
-use std::io::{BufWriter, Read};
 use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::{fs, io};
 
+// 解密失败的记录在输出文件中的前缀，后面是十六进制密文
+const DECRYPT_FAILED_MARK: &[u8] = b"[DECRYPT FAILED] ";
+
 #[derive(Parser, Debug)]
 #[command(name = "decrypt_log")]
 #[command(about = "加密日志的解密工具", long_about = None)]
 struct Args {
-    /// 加密秘钥
+    /// 加密秘钥，解密使用 `app_key` 加密的日志
     #[arg(short, long)]
-    app_key: String,
+    app_key: Option<String>,
+
+    /// 密钥环文件，日志文件头中记录了密钥 id 时从密钥环中选择 `app_key`，格式见 `logger::keyring::Keyring`
+    #[arg(long)]
+    keyring: Option<PathBuf>,
+
+    /// 服务端私钥文件，内容为十六进制的 X25519 私钥，解密使用公钥加密的日志
+    #[arg(short = 'k', long)]
+    private_key: Option<PathBuf>,
+
+    /// 生成一对 X25519 密钥，私钥写入该文件，公钥输出到控制台，用于 `MmapConfig::set_public_key`
+    #[arg(long)]
+    generate_key: Option<PathBuf>,
 
     /// 输入文件路径，支持目录和文件
-    #[arg(short, long)]
+    #[arg(short, long, default_value = "")]
     input: String,
+
+    /// 日志文件路径模板，与写入时 `MmapConfig::set_naming_template` 一致，
+    /// 设置后只解密 input 目录下符合模板的加密日志
+    #[arg(short, long)]
+    template: Option<String>,
+
+    /// 结构化日志每一行的格式，与写入时 `MmapConfig::set_line_format` 的占位符相同，
+    /// 时间按日志文件头中记录的时区输出
+    #[arg(short, long)]
+    format: Option<String>,
+
+    /// 转义日志中的 `\\`、`\n` 和 `\r`，每条日志恰好一行，与 `ExportFormat::Escaped` 相同，
+    /// 用 `logger::record::unescape_line` 还原
+    #[arg(short, long)]
+    escape: bool,
 }
 
 fn main() {
     let args = Args::parse();
 
-    if args.app_key.is_empty() {
-        println!("请输入有效的 app_key");
+    if let Some(path) = &args.generate_key {
+        generate_key_file(path);
+        return;
+    }
+
+    let private_key = args.private_key.as_deref().map(read_private_key);
+    let keyring = args.keyring.as_deref().map(read_keyring);
+    let mut keys = DecryptKeys {
+        app_key: args.app_key.filter(|app_key| !app_key.is_empty()),
+        keyring,
+        private_key,
+        ciphers: HashMap::new(),
+    };
+    if keys.app_key.is_none() && keys.keyring.is_none() && keys.private_key.is_none() {
+        println!("请输入有效的 app_key、keyring 或 private_key");
         exit(1);
     }
 
@@ -33,32 +85,111 @@ fn main() {
         exit(1);
     }
 
-    println!("app_key: {}", args.app_key);
+    let template = match args.template.as_deref().map(str::parse::<NamingTemplate>) {
+        Some(Ok(template)) => Some(template),
+        Some(Err(err)) => {
+            println!("{}", err);
+            exit(1);
+        }
+        None => None,
+    };
+
+    if let Some(app_key) = &keys.app_key {
+        println!("app_key: {}", app_key);
+    }
     println!("输入路径: {}", args.input);
-    decrypt_log(args.app_key, args.input);
+    let format = args
+        .format
+        .as_deref()
+        .map(LineFormat::new)
+        .unwrap_or_default();
+    let export_format = if args.escape {
+        ExportFormat::Escaped
+    } else {
+        ExportFormat::Raw
+    };
+    decrypt_log(&mut keys, args.input, template, &format, export_format);
 }
 
-fn decrypt_log(app_key: String, input: String) {
-    let path = Path::new(input.as_str());
-    let mut log_files = Vec::new();
-    if path.is_dir() {
-        traverse_directory(path, &mut log_files);
-    } else if path.is_file() && path.extension().map_or(false, |ext| ext == "log") {
-        log_files.push(path.to_path_buf());
+// 解密使用的密钥，按日志文件头中的加密参数选择
+struct DecryptKeys {
+    app_key: Option<String>,
+    keyring: Option<Keyring>,
+    private_key: Option<[u8; KEY_SIZE]>,
+    // 按加密参数（包括密钥 id）缓存派生的 cipher，同一个 writer 写入的多个文件只派生一次密钥
+    ciphers: HashMap<CipherParams, Result<LogCipher, String>>,
+}
+
+impl DecryptKeys {
+    fn cipher(&mut self, params: CipherParams) -> Result<&LogCipher, String> {
+        if !self.ciphers.contains_key(&params) {
+            let cipher = self.new_cipher(params.clone());
+            self.ciphers.insert(params.clone(), cipher);
+        }
+        self.ciphers[&params].as_ref().map_err(Clone::clone)
     }
 
-    println!("找到的全部日志文件:");
-    for file in &log_files {
-        println!("{:?}", file);
+    fn new_cipher(&self, params: CipherParams) -> Result<LogCipher, String> {
+        if params.is_public_key() {
+            let private_key = self
+                .private_key
+                .as_ref()
+                .ok_or("公钥加密的日志需要 --private-key")?;
+            LogCipher::with_private_key(params, private_key).map_err(|err| err.to_string())
+        } else {
+            let app_key = match &params.key_id {
+                Some(key_id) => self
+                    .keyring
+                    .as_ref()
+                    .and_then(|keyring| keyring.get(key_id))
+                    .ok_or_else(|| format!("--keyring 中没有密钥 {}", key_id))?,
+                None => self.app_key.as_deref().ok_or("该日志需要 --app-key")?,
+            };
+            LogCipher::new(params, app_key).map_err(|err| err.to_string())
+        }
     }
+}
 
-    let mut encrypt_files = Vec::new();
-    for file in &log_files {
-        if is_encrypt_file(file) {
-            encrypt_files.push(file.to_path_buf());
-            continue;
+fn generate_key_file(path: &Path) {
+    let (private_key, public_key) = generate_key_pair().expect("生成密钥失败");
+    fs::write(path, hex::encode(private_key)).expect("写入私钥文件失败");
+    println!("私钥已写入: {:?}，请妥善保管，不要放入客户端", path);
+    println!("公钥: {}", hex::encode(public_key));
+}
+
+fn read_keyring(path: &Path) -> Keyring {
+    match Keyring::load(path) {
+        Ok(keyring) => keyring,
+        Err(err) => {
+            println!("密钥环文件无效: {}", err);
+            exit(1);
+        }
+    }
+}
+
+fn read_private_key(path: &Path) -> [u8; KEY_SIZE] {
+    let content = fs::read_to_string(path).expect("读取私钥文件失败");
+    match parse_key(&content) {
+        Ok(private_key) => private_key,
+        Err(err) => {
+            println!("私钥文件无效: {}", err);
+            exit(1);
         }
     }
+}
+
+fn decrypt_log(
+    keys: &mut DecryptKeys,
+    input: String,
+    template: Option<NamingTemplate>,
+    format: &LineFormat,
+    export_format: ExportFormat,
+) {
+    let path = Path::new(input.as_str());
+    let encrypt_files = match template {
+        Some(template) if path.is_dir() => find_template_files(path, &template),
+        _ => find_log_files(path),
+    };
 
     if encrypt_files.is_empty() {
         println!("没有找到加密日志文件");
@@ -67,11 +198,53 @@ fn decrypt_log(app_key: String, input: String) {
 
     for file in &encrypt_files {
         println!("开始解密: {:?}", file);
-        decrypt_file(&app_key, file).expect("解密失败");
+        decrypt_file(keys, file, format, export_format).expect("解密失败");
+    }
+}
+
+// 按模板查找加密日志文件
+fn find_template_files(dir: &Path, template: &NamingTemplate) -> Vec<PathBuf> {
+    let files = template.find_files(dir).unwrap_or_default();
+    println!("找到的全部日志文件:");
+    for (file, _) in &files {
+        println!("{:?}", file);
     }
+    files
+        .into_iter()
+        .filter(|(file, file_name)| header_encrypted(file).unwrap_or(file_name.is_encrypt))
+        .map(|(file, _)| file)
+        .collect()
 }
 
-fn is_encrypt_file(file: &PathBuf) -> bool {
+// 查找所有 `.log` 文件，按文件头或文件名判断是否为加密日志
+fn find_log_files(path: &Path) -> Vec<PathBuf> {
+    let mut log_files = Vec::new();
+    if path.is_dir() {
+        traverse_directory(path, &mut log_files);
+    } else if path.is_file() && path.extension().is_some_and(|ext| ext == "log") {
+        log_files.push(path.to_path_buf());
+    }
+
+    println!("找到的全部日志文件:");
+    for file in &log_files {
+        println!("{:?}", file);
+    }
+
+    log_files
+        .into_iter()
+        .filter(|file| is_encrypt_file(file))
+        .collect()
+}
+
+// 文件头中记录的是否为加密日志，旧版本文件没有记录
+fn header_encrypted(file: &Path) -> Option<bool> {
+    read_log_header(file).ok().flatten()?.encrypted
+}
+
+fn is_encrypt_file(file: &Path) -> bool {
+    if let Some(encrypted) = header_encrypted(file) {
+        return encrypted;
+    }
     if let Some(file_name) = file.file_name() {
         if let Some(file_name_str) = file_name.to_str() {
             if file_name_str.contains("encrypt") {
@@ -82,42 +255,119 @@ fn is_encrypt_file(file: &PathBuf) -> bool {
     false
 }
 
-fn decrypt_file(app_key: &str, encrypt_file: &PathBuf) -> io::Result<()> {
+fn decrypt_file(
+    keys: &mut DecryptKeys,
+    encrypt_file: &Path,
+    format: &LineFormat,
+    export_format: ExportFormat,
+) -> io::Result<()> {
+    // 逐条读取，旧版本文件按行分隔，每一行都是密文
+    let mut records =
+        RecordReader::open(encrypt_file, RECORD_FLAG_ENCRYPTED).expect("读取加密文件失败");
+    let header = records.header().cloned().unwrap_or_default();
+    if records.header().is_some() {
+        print_writer_info(&header);
+    }
+    let time_zone = header.time_zone.unwrap_or_default();
+    // 按文件头中的加密参数解密，旧版本文件没有记录，为 AES-128-ECB
+    let params = header.cipher.unwrap_or_default();
+    println!(
+        "加密模式: {}，密钥派生: {}，密钥 id: {}",
+        params.mode,
+        params.kdf,
+        params.key_id.as_deref().unwrap_or("-")
+    );
+    let cipher = match keys.cipher(params) {
+        Ok(cipher) => cipher,
+        Err(err) => {
+            println!("{}，跳过: {:?}", err, encrypt_file);
+            return Ok(());
+        }
+    };
     let decrypt_file = append_to_filename(encrypt_file, "_decrypt");
     let mut out_buf = BufWriter::new(File::create(&decrypt_file).expect("创建解密文件失败"));
 
-    let total_length = get_first_zero_pos(&encrypt_file).expect("倒查 0x00 第一个位置 失败");
-    let mut src_file = File::open(encrypt_file).expect("打开加密文件失败");
-    let mut buffer = vec![0u8; total_length as usize];
-    src_file.read_exact(&mut buffer).expect("读取加密文件失败");
-
-    for bytes in buffer.split(|&b| b == b'\n') {
-        if bytes.is_empty() {
-            continue;
-        }
-        let encrypted_text = String::from_utf8(bytes.to_vec()).unwrap_or("".to_string());
-        let msg = decrypt_line(app_key, encrypted_text.as_str()).unwrap_or("".to_string());
-        writeln!(out_buf, "{}", msg)?;
+    let mut decrypt_failures = 0;
+    while let Some(record) = records.next_record() {
+        let Ok(record) = record else {
+            println!("日志记录损坏，跳过文件剩余部分: {:?}", encrypt_file);
+            break;
+        };
+        let msg = if record.is_encrypted() {
+            match cipher.decrypt_record(&record) {
+                Ok(msg) => msg,
+                // 密钥错误或密文被篡改，输出十六进制密文，不当作空行
+                Err(_) => {
+                    decrypt_failures += 1;
+                    let mut line = DECRYPT_FAILED_MARK.to_vec();
+                    line.extend_from_slice(&hex_ciphertext(&record));
+                    out_buf.write_all(&export_format.apply(Cow::Owned(line)))?;
+                    out_buf.write_all(b"\n")?;
+                    continue;
+                }
+            }
+        } else {
+            record.payload.to_vec()
+        };
+        let structured = record
+            .is_structured()
+            .then(|| Record::decode(&msg).ok())
+            .flatten();
+        let line = match structured {
+            Some(structured) => Cow::Owned(format.render(&structured, &time_zone).into_bytes()),
+            None => Cow::Owned(msg),
+        };
+        out_buf.write_all(&export_format.apply(line))?;
+        out_buf.write_all(b"\n")?;
+    }
+    out_buf.flush()?;
+    if decrypt_failures > 0 {
+        println!(
+            "{} 条记录解密失败（密钥错误或密文被篡改），以 {} 开头输出十六进制密文: {:?}",
+            decrypt_failures,
+            String::from_utf8_lossy(DECRYPT_FAILED_MARK).trim(),
+            decrypt_file
+        );
+    } else {
+        println!("解密成功: {:?}", decrypt_file);
     }
-    println!("解密成功: {:?}", decrypt_file);
     Ok(())
 }
 
-fn get_first_zero_pos(path: &PathBuf) -> io::Result<u64> {
-    let mut file = OpenOptions::new().read(true).open(path)?;
-    let total_length = file.metadata()?.len();
-
-    let mut buffer = vec![0u8; total_length as usize];
-    file.read_exact(&mut buffer)?;
-
-    let mut pos = total_length as usize;
-    while pos > 0 && buffer[pos - 1] == 0 {
-        pos -= 1;
+// 记录的十六进制密文，旧版本的记录本身就是十六进制密文，二进制密文和损坏的旧版本记录转为十六进制
+fn hex_ciphertext<'a>(record: &LogRecord<'a>) -> Cow<'a, [u8]> {
+    if !record.is_binary() && record.payload.iter().all(u8::is_ascii_hexdigit) {
+        Cow::Borrowed(record.payload)
+    } else {
+        Cow::Owned(hex::encode(record.payload).into_bytes())
     }
-    Ok(pos as u64)
 }
 
-fn append_to_filename(path: &PathBuf, suffix: &str) -> PathBuf {
+// 输出文件头中记录的创建信息
+fn print_writer_info(header: &LogHeader) {
+    let created_at = header
+        .created_at
+        .and_then(DateTime::from_timestamp_millis)
+        .map(|time| {
+            let time_zone = header.time_zone.unwrap_or_default();
+            format!("{} ({})", time_zone.to_local(&time), time_zone)
+        });
+    println!(
+        "文件版本: {}，创建时间: {}，进程 id: {}，SDK: {} ({}, {})",
+        header.version,
+        created_at.as_deref().unwrap_or("-"),
+        header
+            .pid
+            .map(|pid| pid.to_string())
+            .as_deref()
+            .unwrap_or("-"),
+        header.sdk_version.as_deref().unwrap_or("-"),
+        header.sdk_commit.as_deref().unwrap_or("-"),
+        header.sdk_target.as_deref().unwrap_or("-"),
+    );
+}
+
+fn append_to_filename(path: &Path, suffix: &str) -> PathBuf {
     let parent = path.parent().expect("获取父目录失败");
     let file_stem = path.file_stem().expect("获取文件名失败");
     let extension = path.extension();
@@ -136,14 +386,12 @@ fn append_to_filename(path: &PathBuf, suffix: &str) -> PathBuf {
 
 fn traverse_directory(dir: &Path, log_files: &mut Vec<PathBuf>) {
     if let Ok(entries) = fs::read_dir(dir) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_dir() {
-                    traverse_directory(&path, log_files);
-                } else if path.extension().map_or(false, |ext| ext == "log") {
-                    log_files.push(path);
-                }
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                traverse_directory(&path, log_files);
+            } else if path.extension().is_some_and(|ext| ext == "log") {
+                log_files.push(path);
             }
         }
     }
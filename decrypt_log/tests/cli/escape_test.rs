@@ -0,0 +1,89 @@
+/// 测试解密工具的输出：`--escape` 时每条日志恰好一行，解密失败的记录输出十六进制密文
+#[cfg(test)]
+pub mod escape_test {
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::MmapWriter;
+    use logger::record::unescape_line;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    const MESSAGES: [&[u8]; 4] = [
+        b"line1\nline2",
+        b"back\\slash\\n",
+        b"crlf\r\n",
+        b"\x00\x01binary\xff",
+    ];
+
+    // 写入一个加密日志文件，返回文件路径
+    fn write_log(base_dir: &Path) -> PathBuf {
+        let _ = fs::remove_dir_all(base_dir);
+        let config = MmapConfig::new("12345", true);
+        let mut writer = MmapWriter::try_new(&base_dir.to_path_buf(), config).unwrap();
+        for message in MESSAGES {
+            writer.write_bytes(message).unwrap();
+        }
+        writer.close().unwrap();
+
+        let mut files = vec![];
+        for day in fs::read_dir(base_dir).unwrap() {
+            let day = day.unwrap().path();
+            if day.is_dir() {
+                for file in fs::read_dir(day).unwrap() {
+                    files.push(file.unwrap().path());
+                }
+            }
+        }
+        assert_eq!(files.len(), 1, "log files: {:?}", files);
+        files.remove(0)
+    }
+
+    // 用 `--escape` 解密，返回输出文件的每一行
+    fn decrypt_escaped(log_file: &Path, app_key: &str) -> Vec<Vec<u8>> {
+        let output = Command::new(env!("CARGO_BIN_EXE_decrypt_log"))
+            .args(["--app-key", app_key, "--escape", "--input"])
+            .arg(log_file)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let stem = log_file.file_stem().unwrap().to_str().unwrap();
+        let decrypted = fs::read(log_file.with_file_name(format!("{}_decrypt.log", stem))).unwrap();
+        let mut lines: Vec<Vec<u8>> = decrypted
+            .split(|&b| b == b'\n')
+            .map(<[u8]>::to_vec)
+            .collect();
+        assert_eq!(lines.pop(), Some(vec![]));
+        lines
+    }
+
+    #[test]
+    fn test_escaped_round_trip() {
+        let base_dir = PathBuf::from("../target/tmp_log_decrypt_escape");
+        let log_file = write_log(&base_dir);
+
+        // 包含换行的日志也只占一行，还原后与写入的内容相同
+        let lines = decrypt_escaped(&log_file, "12345");
+        assert_eq!(lines.len(), MESSAGES.len());
+        for (line, message) in lines.iter().zip(MESSAGES) {
+            assert_eq!(unescape_line(line), message);
+        }
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_decrypt_failed_hex() {
+        let base_dir = PathBuf::from("../target/tmp_log_decrypt_failed_hex");
+        let log_file = write_log(&base_dir);
+
+        // 密钥错误时每条记录输出一行标记和十六进制密文
+        let lines = decrypt_escaped(&log_file, "wrong-key");
+        assert_eq!(lines.len(), MESSAGES.len());
+        for line in lines {
+            let ciphertext = line.strip_prefix(b"[DECRYPT FAILED] ").unwrap();
+            assert!(!ciphertext.is_empty());
+            assert!(ciphertext.iter().all(u8::is_ascii_hexdigit));
+        }
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+}
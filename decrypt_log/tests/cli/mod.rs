@@ -0,0 +1 @@
+mod escape_test;
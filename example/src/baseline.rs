@@ -0,0 +1,83 @@
+use memmap2::MmapMut;
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// 最初版本的写入方式，只用于对比写入耗时：
+/// 每次空间不足时把文件扩展 buffer_size 并重新映射整个文件，写入前倒序查找第一个 0 的位置
+pub struct BaselineWriter {
+    path: PathBuf,
+    buffer_size: usize,
+    flush_size: usize,
+    mmap: Option<MmapMut>,
+    buffer: Vec<u8>,
+}
+
+impl BaselineWriter {
+    pub fn try_new(base_dir: &Path, buffer_size: usize, flush_size: usize) -> io::Result<Self> {
+        fs::create_dir_all(base_dir)?;
+        Ok(BaselineWriter {
+            path: base_dir.join("baseline.log"),
+            buffer_size,
+            flush_size,
+            mmap: None,
+            buffer: Vec::with_capacity(buffer_size),
+        })
+    }
+
+    pub fn write(&mut self, message: &str) -> io::Result<()> {
+        let data = format!("{}\n", message);
+        if self.buffer.len() + data.len() > self.buffer.capacity() {
+            self.flush()?;
+        }
+        self.buffer.extend_from_slice(data.as_bytes());
+        if self.buffer.len() >= self.flush_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        if self.mmap.is_none() {
+            self.remap()?;
+        }
+        loop {
+            let mmap = self
+                .mmap
+                .as_mut()
+                .ok_or_else(|| io::Error::other("Can't flush to disk by mmap"))?;
+
+            // 倒序查找可用空间
+            let mut pos = mmap.len();
+            while pos > 0 && mmap[pos - 1] == 0 {
+                pos -= 1;
+            }
+            if pos + self.buffer.len() > mmap.len() {
+                self.remap()?;
+                continue;
+            }
+
+            mmap[pos..pos + self.buffer.len()].copy_from_slice(&self.buffer);
+            mmap.flush()?;
+            self.buffer.clear();
+            return Ok(());
+        }
+    }
+
+    // 文件扩展 buffer_size 后重新映射整个文件
+    fn remap(&mut self) -> io::Result<()> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)?;
+        let file_size = file.metadata()?.len();
+        file.set_len(file_size + self.buffer_size as u64)?;
+        self.mmap = Some(unsafe { MmapMut::map_mut(&file)? });
+        Ok(())
+    }
+}
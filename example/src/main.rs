@@ -1,3 +1,6 @@
+mod baseline;
+
+use baseline::BaselineWriter;
 use chrono::{FixedOffset, NaiveDateTime, TimeZone};
 use logger::mmap_config::MmapConfig;
 use logger::mmap_writer::MmapWriter;
@@ -7,8 +10,14 @@ use std::path::PathBuf;
 use std::time::Instant;
 
 fn main() {
+    // cargo run -p mmap_demo --release -- bench
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        bench_file_growth(512 * 1024 * 1024);
+        return;
+    }
+
     let _ = remove_dir_all("./target/tmp_log");
-    let count = 1 * 10000;
+    let count = 10000;
     let length = 100;
     // write_log(count, length);
     // write_encrypt_log(count, length);
@@ -32,7 +41,7 @@ fn export_encrypt_log() {
     let is_encrypt = true;
     let base_dir = PathBuf::from("./target/tmp_log/");
     let config = MmapConfig::new(app_key, is_encrypt);
-    let mut encrypt_writer = MmapWriter::try_new(&base_dir, config).unwrap();
+    let encrypt_writer = MmapWriter::try_new(&base_dir, config).unwrap();
     // 添加计时开始点
     let start = Instant::now();
     let output = PathBuf::from("./target/tmp_log/encrypt_log.log");
@@ -54,7 +63,7 @@ fn export_log() {
     let is_encrypt = false;
     let base_dir = PathBuf::from("./target/tmp_log/");
     let config = MmapConfig::new(app_key, is_encrypt);
-    let mut encrypt_writer = MmapWriter::try_new(&base_dir, config).unwrap();
+    let encrypt_writer = MmapWriter::try_new(&base_dir, config).unwrap();
     // 添加计时开始点
     let start = Instant::now();
     let output = PathBuf::from("./target/tmp_log/plain_log.log");
@@ -77,7 +86,7 @@ fn write_all_log(count: i32, length: i32) {
     let mut encrypt_writer = MmapWriter::try_new(&base_dir, config1).unwrap();
     // 添加计时开始点
     let start = Instant::now();
-    for _ in 0..1 * count {
+    for _ in 0..count {
         let text = string_by_length(length);
         let _ = writer.write(text.as_str());
         let _ = encrypt_writer.write(text.as_str());
@@ -88,6 +97,7 @@ fn write_all_log(count: i32, length: i32) {
 }
 
 // 写加密日志
+#[allow(dead_code)]
 fn write_encrypt_log(count: i32, length: i32) {
     let app_key = "testAppKey";
     let is_encrypt = true;
@@ -96,7 +106,7 @@ fn write_encrypt_log(count: i32, length: i32) {
     let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
     // 添加计时开始点
     let start = Instant::now();
-    for _ in 0..1 * count {
+    for _ in 0..count {
         let _ = writer.write(string_by_length(length).as_str());
     }
     // 获取总耗时
@@ -109,6 +119,7 @@ fn write_encrypt_log(count: i32, length: i32) {
 }
 
 // 写普通日志
+#[allow(dead_code)]
 fn write_log(count: i32, length: i32) {
     let app_key = "testAppKey";
     let is_encrypt = false;
@@ -117,7 +128,7 @@ fn write_log(count: i32, length: i32) {
     let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
     // 添加计时开始点
     let start = Instant::now();
-    for _ in 0..1 * count {
+    for _ in 0..count {
         let _ = writer.write(string_by_length(length).as_str());
     }
     // 计算总耗时
@@ -129,6 +140,46 @@ fn write_log(count: i32, length: i32) {
     );
 }
 
+// 对比当前 writer 和最初每次刷新重新映射整个文件的写入方式的耗时，total_bytes 为写入的日志总量
+fn bench_file_growth(total_bytes: usize) {
+    let text = string_by_length(100);
+    let count = total_bytes / text.len();
+    let base_dir = PathBuf::from("./target/tmp_log_bench/");
+    let buffer_size = 128 * 1024;
+
+    let _ = remove_dir_all(&base_dir);
+    let mut config = MmapConfig::new("testAppKey", false);
+    config.set_buffer_size(buffer_size);
+    config.set_flush_size(buffer_size);
+    let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+    let start = Instant::now();
+    for _ in 0..count {
+        let _ = writer.write(text.as_str());
+    }
+    writer.close().unwrap();
+    print_bench("MmapWriter", total_bytes, start);
+
+    let _ = remove_dir_all(&base_dir);
+    let mut writer = BaselineWriter::try_new(&base_dir, buffer_size, buffer_size).unwrap();
+    let start = Instant::now();
+    for _ in 0..count {
+        let _ = writer.write(text.as_str());
+    }
+    writer.flush().unwrap();
+    print_bench("baseline remap per flush", total_bytes, start);
+
+    let _ = remove_dir_all(&base_dir);
+}
+
+fn print_bench(name: &str, total_bytes: usize, start: Instant) {
+    println!(
+        "{}: {} MB in {} ms",
+        name,
+        total_bytes / 1024 / 1024,
+        start.elapsed().as_millis()
+    );
+}
+
 fn string_by_length(length: i32) -> String {
     let chars: Vec<char> = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789"
         .chars()
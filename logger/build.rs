@@ -13,7 +13,7 @@ fn create_build_info() {
     println!("cargo:rerun-if-changed=src/build_info.rs");
 
     let dest_path = Path::new("src/build_info.rs");
-    let mut file = File::create(&dest_path).expect("Problem creating the build_info.rs");
+    let mut file = File::create(dest_path).expect("Problem creating the build_info.rs");
     let version = env::var("CARGO_PKG_VERSION").unwrap();
 
     let mut write_line = |line: &str| {
@@ -50,14 +50,14 @@ fn create_build_info() {
 
     // 编译信息
     // 把编译信息写入 sdk，可以通过命令行直接查看 sdk 信息
-    /// ```
-    /// # 编译
-    /// cargo build -p logger
-    /// # 查看信息:
-    /// strings target/debug/liblogger.a | grep my_version
-    /// # 输出示例：
-    /// {"my_version":"0.1.0","my_commit":"9c13add","my_build_time":"2025-05-27T14:59:22.667099+08:00"}
-    /// ```
+    // ```
+    // # 编译
+    // cargo build -p logger
+    // # 查看信息:
+    // strings target/debug/liblogger.a | grep my_version
+    // # 输出示例：
+    // {"my_version":"0.1.0","my_commit":"9c13add","my_build_time":"2025-05-27T14:59:22.667099+08:00"}
+    // ```
     let json_info = format!(
         r#"{{"my_version":"{version}","my_commit":"{commit}","my_build_time":"{time}","my_target":"{target}"}}"#,
         version = version,
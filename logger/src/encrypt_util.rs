@@ -1,6 +1,17 @@
+use crate::log_file::{record_aad, LogRecord};
 use aes::Aes128;
+use aes_gcm::aead::{Aead, KeyInit, Nonce, Payload};
+use aes_gcm::Aes256Gcm;
+use argon2::Argon2;
 use block_modes::block_padding::Pkcs7;
 use block_modes::{BlockMode, Ecb};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use x25519_dalek::{PublicKey, StaticSecret};
 
 // 定义类型
 type Aes128Ecb = Ecb<Aes128, Pkcs7>;
@@ -15,10 +26,7 @@ fn generate_key(app_key: &str) -> [u8; 16] {
 
 // 加密一行日志
 pub fn encrypt_line(app_key: &str, plain: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let key = generate_key(app_key);
-    let cipher = Aes128Ecb::new_from_slices(&key, &[])?;
-    let encrypted = cipher.encrypt_vec(plain.as_bytes());
-    Ok(hex::encode(encrypted)) // 将二进制加密数据转为十六进制写入
+    encrypt_bytes(app_key, plain.as_bytes())
 }
 
 // 解密一行日志
@@ -26,9 +34,565 @@ pub fn decrypt_line(
     app_key: &str,
     encrypted_hex: &str,
 ) -> Result<String, Box<dyn std::error::Error>> {
+    let decrypted = decrypt_bytes(app_key, encrypted_hex.as_bytes())?;
+    Ok(String::from_utf8(decrypted)?)
+}
+
+// 加密任意二进制数据，返回十六进制字符串
+pub fn encrypt_bytes(app_key: &str, plain: &[u8]) -> Result<String, Box<dyn std::error::Error>> {
+    let key = generate_key(app_key);
+    let cipher = Aes128Ecb::new_from_slices(&key, &[])?;
+    let encrypted = cipher.encrypt_vec(plain);
+    Ok(hex::encode(encrypted)) // 将二进制加密数据转为十六进制写入
+}
+
+// 解密 `encrypt_bytes` 生成的十六进制数据
+pub fn decrypt_bytes(
+    app_key: &str,
+    encrypted_hex: &[u8],
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
     let key = generate_key(app_key);
     let cipher = Aes128Ecb::new_from_slices(&key, &[])?;
     let encrypted = hex::decode(encrypted_hex)?;
-    let decrypted = cipher.decrypt_vec(&encrypted)?;
-    Ok(String::from_utf8(decrypted)?)
+    Ok(cipher.decrypt_vec(&encrypted)?)
+}
+
+// 加密参数中的字段，格式与日志文件头的扩展字段相同
+const PARAM_MODE: u8 = 1;
+const PARAM_EPHEMERAL_KEY: u8 = 2;
+const PARAM_KDF: u8 = 3;
+const PARAM_SALT: u8 = 4;
+const PARAM_KEY_ID: u8 = 5;
+// 从 `app_key` 派生密钥的 HKDF info
+const APP_KEY_INFO: &[u8] = b"mmap-log app key";
+// 每次会话随机生成的 salt 长度
+const SALT_SIZE: usize = 16;
+// 解密时允许的 Argon2 内存上限，避免文件头中的参数占用过多内存，1 GB
+const MAX_ARGON2_MEMORY_KIB: u32 = 1024 * 1024;
+// 解密时允许的 Argon2 迭代次数上限，避免文件头中的参数使派生密钥耗时过长
+const MAX_ARGON2_TIME_COST: u32 = 64;
+// 解密时允许的 PBKDF2 迭代次数上限，推荐值的 16 倍
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+// 公钥加密时派生会话密钥的 HKDF info 前缀
+const SESSION_KEY_INFO: &[u8] = b"mmap-log x25519 session key";
+
+/// X25519 密钥长度，公钥和私钥都是 32 字节
+pub const KEY_SIZE: usize = 32;
+
+/// 加密模式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CipherMode {
+    /// AES-128-ECB，旧版本使用的模式，相同明文的密文相同，也无法发现篡改，仅用于兼容
+    #[default]
+    AesEcb,
+    /// AES-256-GCM
+    Aes256Gcm,
+    /// ChaCha20-Poly1305，没有 AES 硬件加速的设备上更快
+    ChaCha20Poly1305,
+}
+
+impl CipherMode {
+    fn as_u8(self) -> u8 {
+        match self {
+            CipherMode::AesEcb => 0,
+            CipherMode::Aes256Gcm => 1,
+            CipherMode::ChaCha20Poly1305 => 2,
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(CipherMode::AesEcb),
+            1 => Some(CipherMode::Aes256Gcm),
+            2 => Some(CipherMode::ChaCha20Poly1305),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CipherMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CipherMode::AesEcb => "aes-ecb",
+            CipherMode::Aes256Gcm => "aes-256-gcm",
+            CipherMode::ChaCha20Poly1305 => "chacha20-poly1305",
+        };
+        f.pad(name)
+    }
+}
+
+impl FromStr for CipherMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "aes-ecb" => Ok(CipherMode::AesEcb),
+            "aes-256-gcm" => Ok(CipherMode::Aes256Gcm),
+            "chacha20-poly1305" => Ok(CipherMode::ChaCha20Poly1305),
+            _ => Err(format!("Invalid cipher mode: {}", s)),
+        }
+    }
+}
+
+/// 从 `app_key` 派生密钥的方式
+///
+/// 除 `Legacy` 外都使用随机 salt，相同的 `app_key` 每次会话得到不同的密钥；
+/// `Pbkdf2` 和 `Argon2` 有计算成本，`app_key` 强度不高时也难以离线暴力破解
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum KeyDerivation {
+    /// 旧版本的方式，AES-128-ECB 为 `app_key` 的 MD5，AEAD 模式为 SHA-256，没有 salt，仅用于解密旧文件
+    #[default]
+    Legacy,
+    /// HKDF-SHA256，没有计算成本，仅适合随机生成的高强度 `app_key`
+    Hkdf,
+    /// PBKDF2-HMAC-SHA256
+    Pbkdf2 { iterations: u32 },
+    /// Argon2id，`memory_kib` 为占用的内存，单位 KB
+    Argon2 {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl KeyDerivation {
+    /// 推荐的 PBKDF2 参数，60 万次迭代
+    pub const PBKDF2: KeyDerivation = KeyDerivation::Pbkdf2 {
+        iterations: 600_000,
+    };
+    /// 推荐的 Argon2id 参数，19 MB 内存、2 次迭代
+    ///
+    /// 每个会话派生一次密钥，release 构建约 40 ms，未优化的 `argon2` 和 `blake2` 约 1 秒
+    pub const ARGON2: KeyDerivation = KeyDerivation::Argon2 {
+        memory_kib: 19 * 1024,
+        iterations: 2,
+        parallelism: 1,
+    };
+
+    /// 参数是否有效，为了避免文件头中的参数使解密占用过多内存或耗时过长，
+    /// PBKDF2 最多 1000 万次迭代，Argon2 最多 1 GB 内存、64 次迭代
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            KeyDerivation::Legacy | KeyDerivation::Hkdf => true,
+            KeyDerivation::Pbkdf2 { iterations } => {
+                (1..=MAX_PBKDF2_ITERATIONS).contains(&iterations)
+            }
+            KeyDerivation::Argon2 {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                memory_kib <= MAX_ARGON2_MEMORY_KIB
+                    && iterations <= MAX_ARGON2_TIME_COST
+                    && argon2::Params::new(memory_kib, iterations, parallelism, None).is_ok()
+            }
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let (kind, values) = match *self {
+            KeyDerivation::Legacy => (0, vec![]),
+            KeyDerivation::Hkdf => (1, vec![]),
+            KeyDerivation::Pbkdf2 { iterations } => (2, vec![iterations]),
+            KeyDerivation::Argon2 {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => (3, vec![memory_kib, iterations, parallelism]),
+        };
+        let mut bytes = vec![kind];
+        for value in values {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let (&kind, rest) = bytes.split_first()?;
+        let values: Vec<u32> = rest
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        match (kind, values.as_slice()) {
+            (0, []) => Some(KeyDerivation::Legacy),
+            (1, []) => Some(KeyDerivation::Hkdf),
+            (2, &[iterations]) => Some(KeyDerivation::Pbkdf2 { iterations }),
+            (3, &[memory_kib, iterations, parallelism]) => Some(KeyDerivation::Argon2 {
+                memory_kib,
+                iterations,
+                parallelism,
+            }),
+            _ => None,
+        }
+    }
+
+    // 从 `app_key` 和 `salt` 派生 `key.len()` 字节的密钥，`Legacy` 不使用该方法
+    fn derive(&self, app_key: &str, salt: &[u8], key: &mut [u8]) -> Result<(), Box<dyn Error>> {
+        if !self.is_valid() {
+            return Err(format!("Invalid key derivation: {}", self).into());
+        }
+        match *self {
+            KeyDerivation::Legacy => return Err("Legacy key derivation has no salt".into()),
+            KeyDerivation::Hkdf => Hkdf::<Sha256>::new(Some(salt), app_key.as_bytes())
+                .expand(APP_KEY_INFO, key)
+                .map_err(|_| "Failed to derive key")?,
+            KeyDerivation::Pbkdf2 { iterations } => {
+                pbkdf2::pbkdf2_hmac::<Sha256>(app_key.as_bytes(), salt, iterations, key)
+            }
+            KeyDerivation::Argon2 {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let params = argon2::Params::new(memory_kib, iterations, parallelism, None)
+                    .map_err(|err| err.to_string())?;
+                Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                    .hash_password_into(app_key.as_bytes(), salt, key)
+                    .map_err(|err| err.to_string())?
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for KeyDerivation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyDerivation::Legacy => write!(f, "legacy"),
+            KeyDerivation::Hkdf => write!(f, "hkdf-sha256"),
+            KeyDerivation::Pbkdf2 { iterations } => {
+                write!(f, "pbkdf2-sha256(iterations={})", iterations)
+            }
+            KeyDerivation::Argon2 {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => write!(
+                f,
+                "argon2id(m={}KB, t={}, p={})",
+                memory_kib, iterations, parallelism
+            ),
+        }
+    }
+}
+
+/// 解密一个日志文件需要的参数，记录在日志文件头中
+///
+/// 没有记录加密参数的旧版本文件按 `CipherParams::default()`，即 AES-128-ECB 处理
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CipherParams {
+    /// 加密模式
+    pub mode: CipherMode,
+    /// 公钥加密时本次会话的临时公钥，解密时与服务端私钥协商出会话密钥；为 `None` 时使用 `app_key` 加密
+    pub ephemeral_public_key: Option<[u8; KEY_SIZE]>,
+    /// 使用 `app_key` 加密时派生密钥的方式，没有记录时为 `KeyDerivation::Legacy`
+    pub kdf: KeyDerivation,
+    /// 派生密钥使用的 salt，`KeyDerivation::Legacy` 没有 salt
+    pub salt: Option<[u8; SALT_SIZE]>,
+    /// 使用 `Keyring` 加密时 `app_key` 的密钥 id，没有记录时使用 `MmapConfig` 的 `app_key`
+    pub key_id: Option<String>,
+}
+
+impl CipherParams {
+    pub fn new(mode: CipherMode) -> Self {
+        Self {
+            mode,
+            ephemeral_public_key: None,
+            kdf: KeyDerivation::Legacy,
+            salt: None,
+            key_id: None,
+        }
+    }
+
+    /// 使用 `kdf` 从 `app_key` 派生密钥，随机生成 salt
+    pub fn with_key_derivation(
+        mode: CipherMode,
+        kdf: KeyDerivation,
+    ) -> Result<Self, Box<dyn Error>> {
+        let salt = match kdf {
+            KeyDerivation::Legacy => None,
+            _ => {
+                let mut salt = [0u8; SALT_SIZE];
+                getrandom::fill(&mut salt)
+                    .map_err(|err| format!("Failed to generate salt: {}", err))?;
+                Some(salt)
+            }
+        };
+        Ok(Self {
+            kdf,
+            salt,
+            ..Self::new(mode)
+        })
+    }
+
+    /// 是否为公钥加密，解密需要服务端私钥
+    pub fn is_public_key(&self) -> bool {
+        self.ephemeral_public_key.is_some()
+    }
+
+    /// 编码为日志文件头中的字段内容
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        push_param(&mut bytes, PARAM_MODE, &[self.mode.as_u8()]);
+        if let Some(key) = &self.ephemeral_public_key {
+            push_param(&mut bytes, PARAM_EPHEMERAL_KEY, key);
+        }
+        if self.kdf != KeyDerivation::Legacy {
+            push_param(&mut bytes, PARAM_KDF, &self.kdf.encode());
+        }
+        if let Some(salt) = &self.salt {
+            push_param(&mut bytes, PARAM_SALT, salt);
+        }
+        if let Some(key_id) = &self.key_id {
+            push_param(&mut bytes, PARAM_KEY_ID, key_id.as_bytes());
+        }
+        bytes
+    }
+
+    /// 解码 `encode` 的结果，不认识的字段直接跳过，加密模式或密钥派生方式未知时返回 `None`
+    pub fn decode(mut bytes: &[u8]) -> Option<Self> {
+        let mut mode = None;
+        let mut ephemeral_public_key = None;
+        let mut kdf = Some(KeyDerivation::Legacy);
+        let mut salt = None;
+        let mut key_id = None;
+        while bytes.len() >= 3 {
+            let tag = bytes[0];
+            let len = u16::from_le_bytes([bytes[1], bytes[2]]) as usize;
+            let value = bytes.get(3..3 + len)?;
+            match tag {
+                PARAM_MODE => mode = value.first().copied().and_then(CipherMode::from_u8),
+                PARAM_EPHEMERAL_KEY => ephemeral_public_key = value.try_into().ok(),
+                PARAM_KDF => kdf = KeyDerivation::decode(value),
+                PARAM_SALT => salt = value.try_into().ok(),
+                PARAM_KEY_ID => key_id = String::from_utf8(value.to_vec()).ok(),
+                _ => {}
+            }
+            bytes = &bytes[3 + len..];
+        }
+        Some(Self {
+            mode: mode?,
+            ephemeral_public_key,
+            kdf: kdf?,
+            salt,
+            key_id,
+        })
+    }
+}
+
+fn push_param(bytes: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    bytes.push(tag);
+    bytes.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(value);
+}
+
+/// 按 `CipherParams` 加密和解密日志记录
+///
+/// 密钥按 `CipherParams::kdf` 从 `app_key` 派生，旧版本文件的 AES-128-ECB 密钥为 `app_key` 的 MD5，
+/// AEAD 模式每条记录使用随机 nonce，解密时会校验密文是否被篡改。
+/// 记录以二进制密文保存（`encrypt_binary`），记录 flag 和写入时间作为附加数据一起认证，读取时用 `decrypt_record` 解密；
+/// `encrypt` / `decrypt` 使用十六进制密文，仅用于兼容旧版本的记录
+///
+/// 公钥加密时，每次会话生成一个临时 X25519 密钥，与服务端公钥协商后用 HKDF-SHA256 派生会话密钥，
+/// 设备上不保存能解密日志的密钥，只有持有服务端私钥才能解密
+pub struct LogCipher {
+    params: CipherParams,
+    key: CipherKey,
+}
+
+enum CipherKey {
+    AesEcb([u8; 16]),
+    Aes256Gcm(Box<Aes256Gcm>),
+    ChaCha20Poly1305(Box<ChaCha20Poly1305>),
+}
+
+impl LogCipher {
+    /// 使用 `app_key` 加密或解密，`params` 中的临时公钥不会使用
+    pub fn new(params: CipherParams, app_key: &str) -> Result<Self, Box<dyn Error>> {
+        let salt = params.salt.as_ref().map(|salt| salt.as_slice());
+        let key = match (params.mode, params.kdf, salt) {
+            (CipherMode::AesEcb, KeyDerivation::Legacy, _) => {
+                CipherKey::AesEcb(generate_key(app_key))
+            }
+            (mode, KeyDerivation::Legacy, _) => {
+                aead_key(mode, &Sha256::digest(app_key.as_bytes()).into())
+            }
+            (CipherMode::AesEcb, kdf, Some(salt)) => {
+                let mut key = [0u8; 16];
+                kdf.derive(app_key, salt, &mut key)?;
+                CipherKey::AesEcb(key)
+            }
+            (mode, kdf, Some(salt)) => {
+                let mut key = [0u8; KEY_SIZE];
+                kdf.derive(app_key, salt, &mut key)?;
+                aead_key(mode, &key)
+            }
+            (_, _, None) => return Err("Missing key derivation salt".into()),
+        };
+        Ok(Self { params, key })
+    }
+
+    /// 公钥加密：生成本次会话的临时密钥，与服务端公钥 `public_key` 协商出会话密钥，只能使用 AEAD 模式
+    pub fn with_public_key(
+        mode: CipherMode,
+        public_key: &[u8; KEY_SIZE],
+    ) -> Result<Self, Box<dyn Error>> {
+        let (ephemeral_secret, ephemeral_public) = generate_key_pair()?;
+        let params = CipherParams {
+            ephemeral_public_key: Some(ephemeral_public),
+            ..CipherParams::new(mode)
+        };
+        Self::exchange(params, &ephemeral_secret, public_key, public_key)
+    }
+
+    /// 解密公钥加密的日志：用服务端私钥 `private_key` 和文件头中的临时公钥协商出会话密钥
+    pub fn with_private_key(
+        params: CipherParams,
+        private_key: &[u8; KEY_SIZE],
+    ) -> Result<Self, Box<dyn Error>> {
+        let ephemeral_public = params
+            .ephemeral_public_key
+            .ok_or("Log file is not encrypted with a public key")?;
+        let server_public = public_key_of(private_key);
+        Self::exchange(params, private_key, &ephemeral_public, &server_public)
+    }
+
+    // X25519 协商，`secret` 与 `peer_public` 计算共享密钥，会话密钥绑定服务端公钥、临时公钥和加密模式
+    fn exchange(
+        params: CipherParams,
+        secret: &[u8; KEY_SIZE],
+        peer_public: &[u8; KEY_SIZE],
+        server_public: &[u8; KEY_SIZE],
+    ) -> Result<Self, Box<dyn Error>> {
+        if params.mode == CipherMode::AesEcb {
+            return Err("Public key encryption requires an AEAD cipher mode".into());
+        }
+        let ephemeral_public = params
+            .ephemeral_public_key
+            .ok_or("Missing ephemeral public key")?;
+        let secret = StaticSecret::from(*secret);
+        let shared = secret.diffie_hellman(&PublicKey::from(*peer_public));
+        if !shared.was_contributory() {
+            return Err("Invalid X25519 public key".into());
+        }
+        let mut info = SESSION_KEY_INFO.to_vec();
+        info.push(params.mode.as_u8());
+        info.extend_from_slice(server_public);
+        info.extend_from_slice(&ephemeral_public);
+        let mut session_key = [0u8; KEY_SIZE];
+        Hkdf::<Sha256>::new(None, shared.as_bytes())
+            .expand(&info, &mut session_key)
+            .map_err(|_| "Failed to derive session key")?;
+        let key = aead_key(params.mode, &session_key);
+        Ok(Self { params, key })
+    }
+
+    /// 加密参数，写入日志文件头
+    pub fn params(&self) -> &CipherParams {
+        &self.params
+    }
+
+    /// 加密一条记录，返回十六进制密文，仅用于兼容旧版本，写入日志使用 `encrypt_binary`
+    pub fn encrypt(&self, plain: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(hex::encode(self.encrypt_binary(plain, &[])?).into_bytes())
+    }
+
+    /// 解密 `encrypt` 生成的十六进制密文，用于旧版本的记录
+    pub fn decrypt(&self, encrypted_hex: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.decrypt_binary(&hex::decode(encrypted_hex)?, &[])
+    }
+
+    /// 加密一条记录，返回二进制密文，AEAD 模式为 nonce 加密文和认证标签
+    ///
+    /// AEAD 模式的 `aad` 不加密，但解密时必须相同，日志记录使用 `record_aad`；AES-128-ECB 不能认证，忽略 `aad`
+    pub fn encrypt_binary(&self, plain: &[u8], aad: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        match &self.key {
+            CipherKey::AesEcb(key) => Ok(Aes128Ecb::new_from_slices(key, &[])?.encrypt_vec(plain)),
+            CipherKey::Aes256Gcm(cipher) => seal(cipher.as_ref(), plain, aad),
+            CipherKey::ChaCha20Poly1305(cipher) => seal(cipher.as_ref(), plain, aad),
+        }
+    }
+
+    /// 解密 `encrypt_binary` 生成的二进制密文，`aad` 与加密时不同时 AEAD 模式返回错误
+    pub fn decrypt_binary(&self, encrypted: &[u8], aad: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+        match &self.key {
+            CipherKey::AesEcb(key) => {
+                Ok(Aes128Ecb::new_from_slices(key, &[])?.decrypt_vec(encrypted)?)
+            }
+            CipherKey::Aes256Gcm(cipher) => open(cipher.as_ref(), encrypted, aad),
+            CipherKey::ChaCha20Poly1305(cipher) => open(cipher.as_ref(), encrypted, aad),
+        }
+    }
+
+    /// 解密一条加密记录，按记录 flag 区分二进制密文和旧版本的十六进制密文，
+    /// 二进制密文的记录 flag 或写入时间被修改时 AEAD 模式返回错误
+    pub fn decrypt_record(&self, record: &LogRecord) -> Result<Vec<u8>, Box<dyn Error>> {
+        if record.is_binary() {
+            let aad = record_aad(record.flags, record.timestamp_ms);
+            self.decrypt_binary(record.payload, &aad)
+        } else {
+            self.decrypt(record.payload)
+        }
+    }
+}
+
+/// 生成 X25519 密钥对，返回 (私钥, 公钥)
+pub fn generate_key_pair() -> Result<([u8; KEY_SIZE], [u8; KEY_SIZE]), Box<dyn Error>> {
+    let mut secret = [0u8; KEY_SIZE];
+    getrandom::fill(&mut secret).map_err(|err| format!("Failed to generate key: {}", err))?;
+    Ok((secret, public_key_of(&secret)))
+}
+
+/// 私钥对应的 X25519 公钥
+pub fn public_key_of(private_key: &[u8; KEY_SIZE]) -> [u8; KEY_SIZE] {
+    PublicKey::from(&StaticSecret::from(*private_key)).to_bytes()
+}
+
+/// 解析十六进制的 X25519 密钥，忽略首尾空白，例如从密钥文件中读取的内容
+pub fn parse_key(hex_key: &str) -> Result<[u8; KEY_SIZE], Box<dyn Error>> {
+    let bytes = hex::decode(hex_key.trim())?;
+    Ok(bytes
+        .try_into()
+        .map_err(|_| format!("Key must be {} bytes", KEY_SIZE))?)
+}
+
+// AEAD 模式的 cipher
+fn aead_key(mode: CipherMode, key: &[u8; KEY_SIZE]) -> CipherKey {
+    match mode {
+        CipherMode::ChaCha20Poly1305 => {
+            CipherKey::ChaCha20Poly1305(Box::new(ChaCha20Poly1305::new(key.into())))
+        }
+        _ => CipherKey::Aes256Gcm(Box::new(Aes256Gcm::new(key.into()))),
+    }
+}
+
+// AEAD 加密，随机 nonce 放在密文前面，`aad` 只认证不加密
+fn seal<A: Aead>(cipher: &A, plain: &[u8], aad: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut nonce = Nonce::<A>::default();
+    getrandom::fill(&mut nonce).map_err(|err| format!("Failed to generate nonce: {}", err))?;
+    let encrypted = cipher
+        .encrypt(&nonce, Payload { msg: plain, aad })
+        .map_err(|_| "Failed to encrypt record")?;
+    let mut sealed = Vec::with_capacity(nonce.len() + encrypted.len());
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&encrypted);
+    Ok(sealed)
+}
+
+// AEAD 解密，密钥错误、密文或 `aad` 被篡改时返回错误
+fn open<A: Aead>(cipher: &A, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let nonce_size = Nonce::<A>::default().len();
+    if sealed.len() < nonce_size {
+        return Err("Encrypted record is too short".into());
+    }
+    let (nonce, encrypted) = sealed.split_at(nonce_size);
+    Ok(cipher
+        .decrypt(
+            Nonce::<A>::from_slice(nonce),
+            Payload {
+                msg: encrypted,
+                aad,
+            },
+        )
+        .map_err(|_| "Failed to decrypt record")?)
 }
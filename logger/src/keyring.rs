@@ -0,0 +1,142 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+// 密钥 id 最长 128 字节，和其他加密参数一起记录在日志文件头和缓存文件头中，缓存文件头只预留了 206 字节
+const MAX_KEY_ID_LEN: usize = 128;
+// 密钥文件中指定当前密钥的行
+const ACTIVE: &str = "active";
+
+/// 多个 `app_key` 组成的密钥环，用于定期更换 `app_key`
+///
+/// 新的日志使用当前密钥加密，并在日志文件头中记录密钥 id；已经停用的密钥只用于解密旧日志，
+/// 导出和解密时按文件头中的密钥 id 自动选择。密钥文件的格式为：
+///
+/// ```text
+/// # 当前使用的密钥
+/// active = v2
+/// v2 = new-app-key
+/// v1 = old-app-key
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub struct Keyring {
+    active_id: String,
+    keys: Vec<(String, String)>,
+}
+
+impl Keyring {
+    /// 创建密钥环，`active_id` 为当前使用的密钥
+    pub fn new(active_id: &str, app_key: &str) -> Self {
+        Self {
+            active_id: active_id.to_string(),
+            keys: vec![(active_id.to_string(), app_key.to_string())],
+        }
+    }
+
+    /// 添加一个停用的密钥，id 已存在时替换
+    pub fn with_retired_key(mut self, key_id: &str, app_key: &str) -> Self {
+        match self.keys.iter_mut().find(|(id, _)| id == key_id) {
+            Some((_, key)) => *key = app_key.to_string(),
+            None => self.keys.push((key_id.to_string(), app_key.to_string())),
+        }
+        self
+    }
+
+    /// 从密钥文件读取
+    pub fn load(path: &Path) -> io::Result<Self> {
+        fs::read_to_string(path)?
+            .parse()
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    /// 当前使用的密钥 id
+    pub fn active_id(&self) -> &str {
+        &self.active_id
+    }
+
+    /// 当前使用的密钥
+    pub fn active_key(&self) -> &str {
+        self.get(&self.active_id).unwrap_or_default()
+    }
+
+    /// 按 id 查找密钥
+    pub fn get(&self, key_id: &str) -> Option<&str> {
+        self.keys
+            .iter()
+            .find(|(id, _)| id == key_id)
+            .map(|(_, key)| key.as_str())
+    }
+
+    /// 所有密钥 id，当前密钥在最前面
+    pub fn key_ids(&self) -> impl Iterator<Item = &str> {
+        self.keys.iter().map(|(id, _)| id.as_str())
+    }
+
+    /// 密钥 id 和密钥是否都有效：id 不为空、不超过 128 字节、不包含空白、`=` 和 `#`，也不能为 `active`；密钥不为空
+    pub fn is_valid(&self) -> bool {
+        self.keys
+            .iter()
+            .all(|(id, key)| is_valid_key_id(id) && !key.is_empty())
+    }
+}
+
+fn is_valid_key_id(key_id: &str) -> bool {
+    !key_id.is_empty()
+        && key_id.len() <= MAX_KEY_ID_LEN
+        && key_id != ACTIVE
+        && !key_id
+            .chars()
+            .any(|c| c.is_whitespace() || c == '=' || c == '#')
+}
+
+// 不输出密钥
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keyring")
+            .field("active_id", &self.active_id)
+            .field("key_ids", &self.key_ids().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// 解析密钥文件的内容，空行和 `#` 开头的行忽略
+impl FromStr for Keyring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut active_id = None;
+        let mut keys: Vec<(String, String)> = Vec::new();
+        for (index, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (id, key) = line
+                .split_once('=')
+                .map(|(id, key)| (id.trim(), key.trim()))
+                .ok_or_else(|| format!("Invalid keyring line {}: missing '='", index + 1))?;
+            if id == ACTIVE {
+                active_id = Some(key.to_string());
+                continue;
+            }
+            if !is_valid_key_id(id) || key.is_empty() {
+                return Err(format!("Invalid keyring line {}", index + 1));
+            }
+            if keys.iter().any(|(existing, _)| existing == id) {
+                return Err(format!("Duplicate key id: {}", id));
+            }
+            keys.push((id.to_string(), key.to_string()));
+        }
+
+        let active_id = active_id.ok_or("Keyring has no active key")?;
+        let position = keys
+            .iter()
+            .position(|(id, _)| *id == active_id)
+            .ok_or_else(|| format!("Active key {} not found in keyring", active_id))?;
+        let active = keys.remove(position);
+        keys.insert(0, active);
+        Ok(Self { active_id, keys })
+    }
+}
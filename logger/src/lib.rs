@@ -1,5 +1,19 @@
 pub mod encrypt_util;
+pub mod keyring;
+#[cfg(feature = "log")]
+pub mod log_backend;
+pub mod log_file;
+pub mod log_reader;
+pub(crate) mod mmap_appender;
+pub(crate) mod mmap_cache;
 pub mod mmap_config;
 pub mod mmap_writer;
+pub mod naming;
+pub mod record;
+pub mod rotation;
+pub mod time_zone;
+#[cfg(feature = "tracing")]
+pub mod tracing_layer;
+pub(crate) mod writer_lock;
 
 pub mod build_info;
@@ -0,0 +1,121 @@
+use crate::mmap_config::MmapConfig;
+use crate::mmap_writer::SharedMmapWriter;
+use crate::record::{current_thread_id, Level, Record};
+use chrono::Utc;
+use log::{LevelFilter, Log, Metadata};
+use std::io;
+use std::path::PathBuf;
+
+/// `log` crate 的后端，`info!`、`error!` 等宏的日志作为结构化日志写入 mmap 日志文件，tag 为日志的 target
+///
+/// 需要开启 `log` feature。默认记录 `Info` 及以上级别，可以按 target 单独设置级别，
+/// target 按 `::` 分隔的前缀匹配，多个前缀都匹配时使用最长的
+///
+/// ```ignore
+/// MmapLogger::new(writer)
+///     .with_max_level(LevelFilter::Debug)
+///     .with_target_level("hyper", LevelFilter::Warn)
+///     .install()?;
+/// ```
+pub struct MmapLogger {
+    writer: SharedMmapWriter,
+    max_level: LevelFilter,
+    target_levels: Vec<(String, LevelFilter)>,
+}
+
+impl MmapLogger {
+    pub fn new(writer: SharedMmapWriter) -> Self {
+        Self {
+            writer,
+            max_level: LevelFilter::Info,
+            target_levels: Vec::new(),
+        }
+    }
+
+    /// 设置默认的最低级别
+    pub fn with_max_level(mut self, max_level: LevelFilter) -> Self {
+        self.max_level = max_level;
+        self
+    }
+
+    /// 设置 `target` 及其子模块的最低级别，重复设置同一个 target 时以最后一次为准
+    pub fn with_target_level(mut self, target: &str, level: LevelFilter) -> Self {
+        self.target_levels.retain(|(prefix, _)| prefix != target);
+        self.target_levels.push((target.to_string(), level));
+        self
+    }
+
+    /// `target` 实际使用的最低级别
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.target_levels
+            .iter()
+            .filter(|(prefix, _)| is_target_prefix(prefix, target))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.max_level, |(_, level)| *level)
+    }
+
+    /// 设置为全局 logger，一个进程只能设置一次，重复设置返回 `AlreadyExists` 错误
+    pub fn install(self) -> io::Result<()> {
+        // log 宏先按全局级别过滤，取所有级别中最低的，其余的在 `enabled` 中过滤
+        let max_level = self
+            .target_levels
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.max_level, Ord::max);
+        log::set_boxed_logger(Box::new(self))
+            .map_err(|err| io::Error::new(io::ErrorKind::AlreadyExists, err.to_string()))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl Log for MmapLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= self.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let record = Record {
+            timestamp_ms: Utc::now().timestamp_millis(),
+            level: record.level().into(),
+            tag: record.target().to_string(),
+            thread_id: current_thread_id(),
+            message: record.args().to_string(),
+            fields: Vec::new(),
+        };
+        // `Log` 无法返回错误，写入失败的日志直接丢弃
+        let _ = self.writer.write_record(&record);
+    }
+
+    fn flush(&self) {
+        let _ = self.writer.flush();
+    }
+}
+
+impl From<log::Level> for Level {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Trace => Level::Trace,
+            log::Level::Debug => Level::Debug,
+            log::Level::Info => Level::Info,
+            log::Level::Warn => Level::Warn,
+            log::Level::Error => Level::Error,
+        }
+    }
+}
+
+/// 使用 `base_dir` 和 `config` 创建 writer 并设置为全局 logger，默认级别为 `max_level`
+pub fn init(base_dir: &PathBuf, config: MmapConfig, max_level: LevelFilter) -> io::Result<()> {
+    let writer = SharedMmapWriter::try_new(base_dir, config)?;
+    MmapLogger::new(writer).with_max_level(max_level).install()
+}
+
+// `prefix` 是否为 `target` 本身或其上级模块
+fn is_target_prefix(prefix: &str, target: &str) -> bool {
+    target
+        .strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
@@ -0,0 +1,821 @@
+use crate::encrypt_util::CipherParams;
+use crate::time_zone::LogTimeZone;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Take, Write};
+use std::path::{Path, PathBuf};
+
+// 日志文件头部布局：
+// [0..4)   magic
+// [4..6)   版本号，u16 小端
+// [6..8)   头部长度，u16 小端，日志数据从该位置开始
+// [8..16)  已提交的日志数据长度，u64 小端
+// [16..头部长度) 扩展字段，每个字段为 tag u8、长度 u16 小端、内容，不认识的 tag 直接跳过
+//
+// 日志数据是带长度和 CRC 的记录，没有文件头的旧版本文件是以 `\n` 分隔的行
+pub const LOG_MAGIC: &[u8; 4] = b"MLOG";
+pub const LOG_VERSION: u16 = 1;
+pub const LOG_HEADER_SIZE: usize = 16;
+const COMMITTED_LEN_RANGE: std::ops::Range<usize> = 8..16;
+// 扩展字段：文件名中日期和小时使用的时区，内容为 `LogTimeZone` 的字符串形式
+const FIELD_TIME_ZONE: u8 = 1;
+// 扩展字段：文件覆盖的时间长度，单位分钟，u32 小端
+const FIELD_WINDOW_MINUTES: u8 = 2;
+// 扩展字段：加密记录使用的加密参数，内容为 `CipherParams::encode` 的结果
+const FIELD_CIPHER: u8 = 3;
+// 扩展字段：是否为加密日志，u8
+const FIELD_ENCRYPTED: u8 = 4;
+// 扩展字段：日志数据的压缩方式，u8
+const FIELD_COMPRESSION: u8 = 5;
+// 扩展字段：文件创建时间，毫秒时间戳，i64 小端
+const FIELD_CREATED_AT: u8 = 6;
+// 扩展字段：创建文件的进程 id，u32 小端
+const FIELD_PID: u8 = 7;
+// 扩展字段：写入文件的 SDK 版本、commit 和编译目标，见 `build_info`
+const FIELD_SDK_VERSION: u8 = 8;
+const FIELD_SDK_COMMIT: u8 = 9;
+const FIELD_SDK_TARGET: u8 = 10;
+
+// 记录布局：
+// [0]      起始标记
+// [1..5)   payload 长度，u32 小端
+// [5]      flags
+// [6..10)  CRC32，覆盖 payload 长度、flags 和 payload，u32 小端
+// [10..)   payload
+const RECORD_MARK: u8 = 0xB7;
+pub const RECORD_HEADER_SIZE: usize = 10;
+/// 记录 flag：payload 已加密
+pub const RECORD_FLAG_ENCRYPTED: u8 = 0x01;
+/// 记录 flag：payload（解密后）是 `Record` 编码的结构化日志
+pub const RECORD_FLAG_STRUCTURED: u8 = 0x02;
+/// 记录 flag：加密记录的 payload 是二进制密文，没有该 flag 的加密记录为十六进制密文
+pub const RECORD_FLAG_BINARY: u8 = 0x04;
+/// 记录 flag：payload 前 8 字节是写入时间，毫秒时间戳，i64 小端，不加密，导出时不需要解密就能按时间过滤
+pub const RECORD_FLAG_TIMESTAMP: u8 = 0x08;
+pub const RECORD_TIMESTAMP_SIZE: usize = 8;
+
+/// 日志数据的压缩方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// 不压缩
+    #[default]
+    None,
+}
+
+impl Compression {
+    fn as_u8(self) -> u8 {
+        match self {
+            Compression::None => 0,
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Compression::None),
+            _ => None,
+        }
+    }
+}
+
+/// 日志文件头
+///
+/// 旧版本的日志文件没有文件头，数据之后用 0x00 填充，读取时需要倒查 0x00 确定数据长度
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogHeader {
+    /// 文件格式版本
+    pub version: u16,
+    /// 头部长度，日志数据从该位置开始
+    pub header_len: usize,
+    /// 已提交的日志数据长度，只有刷新完成的数据才会计入
+    pub committed_len: u64,
+    /// 文件名中日期和小时使用的时区，没有记录时区的文件为 `None`，按 `Asia/Shanghai` 处理
+    pub time_zone: Option<LogTimeZone>,
+    /// 文件覆盖的时间长度，单位分钟，没有记录时按文件名推断
+    pub window_minutes: Option<u32>,
+    /// 加密记录使用的加密参数，没有记录时按 AES-128-ECB 处理
+    pub cipher: Option<CipherParams>,
+    /// 是否为加密日志，没有记录时按文件名判断
+    pub encrypted: Option<bool>,
+    /// 日志数据的压缩方式，没有记录时为不压缩，不认识的压缩方式为 `None`
+    pub compression: Option<Compression>,
+    /// 文件创建时间，毫秒时间戳
+    pub created_at: Option<i64>,
+    /// 创建文件的进程 id
+    pub pid: Option<u32>,
+    /// 写入文件的 SDK 版本，即 `build_info::RUST_SDK_VER`
+    pub sdk_version: Option<String>,
+    /// 写入文件的 SDK commit，即 `build_info::RUST_SDK_COMMIT`
+    pub sdk_commit: Option<String>,
+    /// 写入文件的 SDK 编译目标，即 `build_info::RUST_SDK_TARGET`
+    pub sdk_target: Option<String>,
+}
+
+impl Default for LogHeader {
+    fn default() -> Self {
+        Self {
+            version: LOG_VERSION,
+            header_len: LOG_HEADER_SIZE,
+            committed_len: 0,
+            time_zone: None,
+            window_minutes: None,
+            cipher: None,
+            encrypted: None,
+            compression: None,
+            created_at: None,
+            pid: None,
+            sdk_version: None,
+            sdk_commit: None,
+            sdk_target: None,
+        }
+    }
+}
+
+impl LogHeader {
+    /// 解析文件头，没有 magic 的旧版本文件返回 `None`
+    ///
+    /// `bytes` 不足 `header_len` 时只解析固定部分，扩展字段按没有记录处理
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < LOG_HEADER_SIZE || &bytes[..LOG_MAGIC.len()] != LOG_MAGIC {
+            return None;
+        }
+        let header_len = u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+        if header_len < LOG_HEADER_SIZE {
+            return None;
+        }
+        let mut committed_len = [0u8; 8];
+        committed_len.copy_from_slice(&bytes[COMMITTED_LEN_RANGE]);
+        let mut header = Self {
+            version: u16::from_le_bytes([bytes[4], bytes[5]]),
+            header_len,
+            committed_len: u64::from_le_bytes(committed_len),
+            ..Self::default()
+        };
+        if let Some(fields) = bytes.get(LOG_HEADER_SIZE..header_len) {
+            header.parse_fields(fields);
+        }
+        Some(header)
+    }
+
+    /// 编码文件头，`header_len` 按实际编码的长度计算
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; LOG_HEADER_SIZE];
+        bytes[..LOG_MAGIC.len()].copy_from_slice(LOG_MAGIC);
+        bytes[4..6].copy_from_slice(&self.version.to_le_bytes());
+        bytes[COMMITTED_LEN_RANGE].copy_from_slice(&self.committed_len.to_le_bytes());
+        if let Some(time_zone) = &self.time_zone {
+            push_field(
+                &mut bytes,
+                FIELD_TIME_ZONE,
+                time_zone.to_string().as_bytes(),
+            );
+        }
+        if let Some(window_minutes) = self.window_minutes {
+            push_field(
+                &mut bytes,
+                FIELD_WINDOW_MINUTES,
+                &window_minutes.to_le_bytes(),
+            );
+        }
+        if let Some(cipher) = &self.cipher {
+            push_field(&mut bytes, FIELD_CIPHER, &cipher.encode());
+        }
+        if let Some(encrypted) = self.encrypted {
+            push_field(&mut bytes, FIELD_ENCRYPTED, &[encrypted as u8]);
+        }
+        if let Some(compression) = self.compression {
+            push_field(&mut bytes, FIELD_COMPRESSION, &[compression.as_u8()]);
+        }
+        if let Some(created_at) = self.created_at {
+            push_field(&mut bytes, FIELD_CREATED_AT, &created_at.to_le_bytes());
+        }
+        if let Some(pid) = self.pid {
+            push_field(&mut bytes, FIELD_PID, &pid.to_le_bytes());
+        }
+        for (tag, value) in [
+            (FIELD_SDK_VERSION, &self.sdk_version),
+            (FIELD_SDK_COMMIT, &self.sdk_commit),
+            (FIELD_SDK_TARGET, &self.sdk_target),
+        ] {
+            if let Some(value) = value {
+                push_field(&mut bytes, tag, value.as_bytes());
+            }
+        }
+        let header_len = bytes.len() as u16;
+        bytes[6..8].copy_from_slice(&header_len.to_le_bytes());
+        bytes
+    }
+
+    // 解析扩展字段，字段不完整时忽略剩余部分
+    fn parse_fields(&mut self, mut fields: &[u8]) {
+        while fields.len() >= 3 {
+            let tag = fields[0];
+            let len = u16::from_le_bytes([fields[1], fields[2]]) as usize;
+            let Some(value) = fields.get(3..3 + len) else {
+                return;
+            };
+            match tag {
+                FIELD_TIME_ZONE => {
+                    self.time_zone = std::str::from_utf8(value)
+                        .ok()
+                        .and_then(|name| name.parse().ok());
+                }
+                FIELD_WINDOW_MINUTES => {
+                    self.window_minutes = value.try_into().ok().map(u32::from_le_bytes);
+                }
+                FIELD_CIPHER => self.cipher = CipherParams::decode(value),
+                FIELD_ENCRYPTED => self.encrypted = value.first().map(|&value| value != 0),
+                FIELD_COMPRESSION => {
+                    self.compression = value.first().and_then(|&value| Compression::from_u8(value));
+                }
+                FIELD_CREATED_AT => {
+                    self.created_at = value.try_into().ok().map(i64::from_le_bytes);
+                }
+                FIELD_PID => self.pid = value.try_into().ok().map(u32::from_le_bytes),
+                FIELD_SDK_VERSION => self.sdk_version = utf8_field(value),
+                FIELD_SDK_COMMIT => self.sdk_commit = utf8_field(value),
+                FIELD_SDK_TARGET => self.sdk_target = utf8_field(value),
+                _ => {}
+            }
+            fields = &fields[3 + len..];
+        }
+    }
+}
+
+fn utf8_field(value: &[u8]) -> Option<String> {
+    std::str::from_utf8(value).ok().map(str::to_string)
+}
+
+fn push_field(bytes: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    bytes.push(tag);
+    bytes.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(value);
+}
+
+// 更新文件头中已提交的数据长度，8 字节对齐写入
+pub(crate) fn set_committed_len(header: &mut [u8], committed_len: u64) {
+    header[COMMITTED_LEN_RANGE].copy_from_slice(&committed_len.to_le_bytes());
+}
+
+/// 日志文件中的一条记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LogRecord<'a> {
+    /// 记录 flag，见 `RECORD_FLAG_ENCRYPTED`、`RECORD_FLAG_STRUCTURED`、`RECORD_FLAG_BINARY` 和 `RECORD_FLAG_TIMESTAMP`
+    pub flags: u8,
+    /// 写入时间，毫秒时间戳，旧版本的记录没有写入时间
+    pub timestamp_ms: Option<i64>,
+    /// 记录内容，加密记录为密文，不包括写入时间
+    pub payload: &'a [u8],
+}
+
+impl<'a> LogRecord<'a> {
+    // 从记录的 flag 和完整 payload 解析，带写入时间的记录拆出前 8 字节
+    fn parse(flags: u8, payload: &'a [u8]) -> io::Result<Self> {
+        if flags & RECORD_FLAG_TIMESTAMP == 0 {
+            return Ok(Self {
+                flags,
+                timestamp_ms: None,
+                payload,
+            });
+        }
+        let Some((timestamp, payload)) = payload.split_first_chunk::<RECORD_TIMESTAMP_SIZE>()
+        else {
+            return Err(invalid_record("Record timestamp missing"));
+        };
+        Ok(Self {
+            flags,
+            timestamp_ms: Some(i64::from_le_bytes(*timestamp)),
+            payload,
+        })
+    }
+
+    pub fn is_encrypted(&self) -> bool {
+        self.flags & RECORD_FLAG_ENCRYPTED != 0
+    }
+
+    pub fn is_structured(&self) -> bool {
+        self.flags & RECORD_FLAG_STRUCTURED != 0
+    }
+
+    pub fn is_binary(&self) -> bool {
+        self.flags & RECORD_FLAG_BINARY != 0
+    }
+}
+
+/// 编码一条记录
+pub fn encode_record(flags: u8, payload: &[u8]) -> Vec<u8> {
+    encode_frame(flags, &[], payload)
+}
+
+/// 加密记录的 AEAD 附加数据：记录 flag 和写入时间，修改后无法解密
+pub fn record_aad(flags: u8, timestamp_ms: Option<i64>) -> Vec<u8> {
+    let mut aad = vec![flags];
+    if let Some(timestamp_ms) = timestamp_ms {
+        aad.extend_from_slice(&timestamp_ms.to_le_bytes());
+    }
+    aad
+}
+
+/// 编码一条带写入时间的记录，导出时按毫秒时间过滤
+pub fn encode_timed_record(flags: u8, timestamp_ms: i64, payload: &[u8]) -> Vec<u8> {
+    encode_frame(
+        flags | RECORD_FLAG_TIMESTAMP,
+        &timestamp_ms.to_le_bytes(),
+        payload,
+    )
+}
+
+// 编码记录，payload 为 `prefix` 和 `payload` 拼接
+fn encode_frame(flags: u8, prefix: &[u8], payload: &[u8]) -> Vec<u8> {
+    let len = ((prefix.len() + payload.len()) as u32).to_le_bytes();
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(&len);
+    hasher.update(&[flags]);
+    hasher.update(prefix);
+    hasher.update(payload);
+
+    let mut record = Vec::with_capacity(RECORD_HEADER_SIZE + prefix.len() + payload.len());
+    record.push(RECORD_MARK);
+    record.extend_from_slice(&len);
+    record.push(flags);
+    record.extend_from_slice(&hasher.finalize().to_le_bytes());
+    record.extend_from_slice(prefix);
+    record.extend_from_slice(payload);
+    record
+}
+
+/// 日志文件的内容，包括文件头和已提交的日志数据
+pub struct LogContent {
+    header: Option<LogHeader>,
+    data: Vec<u8>,
+}
+
+impl LogContent {
+    /// 文件头，没有文件头的旧版本文件返回 `None`
+    pub fn header(&self) -> Option<&LogHeader> {
+        self.header.as_ref()
+    }
+
+    /// 已提交的日志数据
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// 日志数据是否为带长度和 CRC 的记录
+    pub fn is_framed(&self) -> bool {
+        self.header.is_some()
+    }
+
+    /// 遍历日志记录
+    ///
+    /// 旧版本文件按 `\n` 分行，每一行作为一条记录，flag 使用 `line_flags`；
+    /// 遇到损坏的记录时返回一次错误，之后不再继续读取
+    pub fn records(&self, line_flags: u8) -> Records<'_> {
+        Records {
+            data: &self.data,
+            framed: self.is_framed(),
+            line_flags,
+        }
+    }
+}
+
+/// 日志记录迭代器
+pub struct Records<'a> {
+    data: &'a [u8],
+    framed: bool,
+    line_flags: u8,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = io::Result<LogRecord<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.framed {
+            self.next_frame()
+        } else {
+            self.next_line()
+        }
+    }
+}
+
+impl<'a> Records<'a> {
+    fn next_line(&mut self) -> Option<io::Result<LogRecord<'a>>> {
+        loop {
+            if self.data.is_empty() {
+                return None;
+            }
+            let end = self
+                .data
+                .iter()
+                .position(|&b| b == b'\n')
+                .unwrap_or(self.data.len());
+            let line = &self.data[..end];
+            self.data = &self.data[(end + 1).min(self.data.len())..];
+            if !line.is_empty() {
+                return Some(Ok(LogRecord {
+                    flags: self.line_flags,
+                    timestamp_ms: None,
+                    payload: line,
+                }));
+            }
+        }
+    }
+
+    fn next_frame(&mut self) -> Option<io::Result<LogRecord<'a>>> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let result = decode_record(self.data);
+        match result {
+            Ok((record, used)) => {
+                self.data = &self.data[used..];
+                Some(Ok(record))
+            }
+            Err(err) => {
+                self.data = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// 逐条读取日志文件中的记录，每次只读取一条记录，不会把整个文件读入内存
+///
+/// 打开时读取文件头，之后最多读取到当时已提交的数据长度，正在写入的文件也可以读取；
+/// 遇到损坏的记录时返回一次错误，之后不再继续读取
+pub struct RecordReader {
+    header: Option<LogHeader>,
+    reader: Take<BufReader<File>>,
+    line_flags: u8,
+    buf: Vec<u8>,
+    done: bool,
+}
+
+impl RecordReader {
+    /// 打开日志文件，旧版本文件按 `\n` 分行，每一行作为一条记录，flag 使用 `line_flags`
+    pub fn open(path: &Path, line_flags: u8) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut fixed = [0u8; LOG_HEADER_SIZE];
+        let read = read_up_to(&mut file, &mut fixed)?;
+        let (header, data_len) = match LogHeader::parse(&fixed[..read]) {
+            Some(header) => {
+                let header = read_header_fields(&mut file, header)?;
+                // 文件可能被截断，最多读到文件末尾
+                let available = file_len.saturating_sub(header.header_len as u64);
+                let data_len = header.committed_len.min(available);
+                (Some(header), data_len)
+            }
+            None => (None, file_len),
+        };
+        let data_start = header.as_ref().map_or(0, |header| header.header_len as u64);
+        file.seek(SeekFrom::Start(data_start))?;
+        Ok(Self {
+            header,
+            reader: BufReader::new(file).take(data_len),
+            line_flags,
+            buf: Vec::new(),
+            done: false,
+        })
+    }
+
+    /// 文件头，没有文件头的旧版本文件返回 `None`
+    pub fn header(&self) -> Option<&LogHeader> {
+        self.header.as_ref()
+    }
+
+    /// 读取下一条记录，没有更多记录时返回 `None`
+    pub fn next_record(&mut self) -> Option<io::Result<LogRecord<'_>>> {
+        if self.done {
+            return None;
+        }
+        let result = if self.header.is_some() {
+            self.read_frame()
+        } else {
+            self.read_line()
+        };
+        match result {
+            Ok(Some(flags)) => Some(LogRecord::parse(flags, &self.buf)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+
+    // 读取一条带长度和 CRC 的记录到 `buf`，返回记录 flag
+    fn read_frame(&mut self) -> io::Result<Option<u8>> {
+        let mut header = [0u8; RECORD_HEADER_SIZE];
+        match self.reader.read(&mut header[..1])? {
+            0 => return Ok(None),
+            _ => self
+                .reader
+                .read_exact(&mut header[1..])
+                .map_err(|_| invalid_record("Invalid record header"))?,
+        }
+        if header[0] != RECORD_MARK {
+            return Err(invalid_record("Invalid record header"));
+        }
+        let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as u64;
+        if len > self.reader.limit() {
+            return Err(invalid_record("Record length exceeds data"));
+        }
+        self.buf.resize(len as usize, 0);
+        self.reader.read_exact(&mut self.buf)?;
+
+        let crc = u32::from_le_bytes([header[6], header[7], header[8], header[9]]);
+        if record_crc(&header[1..6], &self.buf) != crc {
+            return Err(invalid_record("Record CRC mismatch"));
+        }
+        Ok(Some(header[5]))
+    }
+
+    // 读取旧版本文件的一行到 `buf`，不包括 `\n`，跳过空行；文件末尾 mmap 未填充的 0x00 不计入
+    fn read_line(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            self.buf.clear();
+            // 还不确定是否为末尾填充的 0x00 个数，之后还有数据时补回
+            let mut zeros = 0;
+            let mut line_end = false;
+            while !line_end {
+                let available = self.reader.fill_buf()?;
+                if available.is_empty() {
+                    break;
+                }
+                let mut used = 0;
+                for &byte in available {
+                    used += 1;
+                    if byte == 0 {
+                        zeros += 1;
+                        continue;
+                    }
+                    self.buf.resize(self.buf.len() + zeros, 0);
+                    zeros = 0;
+                    if byte == b'\n' {
+                        line_end = true;
+                        break;
+                    }
+                    self.buf.push(byte);
+                }
+                self.reader.consume(used);
+            }
+            if !self.buf.is_empty() {
+                return Ok(Some(self.line_flags));
+            }
+            if !line_end {
+                return Ok(None);
+            }
+        }
+    }
+}
+
+// 解码一条记录，返回记录和占用的字节数
+pub(crate) fn decode_record(data: &[u8]) -> io::Result<(LogRecord<'_>, usize)> {
+    if data.len() < RECORD_HEADER_SIZE || data[0] != RECORD_MARK {
+        return Err(invalid_record("Invalid record header"));
+    }
+    let len = u32::from_le_bytes([data[1], data[2], data[3], data[4]]) as usize;
+    let flags = data[5];
+    let crc = u32::from_le_bytes([data[6], data[7], data[8], data[9]]);
+    let end = RECORD_HEADER_SIZE
+        .checked_add(len)
+        .filter(|&end| end <= data.len())
+        .ok_or_else(|| invalid_record("Record length exceeds data"))?;
+    let payload = &data[RECORD_HEADER_SIZE..end];
+
+    if record_crc(&data[1..6], payload) != crc {
+        return Err(invalid_record("Record CRC mismatch"));
+    }
+    Ok((LogRecord::parse(flags, payload)?, end))
+}
+
+// 记录的 CRC32，覆盖 payload 长度、flags 和 payload
+fn record_crc(len_and_flags: &[u8], payload: &[u8]) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(len_and_flags);
+    hasher.update(payload);
+    hasher.finalize()
+}
+
+fn invalid_record(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// 读取日志文件中的日志数据，兼容没有文件头的旧版本文件
+pub fn read_log_data(path: &Path) -> io::Result<Vec<u8>> {
+    Ok(read_log_file(path)?.data)
+}
+
+/// 读取日志文件的文件头和日志数据，兼容没有文件头的旧版本文件
+pub fn read_log_file(path: &Path) -> io::Result<LogContent> {
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+
+    let mut header = [0u8; LOG_HEADER_SIZE];
+    let header_read = read_up_to(&mut file, &mut header)?;
+    match LogHeader::parse(&header[..header_read]) {
+        Some(header) => {
+            let header = read_header_fields(&mut file, header)?;
+            // 文件可能被截断，最多读到文件末尾
+            let available = file_len.saturating_sub(header.header_len as u64);
+            let len = header.committed_len.min(available) as usize;
+            let mut data = vec![0u8; len];
+            file.seek(SeekFrom::Start(header.header_len as u64))?;
+            file.read_exact(&mut data)?;
+            Ok(LogContent {
+                header: Some(header),
+                data,
+            })
+        }
+        None => {
+            let mut data = header[..header_read].to_vec();
+            file.read_to_end(&mut data)?;
+            let len = first_zero_pos(&data);
+            data.truncate(len);
+            Ok(LogContent { header: None, data })
+        }
+    }
+}
+
+/// 读取日志文件头，没有文件头的旧版本文件返回 `None`
+pub fn read_log_header(path: &Path) -> io::Result<Option<LogHeader>> {
+    let mut file = File::open(path)?;
+    let mut header = [0u8; LOG_HEADER_SIZE];
+    let read = read_up_to(&mut file, &mut header)?;
+    match LogHeader::parse(&header[..read]) {
+        Some(header) => Ok(Some(read_header_fields(&mut file, header)?)),
+        None => Ok(None),
+    }
+}
+
+// 已读取固定部分的文件头，继续读取扩展字段
+fn read_header_fields(file: &mut File, header: LogHeader) -> io::Result<LogHeader> {
+    if header.header_len == LOG_HEADER_SIZE {
+        return Ok(header);
+    }
+    let mut bytes = vec![0u8; header.header_len];
+    file.seek(SeekFrom::Start(0))?;
+    let read = read_up_to(file, &mut bytes)?;
+    Ok(LogHeader::parse(&bytes[..read]).unwrap_or(header))
+}
+
+// 已存在且没有文件头、按行分隔的旧版本日志文件
+pub(crate) fn needs_migration(path: &Path) -> io::Result<bool> {
+    if !path.exists() || fs::metadata(path)?.len() == 0 {
+        return Ok(false);
+    }
+    let mut header = [0u8; LOG_HEADER_SIZE];
+    let read = read_up_to(&mut File::open(path)?, &mut header)?;
+    Ok(LogHeader::parse(&header[..read]).is_none())
+}
+
+// 把按行分隔的旧版本文件转换为当前的记录格式，便于继续追加，每一行的 flag 使用 `line_flags`，
+// 文件头使用 `header`，其中的数据长度按转换后的数据计算
+pub(crate) fn migrate_legacy(path: &Path, line_flags: u8, header: LogHeader) -> io::Result<()> {
+    let content = read_log_file(path)?;
+    rewrite_lines_as_records(path, &content.data, line_flags, header)
+}
+
+// 日志文件尾部写入不完整、被丢弃的数据
+pub(crate) struct TornTail {
+    /// 丢弃的字节数
+    pub(crate) discarded: usize,
+    /// 丢弃的数据保存的位置
+    pub(crate) quarantine_path: PathBuf,
+}
+
+// 检查日志文件尾部是否有写入不完整的数据，有则截断到最后一条完整的记录，
+// 丢弃的数据追加保存到 `<文件名>.torn`，便于排查；旧版本文件截断后转换为记录格式，文件头使用 `legacy_header`
+pub(crate) fn recover_torn_tail(
+    path: &Path,
+    line_flags: u8,
+    legacy_header: LogHeader,
+) -> io::Result<Option<TornTail>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = read_log_file(path)?;
+    let is_framed = content.is_framed();
+    let valid_len = if is_framed {
+        framed_valid_len(&content.data)
+    } else {
+        lines_valid_len(&content.data)
+    };
+    let torn = &content.data[valid_len..];
+    if torn.is_empty() {
+        return Ok(None);
+    }
+
+    let quarantine_path = append_extension(path, "torn");
+    let mut quarantine = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&quarantine_path)?;
+    quarantine.write_all(torn)?;
+    quarantine.sync_all()?;
+
+    match content.header.as_ref().filter(|_| is_framed) {
+        Some(header) => {
+            // 清空不完整的数据，再更新文件头中的数据长度
+            let mut file = OpenOptions::new().write(true).open(path)?;
+            file.seek(SeekFrom::Start((header.header_len + valid_len) as u64))?;
+            file.write_all(&vec![0u8; torn.len()])?;
+            file.seek(SeekFrom::Start(COMMITTED_LEN_RANGE.start as u64))?;
+            file.write_all(&(valid_len as u64).to_le_bytes())?;
+            file.sync_all()?;
+        }
+        None => {
+            rewrite_lines_as_records(path, &content.data[..valid_len], line_flags, legacy_header)?
+        }
+    }
+
+    Ok(Some(TornTail {
+        discarded: torn.len(),
+        quarantine_path,
+    }))
+}
+
+// 连续的完整记录的总长度
+fn framed_valid_len(data: &[u8]) -> usize {
+    let mut pos = 0;
+    while pos < data.len() {
+        match decode_record(&data[pos..]) {
+            Ok((_, used)) => pos += used,
+            Err(_) => break,
+        }
+    }
+    pos
+}
+
+// 旧版本每一行都以 `\n` 结尾，最后一个 `\n` 之后的数据是写入不完整的行
+fn lines_valid_len(data: &[u8]) -> usize {
+    data.iter()
+        .rposition(|&b| b == b'\n')
+        .map_or(0, |pos| pos + 1)
+}
+
+// 把按行分隔的数据编码为记录，和文件头 `header` 一起写入新的文件替换 `path`
+fn rewrite_lines_as_records(
+    path: &Path,
+    lines: &[u8],
+    line_flags: u8,
+    header: LogHeader,
+) -> io::Result<()> {
+    let content = LogContent {
+        header: None,
+        data: lines.to_vec(),
+    };
+    let mut data = Vec::with_capacity(lines.len());
+    for record in content.records(line_flags) {
+        let record = record?;
+        data.extend_from_slice(&encode_record(record.flags, record.payload));
+    }
+    let header = LogHeader {
+        committed_len: data.len() as u64,
+        ..header
+    };
+
+    let tmp_path = append_extension(path, "tmp");
+    {
+        let mut tmp = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp_path)?;
+        tmp.write_all(&header.encode())?;
+        tmp.write_all(&data)?;
+        tmp.sync_all()?;
+    }
+    fs::rename(tmp_path, path)
+}
+
+// 在文件名后追加扩展名，例如 `a.log` -> `a.log.tmp`
+fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+// 旧版本文件 mmap 未填充完成的部分为 0x00，倒查 0x00 第一个位置
+fn first_zero_pos(buffer: &[u8]) -> usize {
+    let mut pos = buffer.len();
+    while pos > 0 && buffer[pos - 1] == 0 {
+        pos -= 1;
+    }
+    pos
+}
+
+fn read_up_to(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match file.read(&mut buf[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+    Ok(read)
+}
@@ -0,0 +1,439 @@
+use crate::encrypt_util::{CipherParams, LogCipher};
+use crate::log_file::{read_log_header, LogRecord, RecordReader, RECORD_FLAG_ENCRYPTED};
+use crate::mmap_config::MmapConfig;
+use crate::naming::{known_templates, LogFileName};
+use crate::record::{LineFormat, Record};
+use crate::rotation::LogWindow;
+use crate::time_zone::LogTimeZone;
+use chrono::{DateTime, TimeZone, Utc};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io;
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// 按时间范围读取 `base_dir` 下的日志，不需要创建 writer
+///
+/// 日志文件按 `config` 中的路径模板、日志流和是否加密查找，按文件头中的加密参数解密。
+/// 按时间周期逐个打开文件，每次只读取和解密一条记录，正在写入的文件读取打开时已经刷新的部分
+pub struct LogReader {
+    base_dir: PathBuf,
+    config: MmapConfig,
+}
+
+/// 读取到的一条日志
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LogEntry {
+    /// `write` / `write_bytes` 写入的日志
+    Text(Vec<u8>),
+    /// `write_record` 写入的结构化日志
+    Record(Record),
+    /// 无法解密的加密记录，例如公钥加密的日志、缺少对应的密钥或密文被篡改，内容为十六进制密文
+    Encrypted(Vec<u8>),
+    /// 无法解析的结构化日志，内容为解密后的 payload
+    Corrupted(Vec<u8>),
+}
+
+/// 导出日志的统计
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    /// 读取的日志文件数
+    pub files: usize,
+    /// 写入的行数，包括无法解密和无法解析的记录
+    pub lines: usize,
+    /// 写入的字节数，包括换行
+    pub bytes: u64,
+    /// 无法解密的记录数，这些记录输出十六进制密文
+    pub decrypt_failures: usize,
+    /// 无法解析的记录数，包括原样输出的结构化日志和损坏后跳过的文件剩余部分
+    pub decode_failures: usize,
+}
+
+impl LogEntry {
+    /// 导出时输出的一行，不包括换行，结构化日志按 `format` 输出
+    pub fn render(&self, format: &LineFormat, time_zone: &LogTimeZone) -> Cow<'_, [u8]> {
+        match self {
+            LogEntry::Record(record) => Cow::Owned(format.render(record, time_zone).into_bytes()),
+            LogEntry::Text(bytes) | LogEntry::Encrypted(bytes) | LogEntry::Corrupted(bytes) => {
+                Cow::Borrowed(bytes)
+            }
+        }
+    }
+}
+
+impl LogReader {
+    pub fn new(base_dir: &Path, config: MmapConfig) -> Self {
+        Self {
+            base_dir: base_dir.to_path_buf(),
+            config,
+        }
+    }
+
+    /// 与时间范围 `[start_ms, end_ms]` 有交集的日志文件，按所属时间周期的先后排序，
+    /// 同一周期的文件按进程、writer 序号和分片序号排序
+    ///
+    /// 每个文件按文件头中记录的时区和周期长度换算时间范围，修改时区或切分策略前写入的日志也能找到
+    pub fn files(&self, start_ms: i64, end_ms: i64) -> io::Result<Vec<PathBuf>> {
+        let files = self.timed_files(start_ms, end_ms)?;
+        Ok(files.into_iter().map(|file| file.path).collect())
+    }
+
+    // 与 `files` 相同，同时返回每个文件的时间范围
+    fn timed_files(&self, start_ms: i64, end_ms: i64) -> io::Result<Vec<TimedFile>> {
+        let start = utc_of(start_ms, "Invalid start timestamp")?;
+        let end = utc_of(end_ms, "Invalid end timestamp")?;
+        // 文件名中是本地日期，和 UTC 日期最多相差一天
+        let first_date = start.date_naive() - chrono::Duration::days(1);
+        let last_date = end.date_naive() + chrono::Duration::days(1);
+
+        let mut files = vec![];
+        for (filepath, file_name) in find_log_files(&self.base_dir, &self.config)? {
+            if file_name.is_encrypt != self.config.is_encrypt()
+                || file_name.stream != self.config.get_stream_name()
+                || !(first_date..=last_date).contains(&file_name.date)
+            {
+                continue;
+            }
+            let Some((file_start, file_end)) = file_time_range(&filepath, &file_name)? else {
+                continue;
+            };
+            if file_start <= end && file_end > start {
+                let order = (file_name.pid, file_name.slot, file_name.part);
+                files.push((file_start, order, file_end, filepath));
+            }
+        }
+        files.sort();
+        Ok(files
+            .into_iter()
+            .map(|(start, _, end, path)| TimedFile { start, end, path })
+            .collect())
+    }
+
+    // 按时间范围有重叠的文件分组，例如多个进程或 writer 同一周期的文件，组内的日志按写入时间合并
+    fn file_groups(&self, start_ms: i64, end_ms: i64) -> io::Result<VecDeque<Vec<PathBuf>>> {
+        let mut groups: VecDeque<Vec<PathBuf>> = VecDeque::new();
+        let mut group_end = None;
+        for file in self.timed_files(start_ms, end_ms)? {
+            match groups.back_mut() {
+                Some(group) if group_end.is_some_and(|group_end| file.start < group_end) => {
+                    group.push(file.path)
+                }
+                _ => groups.push_back(vec![file.path]),
+            }
+            group_end = group_end.max(Some(file.end));
+        }
+        Ok(groups)
+    }
+
+    /// 逐条读取时间范围 `[start_ms, end_ms]` 内的日志
+    ///
+    /// 记录了写入时间的日志和结构化日志按毫秒精确过滤，旧版本没有写入时间的日志按所在文件的时间周期过滤。
+    /// 同一时间周期有多个文件（多个进程或 writer 写入）时，按写入时间合并输出，写入时间相同时按文件顺序。
+    /// 文件中的记录损坏时返回一次 `InvalidData` 错误，跳过该文件剩余部分后继续读取其他文件
+    pub fn entries(&self, start_ms: i64, end_ms: i64) -> io::Result<LogEntries<'_>> {
+        Ok(LogEntries {
+            reader: self,
+            groups: self.file_groups(start_ms, end_ms)?,
+            range: start_ms..=end_ms,
+            current: vec![],
+            errors: VecDeque::new(),
+            visited: 0,
+            ciphers: HashMap::new(),
+        })
+    }
+
+    /// 把时间范围 `[start_ms, end_ms]` 内的日志逐行写入 `out`，写完后刷新 `out`
+    ///
+    /// 结构化日志按 `MmapConfig::set_line_format` 的格式和配置的时区输出，无法解密的记录输出十六进制密文，
+    /// 无法解析的结构化日志原样输出，记录损坏时跳过该文件剩余部分。
+    /// `MmapConfig::set_export_format` 设为 `ExportFormat::Escaped` 时每条日志恰好一行
+    pub fn export_to<W: Write>(
+        &self,
+        start_ms: i64,
+        end_ms: i64,
+        out: W,
+    ) -> io::Result<ExportSummary> {
+        self.entries(start_ms, end_ms)?.export_to(out)
+    }
+}
+
+/// `LogReader::entries` 返回的迭代器
+pub struct LogEntries<'a> {
+    reader: &'a LogReader,
+    groups: VecDeque<Vec<PathBuf>>,
+    range: RangeInclusive<i64>,
+    // 正在合并读取的同一组文件
+    current: Vec<OpenFile>,
+    // 打开或读取文件时的错误，在之后的日志之前返回
+    errors: VecDeque<io::Error>,
+    visited: usize,
+    // 按加密参数（包括密钥 id）缓存派生的 cipher，同一个 writer 写入的多个文件只派生一次密钥
+    ciphers: HashMap<CipherParams, Option<Arc<LogCipher>>>,
+}
+
+impl LogEntries<'_> {
+    /// 已经打开的日志文件数
+    pub fn files_visited(&self) -> usize {
+        self.visited
+    }
+
+    /// 把剩余的日志逐行写入 `out`，见 `LogReader::export_to`
+    pub fn export_to<W: Write>(mut self, mut out: W) -> io::Result<ExportSummary> {
+        let line_format = self.reader.config.get_line_format();
+        let export_format = self.reader.config.get_export_format();
+        let time_zone = self.reader.config.get_time_zone();
+        let mut summary = ExportSummary::default();
+        for entry in self.by_ref() {
+            let entry = match entry {
+                Ok(entry) => entry,
+                // 记录损坏时，之后的数据无法定位，跳过该文件剩余部分
+                Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+                    summary.decode_failures += 1;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            match entry {
+                LogEntry::Encrypted(_) => summary.decrypt_failures += 1,
+                LogEntry::Corrupted(_) => summary.decode_failures += 1,
+                LogEntry::Text(_) | LogEntry::Record(_) => {}
+            }
+            let line = export_format.apply(entry.render(line_format, &time_zone));
+            out.write_all(&line)?;
+            out.write_all(b"\n")?;
+            summary.lines += 1;
+            summary.bytes += line.len() as u64 + 1;
+        }
+        out.flush()?;
+        summary.files = self.visited;
+        Ok(summary)
+    }
+
+    // 打开日志文件，并按文件头中的加密参数准备解密使用的 cipher
+    fn open(&mut self, path: &Path) -> io::Result<OpenFile> {
+        let config = &self.reader.config;
+        let records = RecordReader::open(path, line_flags(config))?;
+        // 没有记录加密参数的旧版本文件为 AES-128-ECB，公钥加密的文件在设备上无法解密
+        let params = records
+            .header()
+            .and_then(|header| header.cipher.clone())
+            .unwrap_or_default();
+        let cipher = self
+            .ciphers
+            .entry(params)
+            .or_insert_with_key(|params| {
+                if params.is_public_key() {
+                    return None;
+                }
+                let app_key = app_key_for(config, params)?;
+                LogCipher::new(params.clone(), app_key).ok().map(Arc::new)
+            })
+            .clone();
+        Ok(OpenFile {
+            records,
+            cipher,
+            next: None,
+        })
+    }
+}
+
+// 日志文件和它的时间周期
+struct TimedFile {
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    path: PathBuf,
+}
+
+// 正在读取的日志文件
+struct OpenFile {
+    records: RecordReader,
+    cipher: Option<Arc<LogCipher>>,
+    // 已读取、还没有输出的下一条记录
+    next: Option<PendingRecord>,
+}
+
+// 从文件中读取的一条记录，合并多个文件时暂存
+struct PendingRecord {
+    flags: u8,
+    timestamp_ms: Option<i64>,
+    payload: Vec<u8>,
+}
+
+impl OpenFile {
+    // 读取下一条写入时间在 `range` 内的记录，写入时间是明文，不在范围内的记录不需要解密
+    fn read_next(&mut self, range: &RangeInclusive<i64>) -> Option<io::Result<PendingRecord>> {
+        loop {
+            match self.records.next_record()? {
+                Ok(record) => {
+                    if record
+                        .timestamp_ms
+                        .is_some_and(|timestamp_ms| !range.contains(&timestamp_ms))
+                    {
+                        continue;
+                    }
+                    return Some(Ok(PendingRecord {
+                        flags: record.flags,
+                        timestamp_ms: record.timestamp_ms,
+                        payload: record.payload.to_vec(),
+                    }));
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+impl Iterator for LogEntries<'_> {
+    type Item = io::Result<LogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(err) = self.errors.pop_front() {
+                return Some(Err(err));
+            }
+            if self.current.is_empty() {
+                for path in self.groups.pop_front()? {
+                    match self.open(&path) {
+                        Ok(file) => {
+                            self.visited += 1;
+                            self.current.push(file);
+                        }
+                        Err(err) => self.errors.push_back(err),
+                    }
+                }
+                continue;
+            }
+
+            // 每个文件读取下一条记录，读完或记录损坏的文件不再读取
+            let mut i = 0;
+            while i < self.current.len() {
+                let file = &mut self.current[i];
+                if file.next.is_none() {
+                    match file.read_next(&self.range) {
+                        Some(Ok(record)) => file.next = Some(record),
+                        Some(Err(err)) => {
+                            self.current.remove(i);
+                            self.errors.push_back(err);
+                            continue;
+                        }
+                        None => {
+                            self.current.remove(i);
+                            continue;
+                        }
+                    }
+                }
+                i += 1;
+            }
+
+            // 输出写入时间最早的一条，没有写入时间的旧版本记录最先输出
+            let Some(file) = self
+                .current
+                .iter_mut()
+                .min_by_key(|file| file.next.as_ref().map(|record| record.timestamp_ms))
+            else {
+                continue;
+            };
+            let Some(record) = file.next.take() else {
+                continue;
+            };
+            let record = LogRecord {
+                flags: record.flags,
+                timestamp_ms: record.timestamp_ms,
+                payload: &record.payload,
+            };
+            match to_entry(file.cipher.as_deref(), &record) {
+                // 没有记录写入时间的结构化日志按日志中的时间过滤
+                LogEntry::Record(structured) if !self.range.contains(&structured.timestamp_ms) => {}
+                entry => return Some(Ok(entry)),
+            }
+        }
+    }
+}
+
+// 解密并解析一条记录
+fn to_entry(cipher: Option<&LogCipher>, record: &LogRecord) -> LogEntry {
+    let payload = if record.is_encrypted() {
+        let decrypted = cipher.and_then(|cipher| cipher.decrypt_record(record).ok());
+        match decrypted {
+            Some(decrypted) => decrypted,
+            // 二进制密文转为十六进制，与旧版本的输出一致，也不会出现换行
+            None if record.is_binary() => {
+                return LogEntry::Encrypted(hex::encode(record.payload).into_bytes())
+            }
+            None => return LogEntry::Encrypted(record.payload.to_vec()),
+        }
+    } else {
+        record.payload.to_vec()
+    };
+    if !record.is_structured() {
+        return LogEntry::Text(payload);
+    }
+    match Record::decode(&payload) {
+        Ok(structured) => LogEntry::Record(structured),
+        Err(_) => LogEntry::Corrupted(payload),
+    }
+}
+
+fn utc_of(timestamp_ms: i64, msg: &str) -> io::Result<DateTime<Utc>> {
+    Utc.timestamp_millis_opt(timestamp_ms)
+        .single()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, msg))
+}
+
+// 解密使用的 `app_key`，记录了密钥 id 的文件从密钥环中查找，找不到时返回 `None`
+fn app_key_for<'a>(config: &'a MmapConfig, params: &CipherParams) -> Option<&'a str> {
+    match &params.key_id {
+        Some(key_id) => config.get_keyring()?.get(key_id),
+        None => Some(config.get_app_key()),
+    }
+}
+
+// 旧版本按行分隔的日志文件中，每一行的记录 flag
+pub(crate) fn line_flags(config: &MmapConfig) -> u8 {
+    if config.is_encrypt() {
+        RECORD_FLAG_ENCRYPTED
+    } else {
+        0
+    }
+}
+
+// 按当前模板和各个默认模板查找日志文件，修改模板或切分策略前写入的文件也能找到
+pub(crate) fn find_log_files(
+    base_dir: &Path,
+    config: &MmapConfig,
+) -> io::Result<Vec<(PathBuf, LogFileName)>> {
+    let mut found = HashSet::new();
+    let mut files = vec![];
+    for template in known_templates(&config.get_naming_template()) {
+        for (path, file_name) in template.find_files(base_dir)? {
+            if found.insert(path.clone()) {
+                files.push((path, file_name));
+            }
+        }
+    }
+    Ok(files)
+}
+
+// 日志文件覆盖的 UTC 时间范围，按文件头中记录的时区和周期长度换算，夏令时跳过的时间返回 `None`
+pub(crate) fn file_time_range(
+    path: &Path,
+    file_name: &LogFileName,
+) -> io::Result<Option<(DateTime<Utc>, DateTime<Utc>)>> {
+    let window = LogWindow::of_file_name(file_name);
+    let header = read_log_header(path)?.unwrap_or_default();
+    let time_zone = header.time_zone.unwrap_or_default();
+    let minutes = chrono::Duration::minutes(header.window_minutes.unwrap_or(window.minutes) as i64);
+
+    let Some(local_start) = window.start() else {
+        return Ok(None);
+    };
+    let Some(start) = time_zone.to_utc(&local_start) else {
+        return Ok(None);
+    };
+    let end = time_zone
+        .to_utc(&(local_start + minutes))
+        .unwrap_or(start + minutes);
+    Ok(Some((start, end)))
+}
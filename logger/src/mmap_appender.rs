@@ -0,0 +1,154 @@
+use crate::log_file::{set_committed_len, LogHeader, LOG_HEADER_SIZE};
+use memmap2::{MmapMut, MmapOptions};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+// 按文件当前大小成倍扩展时，每次最多扩展 8 MB，单次扩展过大时写入反而变慢
+const MAX_GEOMETRIC_GROW: u64 = 8 * 1024 * 1024;
+
+/// 正在写入的日志文件
+///
+/// 只映射文件头和末尾的写入窗口，追加位置保存在内存中。空间不足时按 `grow_size` 扩展文件，
+/// 再从追加位置开始重新映射，不需要映射整个文件。
+pub(crate) struct MmapAppender {
+    file: File,
+    header: MmapMut,        // 文件头的固定部分，用于更新已提交的数据长度
+    tail: Option<MmapMut>,  // 从 tail_offset 到文件末尾的写入窗口
+    tail_offset: u64,       // 写入窗口在文件中的起始位置
+    file_len: u64,          // 文件当前大小，包括预留的空间
+    header_len: u64,        // 文件头长度，日志数据从该位置开始
+    committed_len: u64,     // 已提交的日志数据长度
+    grow_size: Option<u64>, // 每次扩展的大小，未设置时按文件当前大小成倍扩展
+    min_grow_size: u64,     // 成倍扩展时每次至少扩展的大小
+}
+
+impl MmapAppender {
+    // 打开日志文件，空文件先写入 `new_header`
+    pub(crate) fn open(
+        path: &Path,
+        new_header: &[u8],
+        grow_size: Option<u64>,
+        min_grow_size: u64,
+    ) -> io::Result<Self> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        if file.metadata()?.len() == 0 {
+            file.write_all(new_header)?;
+            file.sync_data()?;
+        }
+        let file_len = file.metadata()?.len();
+        if file_len < LOG_HEADER_SIZE as u64 {
+            return Err(invalid_header("Invalid log file header"));
+        }
+
+        let header = unsafe { MmapOptions::new().len(LOG_HEADER_SIZE).map_mut(&file)? };
+        let parsed =
+            LogHeader::parse(&header).ok_or_else(|| invalid_header("Invalid log file header"))?;
+        let header_len = parsed.header_len as u64;
+        if header_len + parsed.committed_len > file_len {
+            return Err(invalid_header("Committed length exceeds log file size"));
+        }
+
+        Ok(Self {
+            file,
+            header,
+            tail: None,
+            tail_offset: 0,
+            file_len,
+            header_len,
+            committed_len: parsed.committed_len,
+            grow_size,
+            min_grow_size,
+        })
+    }
+
+    // 已提交的日志数据长度
+    pub(crate) fn committed_len(&self) -> u64 {
+        self.committed_len
+    }
+
+    // 追加数据，先写入数据再更新文件头中的数据长度，崩溃时不会读到未写完的数据
+    pub(crate) fn append(&mut self, data: &[u8]) -> io::Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        let pos = self.header_len + self.committed_len;
+        let end = pos + data.len() as u64;
+        if self.tail.is_none() || end > self.file_len {
+            self.remap_tail(pos, end)?;
+        }
+        let tail = self
+            .tail
+            .as_mut()
+            .ok_or_else(|| io::Error::other("Log file tail is not mapped"))?;
+
+        let start = (pos - self.tail_offset) as usize;
+        tail[start..start + data.len()].copy_from_slice(data);
+        tail.flush_range(start, data.len())?;
+
+        self.committed_len += data.len() as u64;
+        set_committed_len(&mut self.header, self.committed_len);
+        self.header.flush()
+    }
+
+    // 同步到磁盘，并截断末尾预留的空间
+    pub(crate) fn seal(self) -> io::Result<()> {
+        let Self {
+            file,
+            header,
+            tail,
+            header_len,
+            committed_len,
+            ..
+        } = self;
+        if let Some(tail) = &tail {
+            tail.flush()?;
+        }
+        header.flush()?;
+        // 截断前先解除映射
+        drop(tail);
+        drop(header);
+        file.set_len(header_len + committed_len)?;
+        file.sync_all()
+    }
+
+    // 确保文件至少有 `end` 字节，从 `pos` 开始重新映射写入窗口
+    fn remap_tail(&mut self, pos: u64, end: u64) -> io::Result<()> {
+        if end > self.file_len {
+            let grow_size = self.grow_size.unwrap_or_else(|| {
+                self.file_len.clamp(
+                    self.min_grow_size,
+                    MAX_GEOMETRIC_GROW.max(self.min_grow_size),
+                )
+            });
+            let new_len = end + grow_size;
+            if let Some(tail) = self.tail.take() {
+                tail.flush()?;
+            }
+            self.file.set_len(new_len)?;
+            self.file_len = new_len;
+        }
+
+        let len = (self.file_len - pos) as usize;
+        let tail = unsafe {
+            MmapOptions::new()
+                .offset(pos)
+                .len(len)
+                .map_mut(&self.file)?
+        };
+        self.tail = Some(tail);
+        self.tail_offset = pos;
+        Ok(())
+    }
+}
+
+fn invalid_header(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
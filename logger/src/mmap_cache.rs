@@ -0,0 +1,177 @@
+use memmap2::MmapMut;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+// 缓存文件头部布局：
+// [0..4)   magic
+// [4]      版本号
+// [5..8)   保留
+// [8..16)  已缓存数据长度，u64 小端
+// [16..24) 第一条缓存数据的写入时间，毫秒时间戳，i64 小端
+// [24..26) 加密参数长度，u16 小端
+// [26..34) 已经写入日志文件的缓存数据长度，u64 小端
+// [34..42) 正在写入日志文件的一段缓存数据的结束位置，u64 小端，没有时不大于已写入的长度
+// [42..50) 写入这一段数据之前，日志文件已提交的数据长度，u64 小端
+// [50..256) 缓存数据的加密参数，写入日志文件头，未加密时为空
+const MAGIC: &[u8; 4] = b"MLCH";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 256;
+const LEN_RANGE: Range<usize> = 8..16;
+const FIRST_WRITE_RANGE: Range<usize> = 16..24;
+const PARAMS_LEN_RANGE: Range<usize> = 24..26;
+const FLUSHED_RANGE: Range<usize> = 26..34;
+const PENDING_END_RANGE: Range<usize> = 34..42;
+const PENDING_FILE_LEN_RANGE: Range<usize> = 42..50;
+const PARAMS_START: usize = 50;
+/// 缓存文件头中可以记录的加密参数的最大长度
+pub(crate) const MAX_PARAMS_LEN: usize = HEADER_SIZE - PARAMS_START;
+
+/// mmap 映射的日志缓存区
+///
+/// 写入的日志先放在缓存文件里，进程崩溃或被杀掉时内存页仍由系统写回文件，
+/// 下次启动时可以从缓存文件中找回还没有刷新到日志文件的内容。
+pub(crate) struct MmapCache {
+    file: File,
+    mmap: MmapMut,
+}
+
+impl MmapCache {
+    // 打开或创建缓存文件，文件头无效时按空缓存处理
+    pub(crate) fn open(path: &Path, capacity: usize) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+
+        let size = (HEADER_SIZE + capacity) as u64;
+        if file.metadata()?.len() < size {
+            file.set_len(size)?;
+        }
+
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        let mut cache = Self { file, mmap };
+        if !cache.is_valid() {
+            cache.mmap[..HEADER_SIZE].fill(0);
+            cache.mmap[..MAGIC.len()].copy_from_slice(MAGIC);
+            cache.mmap[MAGIC.len()] = VERSION;
+        }
+        Ok(cache)
+    }
+
+    // 已缓存的数据长度
+    pub(crate) fn len(&self) -> usize {
+        read_u64(&self.mmap[LEN_RANGE]) as usize
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // 可缓存的数据长度
+    pub(crate) fn capacity(&self) -> usize {
+        self.mmap.len() - HEADER_SIZE
+    }
+
+    // 第一条缓存数据的写入时间，用于确定这些数据属于哪个日志文件
+    pub(crate) fn first_write_ms(&self) -> i64 {
+        read_u64(&self.mmap[FIRST_WRITE_RANGE]) as i64
+    }
+
+    // 已缓存的数据
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.mmap[HEADER_SIZE..HEADER_SIZE + self.len()]
+    }
+
+    // 已经写入日志文件的缓存数据长度，之前的数据刷新时跳过
+    pub(crate) fn flushed(&self) -> usize {
+        (read_u64(&self.mmap[FLUSHED_RANGE]) as usize).min(self.len())
+    }
+
+    // 上次进程正在写入日志文件的一段缓存数据，以及写入前日志文件已提交的数据长度
+    pub(crate) fn pending_flush(&self) -> Option<(Range<usize>, u64)> {
+        let start = self.flushed();
+        let end = (read_u64(&self.mmap[PENDING_END_RANGE]) as usize).min(self.len());
+        (end > start).then(|| (start..end, read_u64(&self.mmap[PENDING_FILE_LEN_RANGE])))
+    }
+
+    // 开始把 `[flushed, end)` 的缓存数据追加到已提交 `file_len` 字节的日志文件，
+    // 崩溃后可以据此判断这段数据是否已经写入
+    pub(crate) fn begin_flush(&mut self, end: usize, file_len: u64) {
+        self.mmap[PENDING_FILE_LEN_RANGE].copy_from_slice(&file_len.to_le_bytes());
+        self.mmap[PENDING_END_RANGE].copy_from_slice(&(end as u64).to_le_bytes());
+    }
+
+    // `end` 之前的缓存数据已经写入日志文件
+    pub(crate) fn finish_flush(&mut self, end: usize) {
+        self.mmap[FLUSHED_RANGE].copy_from_slice(&(end as u64).to_le_bytes());
+    }
+
+    // 缓存数据的加密参数
+    pub(crate) fn params(&self) -> &[u8] {
+        let len = read_u16(&self.mmap[PARAMS_LEN_RANGE]) as usize;
+        &self.mmap[PARAMS_START..PARAMS_START + len.min(MAX_PARAMS_LEN)]
+    }
+
+    // 追加数据，空间不足时扩大缓存文件，`params` 为数据的加密参数，缓存为空时记录
+    pub(crate) fn append(&mut self, data: &[u8], now_ms: i64, params: &[u8]) -> io::Result<()> {
+        if params.len() > MAX_PARAMS_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cipher params exceed cache header",
+            ));
+        }
+        let len = self.len();
+        if len + data.len() > self.capacity() {
+            self.grow(len + data.len())?;
+        }
+
+        if len == 0 {
+            self.mmap[FIRST_WRITE_RANGE].copy_from_slice(&now_ms.to_le_bytes());
+            self.mmap[PARAMS_LEN_RANGE].copy_from_slice(&(params.len() as u16).to_le_bytes());
+            self.mmap[PARAMS_START..PARAMS_START + params.len()].copy_from_slice(params);
+        }
+        let start = HEADER_SIZE + len;
+        self.mmap[start..start + data.len()].copy_from_slice(data);
+        // 数据写完后再更新长度，崩溃时只会丢掉正在写入的这一段，不会读到半截数据
+        self.mmap[LEN_RANGE].copy_from_slice(&((len + data.len()) as u64).to_le_bytes());
+        Ok(())
+    }
+
+    // 清空缓存
+    pub(crate) fn clear(&mut self) {
+        self.mmap[LEN_RANGE].fill(0);
+        self.mmap[FIRST_WRITE_RANGE].fill(0);
+        self.mmap[PARAMS_LEN_RANGE].fill(0);
+        self.mmap[FLUSHED_RANGE].fill(0);
+        self.mmap[PENDING_END_RANGE].fill(0);
+        self.mmap[PENDING_FILE_LEN_RANGE].fill(0);
+    }
+
+    fn is_valid(&self) -> bool {
+        self.mmap.len() >= HEADER_SIZE
+            && &self.mmap[..MAGIC.len()] == MAGIC
+            && self.mmap[MAGIC.len()] == VERSION
+            && self.len() <= self.capacity()
+    }
+
+    fn grow(&mut self, capacity: usize) -> io::Result<()> {
+        self.mmap.flush()?;
+        self.file.set_len((HEADER_SIZE + capacity) as u64)?;
+        self.mmap = unsafe { MmapMut::map_mut(&self.file)? };
+        Ok(())
+    }
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    u64::from_le_bytes(buf)
+}
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
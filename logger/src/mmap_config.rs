@@ -1,16 +1,64 @@
+use crate::encrypt_util::{parse_key, CipherMode, KeyDerivation, KEY_SIZE};
+use crate::keyring::Keyring;
+use crate::naming::{is_stream_byte, NamingTemplate};
+use crate::record::{ExportFormat, LineFormat};
+use crate::rotation::RotationPolicy;
+use crate::time_zone::LogTimeZone;
+use std::io;
+
+/// 多个进程（或同一进程中的多个 writer）使用同一个 `base_dir` 写同一种日志时的处理方式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockMode {
+    /// 已被其他 writer 占用时，`MmapWriter::try_new` 直接返回 `WouldBlock` 错误
+    FailFast,
+    /// 阻塞等待其他 writer 释放
+    Wait,
+    /// 使用带序号后缀的缓存和日志文件，例如 `20250520_10_plain_p1.log`，导出时自动合并
+    ProcessSuffix,
+}
+
+#[derive(Clone)]
 pub struct MmapConfig {
     /// 应用密钥，加密则必须设置该字段
     app_key: String,
     /// 是否加密
     is_encrypt: bool,
+    /// 加密模式，默认 `CipherMode::Aes256Gcm`，记录在日志文件头中，解密时按文件头选择
+    cipher_mode: CipherMode,
+    /// 服务端的 X25519 公钥，设置后使用公钥加密，不再使用 `app_key`，默认不设置
+    public_key: Option<[u8; KEY_SIZE]>,
+    /// 从 `app_key` 派生密钥的方式，默认 `KeyDerivation::ARGON2`，每次创建 writer 随机生成 salt，记录在日志文件头中
+    key_derivation: KeyDerivation,
+    /// 密钥环，设置后使用其中的当前密钥加密，并在日志文件头中记录密钥 id，默认不设置，使用 `app_key`
+    keyring: Option<Keyring>,
     /// 每次扩展的 buffer 大小，默认 128 KB
     buffer_size: usize,
+    /// 日志文件空间不足时每次扩展的大小，默认按文件当前大小成倍扩展，每次至少 buffer_size、最多 8 MB
+    file_grow_size: Option<usize>,
     /// 刷新尺寸，buffer 超过该大小则立即写入，默认 16 KB
     flush_size: usize,
     /// 刷新间隔，单位秒，最多该时间段内强制写入，默认 5 秒
     flush_interval: usize,
     /// 日志过期天数，超过该天数的日志将被删除，默认 7 天
     expiration_days: usize,
+    /// 是否启动后台线程按 `flush_interval` 定时刷新，仅 `SharedMmapWriter` 支持，`MmapWriter::try_new` 会返回错误，默认关闭
+    background_flush: bool,
+    /// `base_dir` 被其他 writer 占用时的处理方式，默认 `LockMode::ProcessSuffix`
+    lock_mode: LockMode,
+    /// 日志文件按哪个时区的日期和小时划分，同时用于导出和过期清理，默认 `Asia/Shanghai`
+    time_zone: LogTimeZone,
+    /// 日志文件切分策略，默认每小时一个文件
+    rotation_policy: RotationPolicy,
+    /// 日志文件路径模板，未设置时使用切分策略对应的默认模板
+    naming_template: Option<NamingTemplate>,
+    /// 日志流名称，用于模板中的 `{stream}`，同一个 `base_dir` 下不同日志流互不影响，默认为空
+    stream_name: String,
+    /// `base_dir` 下所有日志文件的总大小上限，超过后从最早的日志文件开始删除，默认不限制
+    max_total_bytes: Option<u64>,
+    /// 导出时结构化日志每一行的格式，默认 `{time} {level} [{thread}] {tag}: {message}{fields}`
+    line_format: LineFormat,
+    /// 导出时每条日志的输出方式，默认原样输出
+    export_format: ExportFormat,
 }
 
 impl MmapConfig {
@@ -18,10 +66,24 @@ impl MmapConfig {
         Self {
             app_key: app_key.to_string(),
             is_encrypt,
+            cipher_mode: CipherMode::Aes256Gcm,
+            public_key: None,
+            key_derivation: KeyDerivation::ARGON2,
+            keyring: None,
             buffer_size: 128 * 1024,
+            file_grow_size: None,
             flush_size: 16 * 1024,
             flush_interval: 5,
             expiration_days: 7,
+            background_flush: false,
+            lock_mode: LockMode::ProcessSuffix,
+            time_zone: LogTimeZone::default(),
+            rotation_policy: RotationPolicy::default(),
+            naming_template: None,
+            stream_name: String::new(),
+            max_total_bytes: None,
+            line_format: LineFormat::default(),
+            export_format: ExportFormat::default(),
         }
     }
 
@@ -45,6 +107,64 @@ impl MmapConfig {
         self.is_encrypt = is_encrypt;
     }
 
+    /// 获取 `cipher_mode`
+    pub fn get_cipher_mode(&self) -> CipherMode {
+        self.cipher_mode
+    }
+
+    /// 设置 `cipher_mode`，`CipherMode::AesEcb` 仅用于兼容旧版本的解密工具
+    pub fn set_cipher_mode(&mut self, cipher_mode: CipherMode) {
+        self.cipher_mode = cipher_mode;
+    }
+
+    /// 获取 `public_key`
+    pub fn get_public_key(&self) -> Option<[u8; KEY_SIZE]> {
+        self.public_key
+    }
+
+    /// 设置 `public_key`，必须为 64 位十六进制的 X25519 公钥，否则返回 `InvalidInput` 错误，配置保持不变；
+    /// 公钥加密需要 AEAD 加密模式，`CipherMode::AesEcb` 时 `MmapWriter::try_new` 返回错误
+    pub fn set_public_key(&mut self, public_key: &str) -> io::Result<()> {
+        let public_key = parse_key(public_key).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid public key: {err}"),
+            )
+        })?;
+        self.public_key = Some(public_key);
+        Ok(())
+    }
+
+    /// 获取 `key_derivation`
+    pub fn get_key_derivation(&self) -> KeyDerivation {
+        self.key_derivation
+    }
+
+    /// 设置 `key_derivation`，参数无效时不生效，将会使用默认值
+    pub fn set_key_derivation(&mut self, key_derivation: KeyDerivation) {
+        if key_derivation.is_valid() {
+            self.key_derivation = key_derivation;
+        }
+    }
+
+    /// 获取 `keyring`
+    pub fn get_keyring(&self) -> Option<&Keyring> {
+        self.keyring.as_ref()
+    }
+
+    /// 设置 `keyring`，密钥 id 或密钥无效时返回 `InvalidInput` 错误，配置保持不变；
+    /// 导出时没有记录密钥 id 的旧日志仍然使用 `app_key` 解密
+    pub fn set_keyring(&mut self, keyring: Keyring) -> io::Result<()> {
+        if !keyring.is_valid() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Invalid keyring",
+            ));
+        }
+        self.keyring = Some(keyring);
+        Ok(())
+    }
+
     /// 获取 `buffer_size`
     pub fn get_buffer_size(&self) -> usize {
         self.buffer_size
@@ -57,6 +177,18 @@ impl MmapConfig {
         }
     }
 
+    /// 获取 `file_grow_size`
+    pub fn get_file_grow_size(&self) -> Option<usize> {
+        self.file_grow_size
+    }
+
+    /// 设置 `file_grow_size`，每次按固定大小扩展，必须 >= 1024，否则不生效，将会使用默认值
+    pub fn set_file_grow_size(&mut self, file_grow_size: usize) {
+        if file_grow_size >= 1024 {
+            self.file_grow_size = Some(file_grow_size);
+        }
+    }
+
     /// 获取 `flush_size`
     pub fn get_flush_size(&self) -> usize {
         self.flush_size
@@ -92,4 +224,102 @@ impl MmapConfig {
             self.expiration_days = expiration_days;
         }
     }
+
+    /// 获取 `background_flush`
+    pub fn is_background_flush(&self) -> bool {
+        self.background_flush
+    }
+
+    /// 设置 `background_flush`
+    pub fn set_background_flush(&mut self, background_flush: bool) {
+        self.background_flush = background_flush;
+    }
+
+    /// 获取 `lock_mode`
+    pub fn get_lock_mode(&self) -> LockMode {
+        self.lock_mode
+    }
+
+    /// 设置 `lock_mode`
+    pub fn set_lock_mode(&mut self, lock_mode: LockMode) {
+        self.lock_mode = lock_mode;
+    }
+
+    /// 获取 `time_zone`
+    pub fn get_time_zone(&self) -> LogTimeZone {
+        self.time_zone
+    }
+
+    /// 设置 `time_zone`
+    pub fn set_time_zone(&mut self, time_zone: LogTimeZone) {
+        self.time_zone = time_zone;
+    }
+
+    /// 获取 `rotation_policy`
+    pub fn get_rotation_policy(&self) -> RotationPolicy {
+        self.rotation_policy
+    }
+
+    /// 设置 `rotation_policy`，分钟数必须在 1 ~ 1440 之间、文件大小限制必须 > 0，否则不生效，将会使用默认值
+    pub fn set_rotation_policy(&mut self, rotation_policy: RotationPolicy) {
+        if rotation_policy.is_valid() {
+            self.rotation_policy = rotation_policy;
+        }
+    }
+
+    /// 获取实际使用的 `naming_template`，未设置时为切分策略对应的默认模板
+    pub fn get_naming_template(&self) -> NamingTemplate {
+        self.naming_template
+            .clone()
+            .unwrap_or_else(|| NamingTemplate::default_for(self.rotation_policy.get_period()))
+    }
+
+    /// 设置 `naming_template`，模板需要能区分切分策略的不同文件，否则 `MmapWriter::try_new` 返回错误
+    pub fn set_naming_template(&mut self, naming_template: NamingTemplate) {
+        self.naming_template = Some(naming_template);
+    }
+
+    /// 获取 `stream_name`
+    pub fn get_stream_name(&self) -> &str {
+        &self.stream_name
+    }
+
+    /// 设置 `stream_name`，只能包含字母、数字和 `-`，否则不生效，将会使用默认值
+    pub fn set_stream_name(&mut self, stream_name: &str) {
+        if stream_name.bytes().all(is_stream_byte) {
+            self.stream_name = stream_name.to_string();
+        }
+    }
+
+    /// 获取 `max_total_bytes`
+    pub fn get_max_total_bytes(&self) -> Option<u64> {
+        self.max_total_bytes
+    }
+
+    /// 设置 `max_total_bytes`，必须 > 0，否则不生效，将会使用默认值
+    pub fn set_max_total_bytes(&mut self, max_total_bytes: u64) {
+        if max_total_bytes > 0 {
+            self.max_total_bytes = Some(max_total_bytes);
+        }
+    }
+
+    /// 获取 `line_format`
+    pub fn get_line_format(&self) -> &LineFormat {
+        &self.line_format
+    }
+
+    /// 设置 `line_format`
+    pub fn set_line_format(&mut self, line_format: LineFormat) {
+        self.line_format = line_format;
+    }
+
+    /// 获取 `export_format`
+    pub fn get_export_format(&self) -> ExportFormat {
+        self.export_format
+    }
+
+    /// 设置 `export_format`
+    pub fn set_export_format(&mut self, export_format: ExportFormat) {
+        self.export_format = export_format;
+    }
 }
@@ -1,71 +1,193 @@
-use crate::encrypt_util::{decrypt_line, encrypt_line};
+use crate::build_info::{RUST_SDK_COMMIT, RUST_SDK_TARGET, RUST_SDK_VER};
+use crate::encrypt_util::{CipherParams, LogCipher};
+use crate::log_file::{
+    decode_record, encode_timed_record, migrate_legacy, needs_migration, read_log_file,
+    read_log_header, record_aad, recover_torn_tail, Compression, LogHeader, RECORD_FLAG_BINARY,
+    RECORD_FLAG_ENCRYPTED, RECORD_FLAG_STRUCTURED, RECORD_FLAG_TIMESTAMP,
+};
+use crate::log_reader::{file_time_range, find_log_files, line_flags, ExportSummary, LogReader};
+use crate::mmap_appender::MmapAppender;
+use crate::mmap_cache::{MmapCache, MAX_PARAMS_LEN};
 use crate::mmap_config::MmapConfig;
-use chrono::{DateTime, Datelike, LocalResult, NaiveDate, TimeZone, Timelike, Utc};
-use chrono_tz::Asia::Shanghai;
-use chrono_tz::Tz;
-use memmap2::MmapMut;
-use std::fs::{self, File, OpenOptions};
+use crate::naming::{known_templates, mode_str, LogFileName, NamingTemplate};
+use crate::record::Record;
+use crate::rotation::LogWindow;
+use crate::time_zone::LogTimeZone;
+use crate::writer_lock::{slot_suffix, WriterLock};
+use chrono::{NaiveDate, TimeZone, Utc};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs::{self, File};
 use std::io;
+use std::io::BufWriter;
 use std::io::Write;
-use std::io::{BufWriter, Read};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak};
+use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
 pub struct MmapWriter {
     base_dir: PathBuf,
     config: MmapConfig,
-    current_mmap: Option<MmapMut>, // 缓存当前 mmap
-    current_file: Option<PathBuf>, // 当前日志文件路径
-    buffer: Vec<u8>,               // 写入缓冲区
-    buffer_size: usize,            // 缓冲区大小
-    last_flush_time: Instant,      // 上次刷新时间
-    flush_interval: Duration,      // 刷新间隔
+    naming_template: NamingTemplate,        // 日志文件路径模板
+    cipher: Option<Arc<LogCipher>>,         // 加密时使用的 cipher，不加密时为 `None`
+    cipher_params: Vec<u8>,                 // 编码后的加密参数，随数据写入缓存文件
+    current_appender: Option<MmapAppender>, // 当前日志文件的写入窗口
+    current_file: Option<PathBuf>,          // 当前日志文件路径
+    current_window: Option<LogWindow>,      // 当前日志文件所属的时间周期
+    current_part: usize,                    // 当前日志文件在时间周期内的分片序号
+    current_cipher: Option<CipherParams>,   // 当前日志文件的加密参数
+    buffer: MmapCache,                      // 写入缓冲区，mmap 映射的缓存文件
+    lock: WriterLock,                       // base_dir 写入锁，决定文件名后缀
+    last_flush_time: Instant,               // 上次刷新时间
+    flush_interval: Duration,               // 刷新间隔
 }
 
 impl MmapWriter {
+    /// 创建 writer，开启了 `MmapConfig::set_background_flush` 时返回 `InvalidInput` 错误，需要使用 `SharedMmapWriter`
     pub fn try_new(base_dir: &PathBuf, config: MmapConfig) -> io::Result<Self> {
-        delete_expired_directories(base_dir, config.get_expiration_days())?;
+        // 后台刷新需要跨线程共享 writer，只有 SharedMmapWriter 支持
+        if config.is_background_flush() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Background flush requires SharedMmapWriter",
+            ));
+        }
+        Self::open(base_dir, config)
+    }
+
+    fn open(base_dir: &PathBuf, config: MmapConfig) -> io::Result<Self> {
+        let naming_template = config.get_naming_template();
+        let period = config.get_rotation_policy().get_period();
+        if !naming_template.supports(period) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Naming template {} can't tell apart files rotated {:?}",
+                    naming_template, period
+                ),
+            ));
+        }
+
+        let cipher = if config.is_encrypt() {
+            Some(Arc::new(new_cipher(&config)?))
+        } else {
+            None
+        };
+        let cipher_params = cipher
+            .as_ref()
+            .map(|cipher| cipher.params().encode())
+            .unwrap_or_default();
+        // 加密参数随缓存数据写入缓存文件头，创建 writer 时检查，不能等到第一次写入时才出错
+        if cipher_params.len() > MAX_PARAMS_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Cipher params exceed cache header",
+            ));
+        }
+
+        delete_expired_directories_in(
+            base_dir,
+            config.get_expiration_days(),
+            config.get_time_zone(),
+        )?;
+        // 自定义模板的目录不一定是日期，再按文件名中的日期清理
+        for template in known_templates(&naming_template) {
+            delete_expired_files(
+                base_dir,
+                config.get_expiration_days(),
+                config.get_time_zone(),
+                &template,
+            )?;
+        }
         let buf_size = config.get_buffer_size();
         let flush_interval = config.get_flush_interval();
 
-        let writer = MmapWriter {
+        fs::create_dir_all(base_dir)?;
+        let lock_name = stream_key(&config);
+        let lock = WriterLock::acquire(base_dir, &lock_name, config.get_lock_mode())?;
+        let cache_path = base_dir.join(cache_file_name(&lock_name, lock.slot()));
+        let buffer = MmapCache::open(&cache_path, buf_size)?;
+
+        let mut writer = MmapWriter {
             base_dir: base_dir.clone(),
             config,
-            current_mmap: None,
+            naming_template,
+            cipher,
+            cipher_params,
+            current_appender: None,
             current_file: None,
-            buffer: Vec::with_capacity(buf_size), // 缓冲区
-            buffer_size: 0,
+            current_window: None,
+            current_part: 0,
+            current_cipher: None,
+            buffer, // 缓冲区
+            lock,
             last_flush_time: Instant::now(),
             flush_interval: Duration::from_secs(flush_interval as u64), // 刷新间隔
         };
+
+        // 上次进程可能在写入日志文件时退出，先截断写入不完整的数据，已经写入的缓存数据不再重复写入
+        let markers = writer.recover_torn_files()?;
+        writer.skip_pending_flush()?;
+
+        // 上次进程退出前没来得及刷新的日志，追加到它们所属的日志文件
+        writer.flush()?;
+
+        // 记录恢复信息，导出日志时可以看到丢弃了多少数据
+        for marker in markers {
+            writer.write(&marker)?;
+        }
+
+        writer.enforce_quota()?;
         Ok(writer)
     }
 
     // 写入日志
     pub fn write(&mut self, message: &str) -> io::Result<()> {
-        let msg = if self.config.is_encrypt() {
-            let encrypt_msg =
-                encrypt_line(self.config.get_app_key(), message).unwrap_or(message.to_string());
-            format!("{}\n", encrypt_msg)
-        } else {
-            format!("{}\n", message)
-        };
+        self.write_bytes(message.as_bytes())
+    }
+
+    /// 写入任意二进制数据，可以包含换行和 0x00，导出时原样还原
+    pub fn write_bytes(&mut self, payload: &[u8]) -> io::Result<()> {
+        let record = encode_payload(
+            self.cipher.as_deref(),
+            0,
+            Utc::now().timestamp_millis(),
+            payload,
+        )?;
+        self.write_encoded(&record)
+    }
 
-        let data = msg.as_bytes();
+    /// 写入一条结构化日志，导出时按 `MmapConfig::set_line_format` 的格式输出
+    pub fn write_record(&mut self, record: &Record) -> io::Result<()> {
+        let record = encode_payload(
+            self.cipher.as_deref(),
+            RECORD_FLAG_STRUCTURED,
+            record.timestamp_ms,
+            &record.encode(),
+        )?;
+        self.write_encoded(&record)
+    }
 
-        // 如果缓冲区已满，先刷新
-        if self.buffer_size + data.len() > self.buffer.capacity() {
+    // 写入已经编码好的完整记录（可能包含多条）
+    pub(crate) fn write_encoded(&mut self, data: &[u8]) -> io::Result<()> {
+        // 如果缓冲区已满，或缓冲区中是上次进程按其他加密参数写入的数据，先刷新
+        if self.buffer.len() + data.len() > self.buffer.capacity()
+            || (!self.buffer.is_empty() && self.buffer.params() != self.cipher_params)
+        {
             self.flush()?;
         }
 
         // 将数据添加到缓冲区
-        self.buffer.extend_from_slice(data);
-        self.buffer_size += data.len();
+        self.buffer
+            .append(data, Utc::now().timestamp_millis(), &self.cipher_params)?;
 
         // 检查是否需要刷新：
         // 1. 缓冲区超过 flush_size KB
         // 2. 距离上次刷新超过 flush_interval 秒
-        if self.buffer_size >= self.config.get_flush_size()
+        if self.buffer.len() >= self.config.get_flush_size()
             || self.last_flush_time.elapsed() >= self.flush_interval
         {
             self.flush()?;
@@ -76,212 +198,775 @@ impl MmapWriter {
 
     // 刷新缓冲区到磁盘
     pub fn flush(&mut self) -> io::Result<()> {
-        if self.buffer_size == 0 {
+        if self.buffer.is_empty() {
             return Ok(());
         }
 
+        let previous_file = self.current_file.clone();
         self.flush_to_disk()?;
         self.last_flush_time = Instant::now();
+
+        // 切换到新的日志文件时检查磁盘配额
+        if previous_file.is_some() && self.current_file != previous_file {
+            self.enforce_quota()?;
+        }
         Ok(())
     }
 
-    /// 将指定时间范围的日志导出日志到指定路径
-    pub fn export_logs(&self, start_ms: i64, end_ms: i64, output: &PathBuf) -> io::Result<()> {
-        // 将毫秒时间戳转为 UTC 时间
-        let start_utc = Utc.timestamp_millis_opt(start_ms).single().ok_or_else(|| {
-            io::Error::new(io::ErrorKind::InvalidInput, "Invalid start timestamp")
-        })?;
+    /// 刷新缓冲区并关闭当前日志文件：同步到磁盘，截断末尾预留的空间，返回其中的错误
+    ///
+    /// 不调用 `close` 时，`Drop` 也会做同样的处理，但无法得知是否出错
+    pub fn close(mut self) -> io::Result<()> {
+        self.seal()
+    }
 
-        let end_utc = Utc
-            .timestamp_millis_opt(end_ms)
-            .single()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid end timestamp"))?;
+    // 刷新缓冲区并封存当前日志文件，可以重复调用
+    fn seal(&mut self) -> io::Result<()> {
+        self.flush()?;
+        self.seal_current_file()
+    }
 
-        // 转换为北京时间
-        let start: DateTime<Tz> = start_utc.with_timezone(&Shanghai);
-        let end: DateTime<Tz> = end_utc.with_timezone(&Shanghai);
+    /// 所有日志文件的总大小超过 `max_total_bytes` 时，从最早的日志文件开始删除，返回删除的文件
+    ///
+    /// 不会删除正在写入的文件，其他 writer 时间周期还没有结束的文件也可能正在写入，同样跳过；
+    /// 删除的文件会写入一条 `[logger] quota evicted` 日志
+    pub fn enforce_quota(&mut self) -> io::Result<Vec<PathBuf>> {
+        let Some(max_total_bytes) = self.config.get_max_total_bytes() else {
+            return Ok(vec![]);
+        };
+
+        let now = Utc::now();
+        let mut total_bytes = 0;
+        let mut candidates = vec![];
+        for (path, file_name) in find_log_files(&self.base_dir, &self.config)? {
+            let size = fs::metadata(&path)?.len();
+            total_bytes += size;
+            if self.current_file.as_ref() == Some(&path) {
+                continue;
+            }
+            let Some((file_start, file_end)) = file_time_range(&path, &file_name)? else {
+                continue;
+            };
+            if file_end > now && !self.is_own_file(&file_name) {
+                continue;
+            }
+            let order = (file_name.pid, file_name.slot, file_name.part);
+            candidates.push((file_start, order, path, size));
+        }
+        candidates.sort();
 
-        let mut out_buf = BufWriter::new(File::create(output)?);
+        let mut removed = vec![];
+        for (_, _, path, size) in candidates {
+            if total_bytes <= max_total_bytes {
+                break;
+            }
+            println!("超过磁盘配额，删除日志: {:?}，{} 字节", path, size);
+            remove_log_file(&self.base_dir, &path)?;
+            total_bytes -= size;
+            removed.push((path, size));
+        }
 
-        let mut current = start;
-        while current <= end {
-            self.export_log_by_file(&current, &mut out_buf)?;
-            current = current + chrono::Duration::hours(1);
+        for (path, size) in &removed {
+            let marker = format!(
+                "[logger] quota evicted {:?}, {} bytes",
+                path.strip_prefix(&self.base_dir).unwrap_or(path),
+                size
+            );
+            self.write(&marker)?;
         }
-        Ok(())
+        Ok(removed.into_iter().map(|(path, _)| path).collect())
+    }
+
+    /// 读取日志的 `LogReader`，使用相同的 `base_dir` 和配置
+    pub fn reader(&self) -> LogReader {
+        LogReader::new(&self.base_dir, self.config.clone())
     }
 
-    fn export_log_by_file(
+    /// 将时间范围 `[start_ms, end_ms]` 内写入的日志导出到指定路径，按每条日志的写入时间过滤，见 `LogReader::entries`
+    pub fn export_logs(
         &self,
-        current: &DateTime<Tz>,
-        out_buf: &mut BufWriter<File>,
+        start_ms: i64,
+        end_ms: i64,
+        output: &PathBuf,
+    ) -> io::Result<ExportSummary> {
+        let reader = self.reader();
+        // 时间范围无效时不创建导出文件
+        let entries = reader.entries(start_ms, end_ms)?;
+        entries.export_to(BufWriter::new(File::create(output)?))
+    }
+
+    /// 将指定时间范围的日志导出到任意 `Write`，例如标准输出、内存或网络连接，见 `LogReader::export_to`
+    pub fn export_to<W: Write>(
+        &self,
+        start_ms: i64,
+        end_ms: i64,
+        out: W,
+    ) -> io::Result<ExportSummary> {
+        self.reader().export_to(start_ms, end_ms, out)
+    }
+}
+
+impl MmapWriter {
+    // 打开日志文件准备追加，之前的文件不会再写入，先封存，新文件记录 `cipher` 加密参数
+    fn open_log_file(
+        &mut self,
+        path: &Path,
+        window: &LogWindow,
+        cipher: Option<CipherParams>,
     ) -> io::Result<()> {
-        let y = current.year();
-        let m = current.month();
-        let d = current.day();
-        let h = current.hour();
-        let dir = self.base_dir.join(format!("{:04}{:02}{:02}", y, m, d));
-        let encrypt_str = if self.config.is_encrypt() {
-            "encrypt"
-        } else {
-            "plain"
-        };
-        let filename = format!("{:04}{:02}{:02}_{:02}_{}.log", y, m, d, h, encrypt_str);
-        let filepath = dir.join(&filename);
-        if !filepath.exists() {
-            return Ok(());
+        self.seal_current_file()?;
+
+        // 旧版本没有文件头的日志文件，先转换格式再继续追加
+        if needs_migration(path)? {
+            migrate_legacy(path, line_flags(&self.config), self.legacy_header(window))?;
         }
 
-        let total_length = Self::get_first_zero_pos(&filepath)?;
+        let new_header = self.new_header(window, cipher.clone()).encode();
+        let appender = MmapAppender::open(
+            path,
+            &new_header,
+            self.config.get_file_grow_size().map(|size| size as u64),
+            self.config.get_buffer_size() as u64,
+        )?;
+        self.current_appender = Some(appender);
+        self.current_file = Some(path.to_path_buf());
+        self.current_cipher = cipher;
 
-        let mut src_file = File::open(&filepath)?;
-        let mut buffer = vec![0u8; total_length as usize];
-        src_file.read_exact(&mut buffer)?;
+        Ok(())
+    }
 
-        for bytes in buffer.split(|&b| b == b'\n') {
-            if bytes.is_empty() {
-                continue;
-            }
-            let msg = if self.config.is_encrypt() {
-                let encrypted_text = String::from_utf8(bytes.to_vec()).unwrap_or("".to_string());
-                decrypt_line(self.config.get_app_key(), encrypted_text.as_str())
-                    .unwrap_or("".to_string())
-            } else {
-                String::from_utf8(bytes.to_vec()).unwrap_or("".to_string())
-            };
-            writeln!(out_buf, "{}", msg)?;
+    // 实际的磁盘写入逻辑
+    fn flush_to_disk(&mut self) -> io::Result<()> {
+        // 缓冲区的日志按写入时间写入所属时间周期的文件，同一次刷新中的日志可能跨越多个周期
+        let cipher = self.buffered_cipher();
+        for (window, range) in self.buffered_windows() {
+            self.append_to_window(window, cipher.clone(), range)?;
         }
 
+        // 清空缓冲区
+        self.buffer.clear();
+
         Ok(())
     }
 
-    // mmap 为填充完成，会拼接 0x00 ，把日志导出来的时候，需要把文末的 0x00 都去掉
-    // 倒查 0x00 第一个位置
-    fn get_first_zero_pos(path: &PathBuf) -> io::Result<u64> {
-        let mut file = OpenOptions::new().read(true).open(path)?;
-        let total_length = file.metadata()?.len();
+    // 把缓冲区中 `range` 范围的记录追加到 `window` 周期的日志文件
+    fn append_to_window(
+        &mut self,
+        window: LogWindow,
+        cipher: Option<CipherParams>,
+        range: Range<usize>,
+    ) -> io::Result<()> {
+        // 检查是否需要切换文件，同一周期已有多个分片时继续写最后一个，
+        // 最后一个分片的加密参数不同时写入新的分片，一个文件只使用一种加密参数
+        if self.current_window != Some(window)
+            || self.current_appender.is_none()
+            || self.current_cipher != cipher
+        {
+            let mut part = self.latest_part(&window)?;
+            let mut log_path = self.window_path(&window, part)?;
+            if !can_append(&log_path, cipher.as_ref())? {
+                part += 1;
+                log_path = self.window_path(&window, part)?;
+            }
+            self.open_log_file(&log_path, &window, cipher)?;
+            self.current_window = Some(window);
+            self.current_part = part;
+        }
+
+        let len = range.len() as u64;
+        let committed_len = self
+            .current_appender
+            .as_ref()
+            .ok_or_else(no_log_file)?
+            .committed_len();
 
-        let mut buffer = vec![0u8; total_length as usize];
-        file.read_exact(&mut buffer)?;
+        // 超过单个文件的大小限制时，写入同一周期的下一个分片
+        let max_file_bytes = self.config.get_rotation_policy().get_max_file_bytes();
+        if max_file_bytes.is_some_and(|max| committed_len > 0 && committed_len + len > max) {
+            let part = self.current_part + 1;
+            let log_path = self.window_path(&window, part)?;
+            self.open_log_file(&log_path, &window, self.current_cipher.clone())?;
+            self.current_part = part;
+        }
+
+        // 先在缓存中记录这一段数据和日志文件当前的长度，写入后再标记为已写入，
+        // 进程在两者之间退出时，重启后可以判断这段数据是否已经写入，不会重复写入
+        let appender = self.current_appender.as_mut().ok_or_else(no_log_file)?;
+        self.buffer.begin_flush(range.end, appender.committed_len());
+        appender.append(&self.buffer.data()[range.clone()])?;
+        self.buffer.finish_flush(range.end);
+        Ok(())
+    }
+
+    // 上次进程追加一段缓存数据后、标记为已写入前退出时，这段数据已经在日志文件中，跳过这段数据
+    fn skip_pending_flush(&mut self) -> io::Result<()> {
+        let Some((range, file_len)) = self.buffer.pending_flush() else {
+            return Ok(());
+        };
+        let Some((window, _)) = self
+            .buffered_windows()
+            .into_iter()
+            .find(|(_, segment)| segment.start == range.start)
+        else {
+            return Ok(());
+        };
+        let path = self.window_path(&window, self.latest_part(&window)?)?;
+        if !path.exists() {
+            return Ok(());
+        }
+        let content = read_log_file(&path)?;
+        let start = file_len as usize;
+        let written = start
+            .checked_add(range.len())
+            .and_then(|end| content.data().get(start..end))
+            .is_some_and(|data| data == &self.buffer.data()[range.clone()]);
+        if written {
+            self.buffer.finish_flush(range.end);
+        }
+        Ok(())
+    }
 
-        let mut pos = total_length as usize;
-        while pos > 0 && buffer[pos - 1] == 0 {
-            pos -= 1;
+    // 按每条记录的写入时间把缓冲区中还没有写入日志文件的数据切分为连续的几段，返回每段所属的时间周期；
+    // 没有写入时间的记录跟随前一条记录，开头的按第一次写入的时间
+    fn buffered_windows(&self) -> Vec<(LogWindow, Range<usize>)> {
+        let data = self.buffer.data();
+        let mut window = self.window_of(self.buffer.first_write_ms());
+        let mut segments: Vec<(LogWindow, Range<usize>)> = vec![];
+        let mut start = self.buffer.flushed();
+        while start < data.len() {
+            // 缓存中的数据损坏时无法定位之后的记录，剩余部分写入同一个文件
+            let (timestamp_ms, used) = match decode_record(&data[start..]) {
+                Ok((record, used)) => (record.timestamp_ms, used),
+                Err(_) => (None, data.len() - start),
+            };
+            if let Some(timestamp_ms) = timestamp_ms {
+                window = self.window_of(timestamp_ms);
+            }
+            let end = start + used;
+            match segments.last_mut() {
+                Some((last, range)) if *last == window => range.end = end,
+                _ => segments.push((window, start..end)),
+            }
+            start = end;
         }
-        Ok(pos as u64)
+        segments
     }
 }
 
 impl MmapWriter {
-    // 初始化 mmap 映射
-    fn init_mmap(&mut self, path: &Path) -> io::Result<()> {
-        // 创建或打开文件
-        let file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(path)?;
-
-        // 每次给文件扩展 BUFFER_SIZE 大小，确保文件足够大
-        let file_size = file.metadata()?.len();
-        file.set_len(file_size + self.config.get_buffer_size() as u64)?;
-
-        // 创建内存映射
-        let mmap = unsafe { MmapMut::map_mut(&file)? };
-        self.current_mmap = Some(mmap);
-        self.current_file = Some(path.to_path_buf());
+    // 把当前日志文件同步到磁盘，并截断为实际的数据长度
+    fn seal_current_file(&mut self) -> io::Result<()> {
+        match self.current_appender.take() {
+            Some(appender) => appender.seal(),
+            None => Ok(()),
+        }
+    }
 
+    // 是否为当前 writer 写入的文件
+    fn is_own_file(&self, file_name: &LogFileName) -> bool {
+        file_name.is_encrypt == self.config.is_encrypt()
+            && file_name.stream == self.config.get_stream_name()
+            && file_name.slot == self.lock.slot()
+            && file_name.pid.is_none_or(|pid| pid == std::process::id())
+    }
+
+    // 获取指定毫秒时间戳在配置时区所属的时间周期
+    fn window_of(&self, timestamp_ms: i64) -> LogWindow {
+        let utc = Utc
+            .timestamp_millis_opt(timestamp_ms)
+            .single()
+            .unwrap_or_else(Utc::now);
+        let local = self.config.get_time_zone().to_local(&utc);
+        self.config.get_rotation_policy().window_of(&local)
+    }
+
+    // 创建日志文件所在的目录
+    fn ensure_directory(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir_path) = path.parent() {
+            if !dir_path.exists() {
+                fs::create_dir_all(dir_path)?;
+            }
+        }
         Ok(())
     }
 
-    // 实际的磁盘写入逻辑
-    fn flush_to_disk(&mut self) -> io::Result<()> {
-        let log_path = self.current_log_path()?;
+    // 检查当前周期和缓存数据所属周期的日志文件，返回需要写入日志的恢复信息
+    fn recover_torn_files(&self) -> io::Result<Vec<String>> {
+        let mut windows = vec![self.window_of(Utc::now().timestamp_millis())];
+        for (window, _) in self.buffered_windows() {
+            if !windows.contains(&window) {
+                windows.push(window);
+            }
+        }
 
-        // 检查是否需要切换文件
-        if self.current_file.as_ref() != Some(&log_path) {
-            self.init_mmap(&log_path)?;
-            self.current_file = Some(log_path.clone());
+        let mut markers = vec![];
+        for window in windows {
+            let path = self.window_path(&window, self.latest_part(&window)?)?;
+            let legacy_header = self.legacy_header(&window);
+            if let Some(torn) = recover_torn_tail(&path, line_flags(&self.config), legacy_header)? {
+                println!(
+                    "日志文件尾部写入不完整: {:?}，丢弃 {} 字节",
+                    path, torn.discarded
+                );
+                markers.push(format!(
+                    "[logger] recovered torn write in {:?}, discarded {} bytes, saved to {:?}",
+                    path.file_name().unwrap_or_default(),
+                    torn.discarded,
+                    torn.quarantine_path.file_name().unwrap_or_default()
+                ));
+            }
         }
+        Ok(markers)
+    }
 
-        // 获取当前 mmap
-        let mmap = self.current_mmap.as_mut().ok_or_else(|| {
-            io::Error::new(io::ErrorKind::InvalidInput, "Can't flush to disk by mmap")
-        })?;
+    // 新文件的文件头，记录文件名使用的时区、周期长度、加密参数以及创建文件的进程和 SDK 版本
+    fn new_header(&self, window: &LogWindow, cipher: Option<CipherParams>) -> LogHeader {
+        LogHeader {
+            time_zone: Some(self.config.get_time_zone()),
+            window_minutes: Some(window.minutes),
+            cipher,
+            encrypted: Some(self.config.is_encrypt()),
+            compression: Some(Compression::None),
+            created_at: Some(Utc::now().timestamp_millis()),
+            pid: Some(std::process::id()),
+            sdk_version: Some(RUST_SDK_VER.to_string()),
+            sdk_commit: Some(RUST_SDK_COMMIT.to_string()),
+            sdk_target: Some(RUST_SDK_TARGET.to_string()),
+            ..LogHeader::default()
+        }
+    }
 
-        // warning：mmap 申请空间会扩容，且全部填充 0，所以拼接数据的时候，需要找到第一 0 的位置
+    // 旧版本文件转换为记录格式后的文件头，旧版本的加密日志为 AES-128-ECB
+    fn legacy_header(&self, window: &LogWindow) -> LogHeader {
+        let cipher = self.config.is_encrypt().then(CipherParams::default);
+        self.new_header(window, cipher)
+    }
 
-        // 正序查找可用空间，文件越大，速度越慢
-        // let pos = mmap.iter().position(|&b| b == 0).unwrap_or(mmap.len());
+    // 缓冲区中数据的加密参数，不加密时为 `None`，上次进程的缓存没有记录加密参数时为 AES-128-ECB
+    fn buffered_cipher(&self) -> Option<CipherParams> {
+        self.cipher
+            .as_ref()
+            .map(|_| CipherParams::decode(self.buffer.params()).unwrap_or_default())
+    }
 
-        // 倒序查找可用空间，速度快
-        let mut pos = mmap.len();
-        while pos > 0 && mmap[pos - 1] == 0 {
-            pos -= 1;
+    // 时间周期内已存在的最后一个分片序号
+    fn latest_part(&self, window: &LogWindow) -> io::Result<usize> {
+        let mut part = 0;
+        while self.window_path(window, part + 1)?.exists() {
+            part += 1;
         }
+        Ok(part)
+    }
 
-        if pos + self.buffer_size > mmap.len() {
-            // 重新映射
-            self.init_mmap(&log_path)?;
-            return self.flush_to_disk(); // 递归重试
+    // 时间周期内第 `part` 个分片的日志文件路径
+    fn window_path(&self, window: &LogWindow, part: usize) -> io::Result<PathBuf> {
+        let name = LogFileName {
+            date: window.date,
+            hour: Some(window.hour()),
+            minute: Some(window.minute()),
+            stream: self.config.get_stream_name().to_string(),
+            pid: Some(std::process::id()),
+            is_encrypt: self.config.is_encrypt(),
+            slot: self.lock.slot(),
+            part,
+        };
+        let path = self.base_dir.join(self.naming_template.render(&name));
+        self.ensure_directory(&path)?;
+        Ok(path)
+    }
+}
+
+/// 可以跨线程共享的 `MmapWriter` 句柄，`Clone` 之后的句柄写入的是同一组日志文件
+///
+/// 每个线程先把编码好的完整行写入自己的暂存区，暂存区超过 `flush_size`
+/// 或距离上次刷新超过 `flush_interval` 时，再整块合并到 `MmapWriter`，
+/// 所以多个线程同时写入也不会把一行日志拆开。同一线程内的日志保持写入顺序，
+/// 不同线程之间的日志按合并的先后顺序落盘。线程暂存区在内存中，
+/// 只有合并到 `MmapWriter` 的缓存文件之后，进程崩溃时才能恢复，线程退出时会合并剩余日志并释放暂存区。
+///
+/// 开启 `MmapConfig::set_background_flush` 后会启动一个后台线程，每隔 `flush_interval`
+/// 刷新一次，应用空闲时日志也能按时落盘。最后一个句柄销毁时会刷新剩余日志并结束后台线程。
+#[derive(Clone)]
+pub struct SharedMmapWriter {
+    inner: Arc<SharedInner>,
+}
+
+struct SharedInner {
+    id: usize,
+    config: MmapConfig,
+    cipher: Option<Arc<LogCipher>>,
+    writer: Mutex<MmapWriter>,
+    stages: Mutex<Vec<Weak<Mutex<Vec<u8>>>>>, // 所有线程的暂存区，线程退出后失效
+    created_at: Instant,
+    last_flush_ms: AtomicU64, // 上次刷新时间，相对 created_at 的毫秒数
+    flusher: Mutex<Option<Flusher>>, // 后台刷新线程
+}
+
+type Stage = Arc<Mutex<Vec<u8>>>;
+
+// 线程在某个 SharedMmapWriter 中的暂存区，线程退出时把剩余日志合并到 writer
+struct ThreadStage {
+    stage: Stage,
+    inner: Weak<SharedInner>,
+}
+
+static NEXT_SHARED_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // 当前线程在各个 SharedMmapWriter 中的暂存区，key 为 SharedInner::id
+    static THREAD_STAGES: RefCell<HashMap<usize, ThreadStage>> = RefCell::new(HashMap::new());
+}
+
+impl SharedMmapWriter {
+    pub fn try_new(base_dir: &PathBuf, config: MmapConfig) -> io::Result<Self> {
+        let writer = MmapWriter::open(base_dir, config.clone())?;
+        Self::from_writer(writer, config)
+    }
+
+    fn from_writer(writer: MmapWriter, config: MmapConfig) -> io::Result<Self> {
+        let background_flush = config.is_background_flush();
+        let inner = Arc::new(SharedInner {
+            id: NEXT_SHARED_ID.fetch_add(1, Ordering::Relaxed),
+            config,
+            cipher: writer.cipher.clone(),
+            writer: Mutex::new(writer),
+            stages: Mutex::new(Vec::new()),
+            created_at: Instant::now(),
+            last_flush_ms: AtomicU64::new(0),
+            flusher: Mutex::new(None),
+        });
+        if background_flush {
+            let flusher = Flusher::spawn(Arc::downgrade(&inner))?;
+            *lock(&inner.flusher) = Some(flusher);
         }
+        Ok(Self { inner })
+    }
 
-        // 写入数据
-        mmap[pos..pos + self.buffer_size].copy_from_slice(&self.buffer[..self.buffer_size]);
-        mmap.flush()?;
+    // 写入日志
+    pub fn write(&self, message: &str) -> io::Result<()> {
+        self.write_bytes(message.as_bytes())
+    }
 
-        // 清空缓冲区
-        self.buffer_size = 0;
-        self.buffer.clear();
+    /// 写入任意二进制数据，可以包含换行和 0x00，导出时原样还原
+    pub fn write_bytes(&self, payload: &[u8]) -> io::Result<()> {
+        // 加密在调用线程完成，不占用 writer 的锁
+        let record = encode_payload(
+            self.inner.cipher.as_deref(),
+            0,
+            Utc::now().timestamp_millis(),
+            payload,
+        )?;
+        self.write_staged(&record)
+    }
+
+    /// 写入一条结构化日志，导出时按 `MmapConfig::set_line_format` 的格式输出
+    pub fn write_record(&self, record: &Record) -> io::Result<()> {
+        let record = encode_payload(
+            self.inner.cipher.as_deref(),
+            RECORD_FLAG_STRUCTURED,
+            record.timestamp_ms,
+            &record.encode(),
+        )?;
+        self.write_staged(&record)
+    }
+
+    // 把编码好的记录放入当前线程的暂存区，超过 flush_size 或到了刷新时间时合并到 writer
+    fn write_staged(&self, record: &[u8]) -> io::Result<()> {
+        let stage = self.thread_stage();
+
+        let mut staged = lock(&stage);
+        staged.extend_from_slice(record);
+        if staged.len() < self.inner.config.get_flush_size() && !self.inner.is_flush_due() {
+            return Ok(());
+        }
+        let data = std::mem::take(&mut *staged);
+        drop(staged);
 
+        lock(&self.inner.writer).write_encoded(&data)?;
+        if self.inner.is_flush_due() {
+            self.inner.flush()?;
+        }
         Ok(())
     }
+
+    // 合并所有线程的暂存区，并刷新到磁盘
+    pub fn flush(&self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// 将时间范围 `[start_ms, end_ms]` 内写入的日志导出到指定路径，导出前会先刷新所有线程的暂存区，导出时不占用 writer 的锁
+    pub fn export_logs(
+        &self,
+        start_ms: i64,
+        end_ms: i64,
+        output: &PathBuf,
+    ) -> io::Result<ExportSummary> {
+        self.inner.flush()?;
+        let reader = lock(&self.inner.writer).reader();
+        let entries = reader.entries(start_ms, end_ms)?;
+        entries.export_to(BufWriter::new(File::create(output)?))
+    }
+
+    /// 将指定时间范围的日志导出到任意 `Write`，同 `export_logs`
+    pub fn export_to<W: Write>(
+        &self,
+        start_ms: i64,
+        end_ms: i64,
+        out: W,
+    ) -> io::Result<ExportSummary> {
+        self.inner.flush()?;
+        let reader = lock(&self.inner.writer).reader();
+        reader.export_to(start_ms, end_ms, out)
+    }
+
+    // 获取当前线程的暂存区，第一次写入时创建并登记到 stages
+    fn thread_stage(&self) -> Stage {
+        THREAD_STAGES.with(|stages| {
+            let mut stages = stages.borrow_mut();
+            if let Some(thread_stage) = stages.get(&self.inner.id) {
+                return thread_stage.stage.clone();
+            }
+            // 已经销毁的 writer 的暂存区不再使用
+            stages.retain(|_, thread_stage| thread_stage.inner.strong_count() > 0);
+
+            let stage = Arc::new(Mutex::new(Vec::new()));
+            let mut registered = lock(&self.inner.stages);
+            registered.retain(|stage| stage.strong_count() > 0);
+            registered.push(Arc::downgrade(&stage));
+            drop(registered);
+            stages.insert(
+                self.inner.id,
+                ThreadStage {
+                    stage: stage.clone(),
+                    inner: Arc::downgrade(&self.inner),
+                },
+            );
+            stage
+        })
+    }
 }
 
-impl MmapWriter {
-    // 获取当前时间的年月日小时格式
-    fn current_time(&self) -> (i32, u32, u32, u32) {
-        let now = Utc::now().with_timezone(&Shanghai);
-        (now.year(), now.month(), now.day(), now.hour())
+impl TryFrom<MmapWriter> for SharedMmapWriter {
+    type Error = io::Error;
+
+    fn try_from(writer: MmapWriter) -> io::Result<Self> {
+        let config = writer.config.clone();
+        Self::from_writer(writer, config)
+    }
+}
+
+impl SharedInner {
+    fn flush(&self) -> io::Result<()> {
+        let mut writer = lock(&self.writer);
+        let mut stages = lock(&self.stages);
+        // 线程退出时暂存区已经合并到 writer，不再登记
+        stages.retain(|stage| stage.strong_count() > 0);
+        for stage in stages.iter().filter_map(Weak::upgrade) {
+            let data = std::mem::take(&mut *lock(&stage));
+            if !data.is_empty() {
+                writer.write_encoded(&data)?;
+            }
+        }
+        drop(stages);
+        writer.flush()?;
+        self.last_flush_ms.store(
+            self.created_at.elapsed().as_millis() as u64,
+            Ordering::Relaxed,
+        );
+        Ok(())
+    }
+
+    fn is_flush_due(&self) -> bool {
+        let last_flush_ms = self.last_flush_ms.load(Ordering::Relaxed);
+        let elapsed_ms = self.created_at.elapsed().as_millis() as u64;
+        elapsed_ms.saturating_sub(last_flush_ms) >= self.flush_interval_ms()
+    }
+
+    fn flush_interval_ms(&self) -> u64 {
+        (self.config.get_flush_interval() as u64).saturating_mul(1000)
     }
+}
+
+impl Drop for MmapWriter {
+    fn drop(&mut self) {
+        // Drop 里无法返回错误，需要知道结果时使用 `close`
+        let _ = self.seal();
+    }
+}
 
-    // 创建目录结构
-    fn ensure_directory(&self, year: i32, month: u32, day: u32) -> io::Result<PathBuf> {
-        let name = format!("{}{:02}{:02}", year, month, day);
-        let dir_path = self.base_dir.join(name.to_string());
+impl Drop for ThreadStage {
+    fn drop(&mut self) {
+        // writer 已经销毁时，剩余日志由 SharedInner 销毁时的 flush 写入
+        let Some(inner) = self.inner.upgrade() else {
+            return;
+        };
+        let data = std::mem::take(&mut *lock(&self.stage));
+        if !data.is_empty() {
+            // Drop 里无法返回错误，剩余日志尽力写入
+            let _ = lock(&inner.writer).write_encoded(&data);
+        }
+    }
+}
 
-        if !dir_path.exists() {
-            fs::create_dir_all(&dir_path)?;
+impl Drop for SharedInner {
+    fn drop(&mut self) {
+        if let Some(flusher) = lock(&self.flusher).take() {
+            flusher.stop();
         }
-        Ok(dir_path)
+        // Drop 里无法返回错误，剩余日志尽力写入
+        let _ = self.flush();
     }
+}
 
-    // 获取当前日志文件路径
-    fn current_log_path(&mut self) -> io::Result<PathBuf> {
-        let (year, month, day, hour) = self.current_time();
-        let dir = self.ensure_directory(year, month, day)?;
+// 后台刷新线程，只持有 SharedInner 的弱引用，不影响 writer 的销毁
+struct Flusher {
+    shutdown: Arc<(Mutex<bool>, Condvar)>,
+    handle: JoinHandle<()>,
+}
 
-        let encrypt_str = if self.config.is_encrypt() {
-            "encrypt"
-        } else {
-            "plain"
+impl Flusher {
+    fn spawn(inner: Weak<SharedInner>) -> io::Result<Self> {
+        let interval = match inner.upgrade() {
+            Some(inner) => Duration::from_millis(inner.flush_interval_ms()),
+            None => return Err(io::Error::other("writer already dropped")),
         };
+        let shutdown = Arc::new((Mutex::new(false), Condvar::new()));
+        let thread_shutdown = shutdown.clone();
+        let handle = thread::Builder::new()
+            .name("mmap-log-flusher".to_string())
+            .spawn(move || Self::run(inner, interval, thread_shutdown))?;
+        Ok(Self { shutdown, handle })
+    }
+
+    fn run(inner: Weak<SharedInner>, interval: Duration, shutdown: Arc<(Mutex<bool>, Condvar)>) {
+        let (stopped, cvar) = &*shutdown;
+        loop {
+            let guard = lock(stopped);
+            let (guard, _) = cvar
+                .wait_timeout_while(guard, interval, |stopped| !*stopped)
+                .unwrap_or_else(PoisonError::into_inner);
+            if *guard {
+                return;
+            }
+            drop(guard);
+
+            // writer 已经销毁则退出
+            let Some(inner) = inner.upgrade() else {
+                return;
+            };
+            let _ = inner.flush();
+        }
+    }
+
+    fn stop(self) {
+        let (stopped, cvar) = &*self.shutdown;
+        *lock(stopped) = true;
+        cvar.notify_all();
+        // 最后一个句柄可能正好在后台线程里释放，此时不能 join 自己
+        if self.handle.thread().id() != thread::current().id() {
+            let _ = self.handle.join();
+        }
+    }
+}
+
+fn no_log_file() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "Can't flush to disk by mmap")
+}
+
+// 某个线程写入时 panic 不影响其他线程继续写日志
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// 区分缓存文件和写入锁的名称，加密和明文日志、不同的日志流各用一个，例如 `plain`、`encrypt_{stream}`
+fn stream_key(config: &MmapConfig) -> String {
+    let mode = mode_str(config.is_encrypt());
+    match config.get_stream_name() {
+        "" => mode.to_string(),
+        stream => format!("{}_{}", mode, stream),
+    }
+}
+
+// 缓存文件名，带序号的 writer 再加上序号后缀
+fn cache_file_name(stream_key: &str, slot: usize) -> String {
+    format!("mmap_{}{}.cache", stream_key, slot_suffix(slot))
+}
+
+// 按配置创建加密使用的 cipher，设置了公钥时使用公钥加密，否则按 `key_derivation` 从 `app_key` 派生密钥，
+// 设置了密钥环时使用其中的当前密钥
+fn new_cipher(config: &MmapConfig) -> io::Result<LogCipher> {
+    let mode = config.get_cipher_mode();
+    let cipher = match (config.get_public_key(), config.get_keyring()) {
+        (Some(public_key), _) => LogCipher::with_public_key(mode, &public_key),
+        (None, Some(keyring)) => {
+            CipherParams::with_key_derivation(mode, config.get_key_derivation()).and_then(
+                |params| {
+                    let params = CipherParams {
+                        key_id: Some(keyring.active_id().to_string()),
+                        ..params
+                    };
+                    LogCipher::new(params, keyring.active_key())
+                },
+            )
+        }
+        (None, None) => CipherParams::with_key_derivation(mode, config.get_key_derivation())
+            .and_then(|params| LogCipher::new(params, config.get_app_key())),
+    };
+    cipher.map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err.to_string()))
+}
 
-        Ok(dir.join(format!(
-            "{:04}{:02}{:02}_{:02}_{}.log",
-            year, month, day, hour, encrypt_str
-        )))
+// 把一条日志编码为写入文件的记录，写入时间以明文保存，密文直接以二进制保存；
+// 加密失败时返回错误，不能把明文写入加密的日志文件
+fn encode_payload(
+    cipher: Option<&LogCipher>,
+    flags: u8,
+    timestamp_ms: i64,
+    payload: &[u8],
+) -> io::Result<Vec<u8>> {
+    let Some(cipher) = cipher else {
+        return Ok(encode_timed_record(flags, timestamp_ms, payload));
+    };
+    // 记录 flag 和写入时间是明文，作为附加数据认证，被修改后无法解密
+    let flags = flags | RECORD_FLAG_ENCRYPTED | RECORD_FLAG_BINARY;
+    let aad = record_aad(flags | RECORD_FLAG_TIMESTAMP, Some(timestamp_ms));
+    let encrypted = cipher
+        .encrypt_binary(payload, &aad)
+        .map_err(|err| io::Error::other(format!("Failed to encrypt log: {err}")))?;
+    Ok(encode_timed_record(flags, timestamp_ms, &encrypted))
+}
+
+// 日志文件中已有的记录和 `cipher` 的加密参数相同时才能继续追加，没有记录加密参数的旧版本文件为 AES-128-ECB
+fn can_append(path: &Path, cipher: Option<&CipherParams>) -> io::Result<bool> {
+    let Some(cipher) = cipher else {
+        return Ok(true);
+    };
+    if !path.exists() || fs::metadata(path)?.len() == 0 {
+        return Ok(true);
     }
+    let existing = read_log_header(path)?
+        .and_then(|header| header.cipher)
+        .unwrap_or_default();
+    Ok(existing == *cipher)
 }
 
-/// 删除 base_dir 下超过 7 天的子目录（目录名格式为 yyyymmdd）
+/// 删除 base_dir 下超过 7 天的子目录（目录名格式为 yyyymmdd，按北京时间计算）
 pub fn delete_expired_directories(
     base_dir: &PathBuf,
     expiration_days: usize,
+) -> Result<(), io::Error> {
+    delete_expired_directories_in(base_dir, expiration_days, LogTimeZone::default())
+}
+
+/// 删除 base_dir 下超过 `expiration_days` 天的子目录，目录名为 `time_zone` 时区的日期（yyyymmdd）
+pub fn delete_expired_directories_in(
+    base_dir: &PathBuf,
+    expiration_days: usize,
+    time_zone: LogTimeZone,
 ) -> Result<(), io::Error> {
     if !base_dir.exists() {
         return Ok(());
     }
+    let expired_before = Utc::now() - chrono::Duration::days(expiration_days as i64);
     for entry in fs::read_dir(base_dir)? {
         let entry = entry?;
         let path = entry.path();
@@ -292,14 +977,12 @@ pub fn delete_expired_directories(
 
         if let Some(name) = path.file_name().and_then(|os| os.to_str()) {
             if let Ok(date) = NaiveDate::parse_from_str(name, "%Y%m%d") {
-                // 直接构造北京时间当天的 00:00:00
-                if let LocalResult::Single(dir_datetime) =
-                    Shanghai.with_ymd_and_hms(date.year(), date.month(), date.day(), 0, 0, 0)
-                {
-                    let now = Utc::now().with_timezone(&Shanghai);
-                    let seven_days_ago = now - chrono::Duration::days(expiration_days as i64);
-
-                    if dir_datetime < seven_days_ago {
+                // 目录对应的日期在该时区的 00:00:00
+                let dir_datetime = date
+                    .and_hms_opt(0, 0, 0)
+                    .and_then(|local| time_zone.to_utc(&local));
+                if let Some(dir_datetime) = dir_datetime {
+                    if dir_datetime < expired_before {
                         println!("删除过期目录: {:?}", path);
                         fs::remove_dir_all(&path)?;
                     }
@@ -310,3 +993,43 @@ pub fn delete_expired_directories(
 
     Ok(())
 }
+
+/// 删除 base_dir 下符合模板、文件名中的日期超过 `expiration_days` 天的日志文件（不区分加密和日志流），
+/// 同时删除对应的 `.torn` 文件和删除后为空的目录
+pub fn delete_expired_files(
+    base_dir: &Path,
+    expiration_days: usize,
+    time_zone: LogTimeZone,
+    naming_template: &NamingTemplate,
+) -> io::Result<()> {
+    let expired_before = Utc::now() - chrono::Duration::days(expiration_days as i64);
+    for (path, file_name) in naming_template.find_files(base_dir)? {
+        let file_datetime = file_name
+            .date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|local| time_zone.to_utc(&local));
+        if file_datetime.is_none_or(|time| time >= expired_before) {
+            continue;
+        }
+        println!("删除过期日志: {:?}", path);
+        remove_log_file(base_dir, &path)?;
+    }
+    Ok(())
+}
+
+// 删除日志文件和对应的 `.torn` 文件，再删除变为空的目录，直到 base_dir
+fn remove_log_file(base_dir: &Path, path: &Path) -> io::Result<()> {
+    fs::remove_file(path)?;
+    let mut torn = path.as_os_str().to_os_string();
+    torn.push(".torn");
+    let _ = fs::remove_file(torn);
+
+    let mut dir = path.parent();
+    while let Some(current) = dir.filter(|dir| *dir != base_dir && dir.starts_with(base_dir)) {
+        if fs::remove_dir(current).is_err() {
+            break;
+        }
+        dir = current.parent();
+    }
+    Ok(())
+}
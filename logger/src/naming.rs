@@ -0,0 +1,460 @@
+use crate::rotation::RotationPeriod;
+use crate::writer_lock::slot_suffix;
+use chrono::{Datelike, NaiveDate};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// 日志文件相对 `base_dir` 的路径模板，`/` 分隔目录
+///
+/// 支持的占位符：
+/// - `{yyyy}` `{MM}` `{dd}`：日期，必填
+/// - `{HH}` `{mm}`：时间周期开始的小时和分钟，按小时切分需要 `{HH}`，按分钟切分需要 `{HH}` 和 `{mm}`
+/// - `{stream}`：日志流名称，见 `MmapConfig::set_stream_name`
+/// - `{pid}`：进程 id
+/// - `{mode}`：`plain` 或 `encrypt`，必填
+/// - `{slot}`：同一个 `base_dir` 下多个 writer 的序号后缀，如 `_p1`，必填
+/// - `{part}`：超过文件大小限制后的分片后缀，如 `_part1`，必填
+///
+/// 模板必须以 `.log` 结尾，`{stream}` 和 `{pid}` 之后需要紧跟固定文本、`{slot}`、`{part}` 或目录结尾，
+/// 否则无法从文件名中解析出来
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NamingTemplate {
+    pattern: String,
+    components: Vec<Vec<Token>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Literal(String),
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Stream,
+    Pid,
+    Mode,
+    Slot,
+    Part,
+}
+
+/// 日志文件路径中包含的信息，模板中没有的占位符对应的字段为 `None` 或空
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogFileName {
+    /// 时间周期开始的日期
+    pub date: NaiveDate,
+    /// 时间周期开始的小时
+    pub hour: Option<u32>,
+    /// 时间周期开始的分钟
+    pub minute: Option<u32>,
+    /// 日志流名称
+    pub stream: String,
+    /// 进程 id
+    pub pid: Option<u32>,
+    /// 是否为加密日志
+    pub is_encrypt: bool,
+    /// writer 序号，不带后缀为 0
+    pub slot: usize,
+    /// 分片序号，不带后缀为 0
+    pub part: usize,
+}
+
+impl NamingTemplate {
+    /// 各个切分周期的默认模板
+    ///
+    /// - `Hourly`：`{yyyy}{MM}{dd}/{yyyy}{MM}{dd}_{HH}_{mode}{slot}{part}.log`
+    /// - `Daily`：`{yyyy}{MM}{dd}/{yyyy}{MM}{dd}_{mode}{slot}{part}.log`
+    /// - `Minutes`：`{yyyy}{MM}{dd}/{yyyy}{MM}{dd}_{HH}{mm}_{mode}{slot}{part}.log`
+    pub fn default_for(period: RotationPeriod) -> Self {
+        let pattern = match period {
+            RotationPeriod::Hourly => "{yyyy}{MM}{dd}/{yyyy}{MM}{dd}_{HH}_{mode}{slot}{part}.log",
+            RotationPeriod::Daily => "{yyyy}{MM}{dd}/{yyyy}{MM}{dd}_{mode}{slot}{part}.log",
+            RotationPeriod::Minutes(_) => {
+                "{yyyy}{MM}{dd}/{yyyy}{MM}{dd}_{HH}{mm}_{mode}{slot}{part}.log"
+            }
+        };
+        Self::from_str(pattern).unwrap_or_else(|_| Self::fallback())
+    }
+
+    /// 所有切分周期的默认模板
+    pub fn defaults() -> Vec<Self> {
+        [
+            RotationPeriod::Hourly,
+            RotationPeriod::Daily,
+            RotationPeriod::Minutes(1),
+        ]
+        .into_iter()
+        .map(Self::default_for)
+        .collect()
+    }
+
+    /// 模板字符串
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// 模板能否区分该切分周期的不同文件
+    pub fn supports(&self, period: RotationPeriod) -> bool {
+        match period {
+            RotationPeriod::Hourly => self.contains(&Token::Hour),
+            RotationPeriod::Daily => true,
+            RotationPeriod::Minutes(_) => {
+                self.contains(&Token::Hour) && self.contains(&Token::Minute)
+            }
+        }
+    }
+
+    /// 生成日志文件相对 `base_dir` 的路径
+    pub fn render(&self, name: &LogFileName) -> PathBuf {
+        let mut path = PathBuf::new();
+        for tokens in &self.components {
+            let mut component = String::new();
+            for token in tokens {
+                match token {
+                    Token::Literal(literal) => component.push_str(literal),
+                    Token::Year => component.push_str(&format!("{:04}", name.date.year())),
+                    Token::Month => component.push_str(&format!("{:02}", name.date.month())),
+                    Token::Day => component.push_str(&format!("{:02}", name.date.day())),
+                    Token::Hour => component.push_str(&format!("{:02}", name.hour.unwrap_or(0))),
+                    Token::Minute => {
+                        component.push_str(&format!("{:02}", name.minute.unwrap_or(0)))
+                    }
+                    Token::Stream => component.push_str(&name.stream),
+                    Token::Pid => component.push_str(&name.pid.unwrap_or(0).to_string()),
+                    Token::Mode => component.push_str(mode_str(name.is_encrypt)),
+                    Token::Slot => component.push_str(&slot_suffix(name.slot)),
+                    Token::Part => component.push_str(&part_suffix(name.part)),
+                }
+            }
+            path.push(component);
+        }
+        path
+    }
+
+    /// 解析日志文件相对 `base_dir` 的路径，不符合模板时返回 `None`
+    pub fn parse(&self, relative_path: &Path) -> Option<LogFileName> {
+        let names: Vec<&str> = relative_path
+            .iter()
+            .map(|name| name.to_str())
+            .collect::<Option<_>>()?;
+        if names.len() != self.components.len() {
+            return None;
+        }
+        let mut fields = Fields::default();
+        for (tokens, name) in self.components.iter().zip(names) {
+            fields = match_tokens(tokens, name, &fields)?;
+        }
+        fields.into_file_name()
+    }
+
+    /// 查找 `base_dir` 下所有符合模板的日志文件
+    pub fn find_files(&self, base_dir: &Path) -> io::Result<Vec<(PathBuf, LogFileName)>> {
+        let mut files = vec![];
+        if base_dir.is_dir() {
+            self.walk(base_dir, 0, &Fields::default(), &mut files)?;
+        }
+        Ok(files)
+    }
+
+    fn walk(
+        &self,
+        dir: &Path,
+        depth: usize,
+        fields: &Fields,
+        files: &mut Vec<(PathBuf, LogFileName)>,
+    ) -> io::Result<()> {
+        let is_last = depth + 1 == self.components.len();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(fields) = match_tokens(&self.components[depth], name, fields) else {
+                continue;
+            };
+            if is_last {
+                if let Some(file_name) = fields.into_file_name().filter(|_| path.is_file()) {
+                    files.push((path, file_name));
+                }
+            } else if path.is_dir() {
+                self.walk(&path, depth + 1, &fields, files)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn contains(&self, token: &Token) -> bool {
+        self.components.iter().flatten().any(|t| t == token)
+    }
+
+    // 默认模板解析失败时使用，不会出现
+    fn fallback() -> Self {
+        Self {
+            pattern: String::new(),
+            components: vec![],
+        }
+    }
+}
+
+impl fmt::Display for NamingTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.pattern)
+    }
+}
+
+impl FromStr for NamingTemplate {
+    type Err = String;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let invalid = |msg: &str| format!("Invalid naming template {:?}: {}", pattern, msg);
+        if !pattern.ends_with(".log") {
+            return Err(invalid("must end with .log"));
+        }
+
+        let mut components = vec![];
+        for component in pattern.split('/') {
+            if component.is_empty() || component == "." || component == ".." {
+                return Err(invalid("empty or relative directory"));
+            }
+            components.push(tokenize(component).map_err(|msg| invalid(&msg))?);
+        }
+        let template = Self {
+            pattern: pattern.to_string(),
+            components,
+        };
+
+        for (token, name) in [
+            (Token::Year, "{yyyy}"),
+            (Token::Month, "{MM}"),
+            (Token::Day, "{dd}"),
+            (Token::Mode, "{mode}"),
+            (Token::Slot, "{slot}"),
+            (Token::Part, "{part}"),
+        ] {
+            if !template.contains(&token) {
+                return Err(invalid(&format!("missing {}", name)));
+            }
+        }
+        Ok(template)
+    }
+}
+
+// 拆分一级目录或文件名中的占位符和固定文本
+fn tokenize(component: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut rest = component;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('{') {
+            let (name, after) = after
+                .split_once('}')
+                .ok_or_else(|| "unclosed placeholder".to_string())?;
+            tokens.push(match name {
+                "yyyy" => Token::Year,
+                "MM" => Token::Month,
+                "dd" => Token::Day,
+                "HH" => Token::Hour,
+                "mm" => Token::Minute,
+                "stream" => Token::Stream,
+                "pid" => Token::Pid,
+                "mode" => Token::Mode,
+                "slot" => Token::Slot,
+                "part" => Token::Part,
+                _ => return Err(format!("unknown placeholder {{{}}}", name)),
+            });
+            rest = after;
+        } else {
+            let end = rest.find('{').unwrap_or(rest.len());
+            if rest[..end].contains('}') {
+                return Err("unexpected }".to_string());
+            }
+            tokens.push(Token::Literal(rest[..end].to_string()));
+            rest = &rest[end..];
+        }
+    }
+
+    // 长度不固定的占位符之后必须能确定结束位置
+    for (i, token) in tokens.iter().enumerate() {
+        if matches!(token, Token::Stream | Token::Pid) {
+            let next = tokens.get(i + 1);
+            if !matches!(
+                next,
+                None | Some(Token::Literal(_)) | Some(Token::Slot) | Some(Token::Part)
+            ) {
+                return Err("{stream} and {pid} must be followed by text, {slot} or {part}".into());
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// 解析过程中得到的字段，同一个占位符出现多次时必须一致
+#[derive(Clone, Debug, Default)]
+struct Fields {
+    year: Option<u32>,
+    month: Option<u32>,
+    day: Option<u32>,
+    hour: Option<u32>,
+    minute: Option<u32>,
+    stream: Option<String>,
+    pid: Option<u32>,
+    is_encrypt: Option<bool>,
+    slot: Option<usize>,
+    part: Option<usize>,
+}
+
+impl Fields {
+    fn into_file_name(self) -> Option<LogFileName> {
+        let date = NaiveDate::from_ymd_opt(self.year? as i32, self.month?, self.day?)?;
+        Some(LogFileName {
+            date,
+            hour: self.hour,
+            minute: self.minute,
+            stream: self.stream.unwrap_or_default(),
+            pid: self.pid,
+            is_encrypt: self.is_encrypt?,
+            slot: self.slot?,
+            part: self.part?,
+        })
+    }
+}
+
+// 已有值时必须相同
+fn set<T: PartialEq>(field: &mut Option<T>, value: T) -> bool {
+    match field {
+        Some(existing) => *existing == value,
+        None => {
+            *field = Some(value);
+            true
+        }
+    }
+}
+
+// 按模板匹配一级目录或文件名，成功时返回补充后的字段
+fn match_tokens(tokens: &[Token], s: &str, fields: &Fields) -> Option<Fields> {
+    let Some((token, rest_tokens)) = tokens.split_first() else {
+        return s.is_empty().then(|| fields.clone());
+    };
+    // 尝试一种取值，匹配剩余部分
+    let attempt = |len: usize, update: &dyn Fn(&mut Fields) -> bool| {
+        let mut fields = fields.clone();
+        if !update(&mut fields) {
+            return None;
+        }
+        match_tokens(rest_tokens, &s[len..], &fields)
+    };
+
+    match token {
+        Token::Literal(literal) => s
+            .starts_with(literal.as_str())
+            .then(|| match_tokens(rest_tokens, &s[literal.len()..], fields))?,
+        Token::Year | Token::Month | Token::Day | Token::Hour | Token::Minute => {
+            let width = if *token == Token::Year { 4 } else { 2 };
+            let value = fixed_digits(s, width)?;
+            let valid = match token {
+                Token::Month => (1..=12).contains(&value),
+                Token::Day => (1..=31).contains(&value),
+                Token::Hour => value < 24,
+                Token::Minute => value < 60,
+                _ => true,
+            };
+            if !valid {
+                return None;
+            }
+            attempt(width, &|fields| match token {
+                Token::Year => set(&mut fields.year, value),
+                Token::Month => set(&mut fields.month, value),
+                Token::Day => set(&mut fields.day, value),
+                Token::Hour => set(&mut fields.hour, value),
+                _ => set(&mut fields.minute, value),
+            })
+        }
+        Token::Mode => [(false, "plain"), (true, "encrypt")]
+            .into_iter()
+            .filter(|(_, mode)| s.starts_with(mode))
+            .find_map(|(is_encrypt, mode)| {
+                attempt(mode.len(), &|fields| {
+                    set(&mut fields.is_encrypt, is_encrypt)
+                })
+            }),
+        Token::Pid => {
+            let max = prefix_len(s, |b| b.is_ascii_digit());
+            (1..=max).rev().find_map(|len| {
+                let pid: u32 = s[..len].parse().ok()?;
+                attempt(len, &|fields| set(&mut fields.pid, pid))
+            })
+        }
+        Token::Stream => {
+            let max = prefix_len(s, is_stream_byte);
+            (0..=max).rev().find_map(|len| {
+                let stream = s[..len].to_string();
+                attempt(len, &|fields| set(&mut fields.stream, stream.clone()))
+            })
+        }
+        Token::Slot | Token::Part => {
+            let prefix = if *token == Token::Slot { "_p" } else { "_part" };
+            let mut candidates = vec![];
+            if let Some(digits) = s.strip_prefix(prefix) {
+                let max = prefix_len(digits, |b| b.is_ascii_digit());
+                for len in (1..=max).rev() {
+                    if let Some(index) = digits[..len].parse().ok().filter(|index| *index > 0) {
+                        candidates.push((prefix.len() + len, index));
+                    }
+                }
+            }
+            candidates.push((0, 0));
+            candidates.into_iter().find_map(|(len, index)| {
+                attempt(len, &|fields| match token {
+                    Token::Slot => set(&mut fields.slot, index),
+                    _ => set(&mut fields.part, index),
+                })
+            })
+        }
+    }
+}
+
+fn fixed_digits(s: &str, width: usize) -> Option<u32> {
+    let digits = s.get(..width)?;
+    if prefix_len(digits, |b| b.is_ascii_digit()) != width {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+fn prefix_len(s: &str, accept: impl Fn(u8) -> bool) -> usize {
+    s.bytes().take_while(|b| accept(*b)).count()
+}
+
+/// 日志流名称允许的字符：字母、数字和 `-`
+pub(crate) fn is_stream_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'-'
+}
+
+pub(crate) fn mode_str(is_encrypt: bool) -> &'static str {
+    if is_encrypt {
+        "encrypt"
+    } else {
+        "plain"
+    }
+}
+
+fn part_suffix(part: usize) -> String {
+    if part == 0 {
+        String::new()
+    } else {
+        format!("_part{}", part)
+    }
+}
+
+// 当前模板和各个默认模板，当前模板优先
+pub(crate) fn known_templates(naming_template: &NamingTemplate) -> Vec<NamingTemplate> {
+    let mut templates = vec![naming_template.clone()];
+    for template in NamingTemplate::defaults() {
+        if !templates.contains(&template) {
+            templates.push(template);
+        }
+    }
+    templates
+}
@@ -0,0 +1,363 @@
+use crate::time_zone::LogTimeZone;
+use chrono::{TimeZone, Utc};
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// 结构化日志 payload 布局：
+// [0]        版本号
+// [1..9)     写入时间，毫秒时间戳，i64 小端
+// [9]        日志级别
+// [10..18)   线程 id，u64 小端
+// [18..20)   tag 长度，u16 小端
+// [20..20+n) tag
+// 之后是键值对：数量 u16 小端，每一对为 key 长度 u16 小端、key、value 长度 u32 小端、value
+// 最后是日志内容，直到 payload 结束
+const RECORD_VERSION: u8 = 1;
+const FIXED_SIZE: usize = 20;
+
+/// 日志级别
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    fn as_u8(self) -> u8 {
+        match self {
+            Level::Trace => 0,
+            Level::Debug => 1,
+            Level::Info => 2,
+            Level::Warn => 3,
+            Level::Error => 4,
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Level::Trace),
+            1 => Some(Level::Debug),
+            2 => Some(Level::Info),
+            3 => Some(Level::Warn),
+            4 => Some(Level::Error),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Level::Trace => "TRACE",
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        };
+        f.pad(name)
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "TRACE" => Ok(Level::Trace),
+            "DEBUG" => Ok(Level::Debug),
+            "INFO" => Ok(Level::Info),
+            "WARN" => Ok(Level::Warn),
+            "ERROR" => Ok(Level::Error),
+            _ => Err(format!("Invalid level: {}", s)),
+        }
+    }
+}
+
+/// 一条结构化日志
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    /// 写入时间，毫秒时间戳
+    pub timestamp_ms: i64,
+    /// 日志级别
+    pub level: Level,
+    /// 标签，通常为模块名
+    pub tag: String,
+    /// 写入日志的线程 id，同一进程内每个线程唯一
+    pub thread_id: u64,
+    /// 日志内容
+    pub message: String,
+    /// 附加的键值对，例如 `tracing` 的 span 和 event 字段
+    pub fields: Vec<(String, String)>,
+}
+
+impl Record {
+    /// 使用当前时间和当前线程创建日志
+    pub fn new(level: Level, tag: &str, message: &str) -> Self {
+        Self {
+            timestamp_ms: Utc::now().timestamp_millis(),
+            level,
+            tag: tag.to_string(),
+            thread_id: current_thread_id(),
+            message: message.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    /// 添加一个键值对
+    pub fn with_field(mut self, key: &str, value: &str) -> Self {
+        self.fields.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// 编码为写入日志文件的 payload，tag 和 key 超过 65535 字节时截断，超过 65535 个的键值对丢弃
+    pub fn encode(&self) -> Vec<u8> {
+        let tag = truncate_utf8(&self.tag, u16::MAX as usize);
+        let fields = &self.fields[..self.fields.len().min(u16::MAX as usize)];
+        let mut bytes = Vec::with_capacity(FIXED_SIZE + tag.len() + 2 + self.message.len());
+        bytes.push(RECORD_VERSION);
+        bytes.extend_from_slice(&self.timestamp_ms.to_le_bytes());
+        bytes.push(self.level.as_u8());
+        bytes.extend_from_slice(&self.thread_id.to_le_bytes());
+        bytes.extend_from_slice(&(tag.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(tag.as_bytes());
+        bytes.extend_from_slice(&(fields.len() as u16).to_le_bytes());
+        for (key, value) in fields {
+            let key = truncate_utf8(key, u16::MAX as usize);
+            let value = truncate_utf8(value, u32::MAX as usize);
+            bytes.extend_from_slice(&(key.len() as u16).to_le_bytes());
+            bytes.extend_from_slice(key.as_bytes());
+            bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(value.as_bytes());
+        }
+        bytes.extend_from_slice(self.message.as_bytes());
+        bytes
+    }
+
+    /// 从 payload 解码
+    pub fn decode(bytes: &[u8]) -> io::Result<Self> {
+        let version = *bytes.first().ok_or_else(invalid_record)?;
+        if version != RECORD_VERSION {
+            return Err(invalid_record());
+        }
+        let mut reader = FieldReader { bytes, pos: 1 };
+        let timestamp_ms = i64::from_le_bytes(read_array(reader.take(8)?));
+        let level = Level::from_u8(reader.take(1)?[0]).ok_or_else(invalid_record)?;
+        let thread_id = u64::from_le_bytes(read_array(reader.take(8)?));
+        let tag = reader.take_string(2)?;
+        let count = reader.take_len(2)?;
+        let mut fields = Vec::new();
+        for _ in 0..count {
+            let key = reader.take_string(2)?;
+            let value = reader.take_string(4)?;
+            fields.push((key, value));
+        }
+        Ok(Self {
+            timestamp_ms,
+            level,
+            tag,
+            thread_id,
+            message: String::from_utf8_lossy(&bytes[reader.pos..]).into_owned(),
+            fields,
+        })
+    }
+}
+
+// 按顺序读取 payload 中的字段，长度不足时返回错误
+struct FieldReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> FieldReader<'a> {
+    fn take(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).ok_or_else(invalid_record)?;
+        let slice = self.bytes.get(self.pos..end).ok_or_else(invalid_record)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    // 读取 2 或 4 字节的小端长度
+    fn take_len(&mut self, size: usize) -> io::Result<usize> {
+        let mut buf = [0u8; 4];
+        buf[..size].copy_from_slice(self.take(size)?);
+        Ok(u32::from_le_bytes(buf) as usize)
+    }
+
+    // 读取长度前缀的字符串
+    fn take_string(&mut self, size: usize) -> io::Result<String> {
+        let len = self.take_len(size)?;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
+
+/// 导出结构化日志时每一行的格式
+///
+/// 支持的占位符：
+/// - `{time}`：本地时间，如 `2025-05-20 10:45:01.123`
+/// - `{timestamp}`：毫秒时间戳
+/// - `{level}`：日志级别
+/// - `{tag}`：标签
+/// - `{thread}`：线程 id
+/// - `{message}`：日志内容
+/// - `{fields}`：键值对，输出为 ` key=value key=value`，每一对前面带一个空格，没有键值对时为空
+///
+/// 不认识的占位符原样输出
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineFormat {
+    pattern: String,
+}
+
+impl Default for LineFormat {
+    fn default() -> Self {
+        Self::new("{time} {level} [{thread}] {tag}: {message}{fields}")
+    }
+}
+
+impl LineFormat {
+    pub fn new(pattern: &str) -> Self {
+        Self {
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// 格式字符串
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// 按格式输出一条日志，时间使用 `time_zone` 时区
+    pub fn render(&self, record: &Record, time_zone: &LogTimeZone) -> String {
+        let mut line = String::with_capacity(self.pattern.len() + record.message.len());
+        let mut rest = self.pattern.as_str();
+        while let Some(start) = rest.find('{') {
+            line.push_str(&rest[..start]);
+            let after = &rest[start..];
+            let Some(end) = after.find('}') else {
+                rest = after;
+                break;
+            };
+            match &after[1..end] {
+                "time" => line.push_str(&format_time(record.timestamp_ms, time_zone)),
+                "timestamp" => line.push_str(&record.timestamp_ms.to_string()),
+                "level" => line.push_str(&record.level.to_string()),
+                "tag" => line.push_str(&record.tag),
+                "thread" => line.push_str(&record.thread_id.to_string()),
+                "message" => line.push_str(&record.message),
+                "fields" => {
+                    for (key, value) in &record.fields {
+                        line.push(' ');
+                        line.push_str(key);
+                        line.push('=');
+                        line.push_str(value);
+                    }
+                }
+                _ => line.push_str(&after[..=end]),
+            }
+            rest = &after[end + 1..];
+        }
+        line.push_str(rest);
+        line
+    }
+}
+
+/// 导出时每条日志的输出方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// 原样输出，日志中的换行会拆成多行，与旧版本一致
+    #[default]
+    Raw,
+    /// 转义 `\\`、`\n` 和 `\r`，每条日志恰好一行，用 `unescape_line` 还原
+    Escaped,
+}
+
+impl ExportFormat {
+    /// 按输出方式处理导出的一条日志，不包括换行
+    pub fn apply<'a>(&self, line: Cow<'a, [u8]>) -> Cow<'a, [u8]> {
+        let needs_escape = |b: &u8| matches!(b, b'\\' | b'\n' | b'\r');
+        if *self == ExportFormat::Raw || !line.iter().any(needs_escape) {
+            return line;
+        }
+        let mut escaped = Vec::with_capacity(line.len() + 16);
+        for &b in line.iter() {
+            match b {
+                b'\\' => escaped.extend_from_slice(b"\\\\"),
+                b'\n' => escaped.extend_from_slice(b"\\n"),
+                b'\r' => escaped.extend_from_slice(b"\\r"),
+                _ => escaped.push(b),
+            }
+        }
+        Cow::Owned(escaped)
+    }
+}
+
+/// 还原 `ExportFormat::Escaped` 导出的一行，不包括换行
+pub fn unescape_line(line: &[u8]) -> Vec<u8> {
+    let mut unescaped = Vec::with_capacity(line.len());
+    let mut bytes = line.iter();
+    while let Some(&b) = bytes.next() {
+        if b != b'\\' {
+            unescaped.push(b);
+            continue;
+        }
+        match bytes.next() {
+            Some(b'n') => unescaped.push(b'\n'),
+            Some(b'r') => unescaped.push(b'\r'),
+            Some(&other) => unescaped.push(other),
+            None => unescaped.push(b'\\'),
+        }
+    }
+    unescaped
+}
+
+/// 当前线程的 id，进程内从 1 开始依次分配
+pub fn current_thread_id() -> u64 {
+    static NEXT_THREAD_ID: AtomicU64 = AtomicU64::new(1);
+    thread_local! {
+        static THREAD_ID: Cell<u64> = const { Cell::new(0) };
+    }
+    THREAD_ID.with(|id| {
+        if id.get() == 0 {
+            id.set(NEXT_THREAD_ID.fetch_add(1, Ordering::Relaxed));
+        }
+        id.get()
+    })
+}
+
+fn format_time(timestamp_ms: i64, time_zone: &LogTimeZone) -> String {
+    match Utc.timestamp_millis_opt(timestamp_ms).single() {
+        Some(utc) => time_zone
+            .to_local(&utc)
+            .format("%Y-%m-%d %H:%M:%S%.3f")
+            .to_string(),
+        None => timestamp_ms.to_string(),
+    }
+}
+
+// 截断到不超过 max 字节的字符边界
+fn truncate_utf8(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+fn read_array(bytes: &[u8]) -> [u8; 8] {
+    let mut buf = [0u8; 8];
+    buf.copy_from_slice(bytes);
+    buf
+}
+
+fn invalid_record() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Invalid structured record")
+}
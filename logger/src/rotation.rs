@@ -0,0 +1,161 @@
+use crate::naming::LogFileName;
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+
+/// 日志文件按多长时间切分一次
+///
+/// 不同周期默认的文件名（见 `NamingTemplate::default_for`）：
+/// - `Hourly`：`20250520_10_plain.log`
+/// - `Daily`：`20250520_plain.log`
+/// - `Minutes(15)`：`20250520_1045_plain.log`，从当天 00:00 开始每 15 分钟一个文件
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RotationPeriod {
+    /// 每小时一个文件
+    Hourly,
+    /// 每天一个文件
+    Daily,
+    /// 每 N 分钟一个文件，取值 1 ~ 1440
+    Minutes(u32),
+}
+
+/// 日志文件切分策略，按时间周期切分，可以再限制单个文件的大小
+///
+/// 单个文件的日志数据超过 `max_file_bytes` 后，同一周期的日志写入 `_part1`、`_part2` 等后续文件，
+/// 例如 `20250520_10_plain_part1.log`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RotationPolicy {
+    period: RotationPeriod,
+    max_file_bytes: Option<u64>,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self::hourly()
+    }
+}
+
+impl RotationPolicy {
+    /// 每小时一个文件
+    pub fn hourly() -> Self {
+        Self {
+            period: RotationPeriod::Hourly,
+            max_file_bytes: None,
+        }
+    }
+
+    /// 每天一个文件
+    pub fn daily() -> Self {
+        Self {
+            period: RotationPeriod::Daily,
+            max_file_bytes: None,
+        }
+    }
+
+    /// 每 `minutes` 分钟一个文件
+    pub fn every_minutes(minutes: u32) -> Self {
+        Self {
+            period: RotationPeriod::Minutes(minutes),
+            max_file_bytes: None,
+        }
+    }
+
+    /// 每小时切分，单个文件超过 `max_file_bytes` 时再拆分
+    pub fn max_bytes(max_file_bytes: u64) -> Self {
+        Self::hourly().with_max_bytes(max_file_bytes)
+    }
+
+    /// 在当前时间周期的基础上限制单个文件的大小
+    pub fn with_max_bytes(mut self, max_file_bytes: u64) -> Self {
+        self.max_file_bytes = Some(max_file_bytes);
+        self
+    }
+
+    /// 获取 `period`
+    pub fn get_period(&self) -> RotationPeriod {
+        self.period
+    }
+
+    /// 获取 `max_file_bytes`
+    pub fn get_max_file_bytes(&self) -> Option<u64> {
+        self.max_file_bytes
+    }
+
+    /// 分钟数在 1 ~ 1440 之间，且文件大小限制大于 0
+    pub fn is_valid(&self) -> bool {
+        let period_valid = match self.period {
+            RotationPeriod::Minutes(minutes) => (1..=MINUTES_PER_DAY).contains(&minutes),
+            _ => true,
+        };
+        period_valid && self.max_file_bytes.is_none_or(|max| max > 0)
+    }
+
+    // 本地时间所在的时间周期
+    pub(crate) fn window_of(&self, local: &NaiveDateTime) -> LogWindow {
+        let date = local.date();
+        let minute_of_day = local.hour() * 60 + local.minute();
+        match self.period {
+            RotationPeriod::Hourly => LogWindow {
+                date,
+                start_minute: local.hour() * 60,
+                minutes: 60,
+            },
+            RotationPeriod::Daily => LogWindow {
+                date,
+                start_minute: 0,
+                minutes: MINUTES_PER_DAY,
+            },
+            RotationPeriod::Minutes(minutes) => {
+                let minutes = minutes.clamp(1, MINUTES_PER_DAY);
+                let start_minute = minute_of_day / minutes * minutes;
+                LogWindow {
+                    date,
+                    start_minute,
+                    // 不能整除一天时，最后一个周期到当天结束为止
+                    minutes: minutes.min(MINUTES_PER_DAY - start_minute),
+                }
+            }
+        }
+    }
+}
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+// 一个日志文件覆盖的本地时间范围
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct LogWindow {
+    pub(crate) date: NaiveDate,
+    // 开始时间，当天的第几分钟
+    pub(crate) start_minute: u32,
+    // 覆盖的分钟数
+    pub(crate) minutes: u32,
+}
+
+impl LogWindow {
+    // 从文件名推断时间范围，只有小时时按一小时计算，有分钟时看不出周期长度，按到当天结束计算
+    pub(crate) fn of_file_name(name: &LogFileName) -> Self {
+        let start_minute = name.hour.unwrap_or(0) * 60 + name.minute.unwrap_or(0);
+        let minutes = match (name.hour, name.minute) {
+            (_, Some(_)) => MINUTES_PER_DAY - start_minute,
+            (Some(_), None) => 60,
+            (None, None) => MINUTES_PER_DAY,
+        };
+        Self {
+            date: name.date,
+            start_minute,
+            minutes,
+        }
+    }
+
+    // 周期开始的本地时间
+    pub(crate) fn start(&self) -> Option<NaiveDateTime> {
+        self.date
+            .and_hms_opt(self.start_minute / 60, self.start_minute % 60, 0)
+    }
+
+    pub(crate) fn hour(&self) -> u32 {
+        self.start_minute / 60
+    }
+
+    pub(crate) fn minute(&self) -> u32 {
+        self.start_minute % 60
+    }
+}
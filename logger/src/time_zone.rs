@@ -0,0 +1,119 @@
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, TimeZone, Timelike, Utc};
+use chrono_tz::Asia::Shanghai;
+use chrono_tz::Tz;
+use std::fmt;
+use std::str::FromStr;
+
+/// 日志使用的时区，决定日志文件按哪个时区的日期和小时划分
+///
+/// 默认为 `Asia/Shanghai`，没有记录时区的旧日志文件也按 `Asia/Shanghai` 处理
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogTimeZone {
+    /// IANA 时区，例如 `Europe/Berlin`，会处理夏令时
+    Named(Tz),
+    /// 固定偏移
+    FixedOffset(FixedOffset),
+    /// UTC
+    Utc,
+}
+
+impl Default for LogTimeZone {
+    fn default() -> Self {
+        LogTimeZone::Named(Shanghai)
+    }
+}
+
+impl LogTimeZone {
+    /// 按秒创建固定偏移的时区，超出 ±24 小时返回 `None`
+    pub fn from_offset_seconds(seconds: i32) -> Option<Self> {
+        FixedOffset::east_opt(seconds).map(LogTimeZone::FixedOffset)
+    }
+
+    /// UTC 时间转为该时区的本地时间
+    pub fn to_local(&self, utc: &DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            LogTimeZone::Named(tz) => utc.with_timezone(tz).naive_local(),
+            LogTimeZone::FixedOffset(offset) => utc.with_timezone(offset).naive_local(),
+            LogTimeZone::Utc => utc.naive_utc(),
+        }
+    }
+
+    /// 该时区的本地时间转为 UTC 时间
+    ///
+    /// 夏令时回拨导致本地时间出现两次时取较早的一个；夏令时开始时跳过的本地时间不存在，
+    /// 取跳过之后的第一个时刻，例如 `America/Santiago` 跳过 00:00 ~ 01:00 时，当天 00:00 对应 01:00。
+    /// 超出可以表示的时间范围时返回 `None`
+    pub fn to_utc(&self, local: &NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            LogTimeZone::Named(tz) => earliest_after(tz, local),
+            LogTimeZone::FixedOffset(offset) => earliest_after(offset, local),
+            LogTimeZone::Utc => Some(local.and_utc()),
+        }
+    }
+}
+
+// 跳过的时间段最长一天（例如 2011 年的 Pacific/Apia），按分钟向后查找第一个存在的本地时间，
+// 时区切换都在整分钟
+const MAX_GAP_MINUTES: i64 = 2 * 24 * 60;
+
+// 本地时间对应的最早时刻，本地时间不存在时取之后第一个存在的时刻
+fn earliest_after<T: TimeZone>(tz: &T, local: &NaiveDateTime) -> Option<DateTime<Utc>> {
+    if let Some(time) = tz.from_local_datetime(local).earliest() {
+        return Some(time.with_timezone(&Utc));
+    }
+    let start = local.with_second(0)?.with_nanosecond(0)?;
+    (1..=MAX_GAP_MINUTES).find_map(|minutes| {
+        let local = start.checked_add_signed(Duration::minutes(minutes))?;
+        tz.from_local_datetime(&local)
+            .earliest()
+            .map(|time| time.with_timezone(&Utc))
+    })
+}
+
+/// 格式与 `FromStr` 对应：`Asia/Shanghai`、`+08:00`、`UTC`
+impl fmt::Display for LogTimeZone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogTimeZone::Named(tz) => write!(f, "{}", tz.name()),
+            LogTimeZone::FixedOffset(offset) => {
+                let seconds = offset.local_minus_utc();
+                let sign = if seconds < 0 { '-' } else { '+' };
+                let minutes = seconds.abs() / 60;
+                write!(f, "{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+            }
+            LogTimeZone::Utc => write!(f, "UTC"),
+        }
+    }
+}
+
+impl FromStr for LogTimeZone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "UTC" {
+            return Ok(LogTimeZone::Utc);
+        }
+        if let Some(offset) = parse_offset(s) {
+            return Ok(LogTimeZone::FixedOffset(offset));
+        }
+        Tz::from_str(s)
+            .map(LogTimeZone::Named)
+            .map_err(|_| format!("Invalid time zone: {}", s))
+    }
+}
+
+// 解析 `+08:00` / `-05:30` 格式的固定偏移
+fn parse_offset(s: &str) -> Option<FixedOffset> {
+    let sign = match s.as_bytes().first()? {
+        b'+' => 1,
+        b'-' => -1,
+        _ => return None,
+    };
+    let (hours, minutes) = s[1..].split_once(':')?;
+    let hours: i32 = hours.parse().ok()?;
+    let minutes: i32 = minutes.parse().ok()?;
+    if !(0..60).contains(&minutes) {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
@@ -0,0 +1,208 @@
+use crate::mmap_writer::SharedMmapWriter;
+use crate::record::{current_thread_id, Level, Record};
+use chrono::Utc;
+use std::fmt;
+use std::time::{Duration, Instant};
+use tracing_core::field::{Field, Visit};
+use tracing_core::span::{self, Attributes, Id};
+use tracing_core::{Event, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::{LookupSpan, SpanRef};
+
+/// `tracing` 的 `Layer`，event 和 span 的进入、退出、关闭都作为结构化日志写入 mmap 日志文件
+///
+/// 需要开启 `tracing` feature。tag 为 event 或 span 的 target，日志中的键值对包括：
+/// - `spans`：从最外层开始的 span 名称，以 `:` 分隔，如 `request:db`
+/// - `<span 名称>.<字段>`：每个 span 的字段，如 `request.id=42`
+/// - event 自身的字段（`message` 作为日志内容）
+/// - 退出 span 时的 `time.elapsed`（本次进入的耗时），关闭 span 时的 `time.busy` 和 `time.idle`
+///
+/// ```ignore
+/// tracing::subscriber::set_global_default(tracing_subscriber::registry().with(MmapLayer::new(writer)))?;
+/// ```
+pub struct MmapLayer {
+    writer: SharedMmapWriter,
+}
+
+impl MmapLayer {
+    pub fn new(writer: SharedMmapWriter) -> Self {
+        Self { writer }
+    }
+
+    fn write(&self, metadata: &Metadata<'_>, message: String, fields: Vec<(String, String)>) {
+        let record = Record {
+            timestamp_ms: Utc::now().timestamp_millis(),
+            level: (*metadata.level()).into(),
+            tag: metadata.target().to_string(),
+            thread_id: current_thread_id(),
+            message,
+            fields,
+        };
+        // `Layer` 无法返回错误，写入失败的日志直接丢弃
+        let _ = self.writer.write_record(&record);
+    }
+}
+
+// span 的字段和耗时，保存在 span 的 extensions 中
+struct SpanData {
+    fields: Vec<(String, String)>,
+    created: Instant,
+    entered: Option<Instant>,
+    busy: Duration,
+}
+
+impl<S> Layer<S> for MmapLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = FieldVisitor::default();
+        attrs.record(&mut visitor);
+        span.extensions_mut().insert(SpanData {
+            fields: visitor.into_fields(),
+            created: Instant::now(),
+            entered: None,
+            busy: Duration::ZERO,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = FieldVisitor::default();
+        values.record(&mut visitor);
+        let mut extensions = span.extensions_mut();
+        if let Some(data) = extensions.get_mut::<SpanData>() {
+            data.fields.extend(visitor.into_fields());
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let mut fields = ctx
+            .event_scope(event)
+            .map(|scope| span_context(scope.from_root()))
+            .unwrap_or_default();
+        fields.extend(visitor.fields);
+        self.write(
+            event.metadata(),
+            visitor.message.unwrap_or_default(),
+            fields,
+        );
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        if let Some(data) = span.extensions_mut().get_mut::<SpanData>() {
+            data.entered = Some(Instant::now());
+        }
+        let fields = span_context(span.scope().from_root());
+        self.write(span.metadata(), format!("enter {}", span.name()), fields);
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let elapsed = span
+            .extensions_mut()
+            .get_mut::<SpanData>()
+            .and_then(|data| {
+                let elapsed = data.entered.take()?.elapsed();
+                data.busy += elapsed;
+                Some(elapsed)
+            })
+            .unwrap_or_default();
+        let mut fields = span_context(span.scope().from_root());
+        fields.push(("time.elapsed".to_string(), format!("{:?}", elapsed)));
+        self.write(span.metadata(), format!("exit {}", span.name()), fields);
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let (busy, idle) = span
+            .extensions()
+            .get::<SpanData>()
+            .map(|data| (data.busy, data.created.elapsed().saturating_sub(data.busy)))
+            .unwrap_or_default();
+        let mut fields = span_context(span.scope().from_root());
+        fields.push(("time.busy".to_string(), format!("{:?}", busy)));
+        fields.push(("time.idle".to_string(), format!("{:?}", idle)));
+        self.write(span.metadata(), format!("close {}", span.name()), fields);
+    }
+}
+
+impl From<tracing_core::Level> for Level {
+    fn from(level: tracing_core::Level) -> Self {
+        match level {
+            tracing_core::Level::ERROR => Level::Error,
+            tracing_core::Level::WARN => Level::Warn,
+            tracing_core::Level::INFO => Level::Info,
+            tracing_core::Level::DEBUG => Level::Debug,
+            _ => Level::Trace,
+        }
+    }
+}
+
+// 从最外层开始的 span 名称和字段
+fn span_context<'a, R>(spans: impl Iterator<Item = SpanRef<'a, R>>) -> Vec<(String, String)>
+where
+    R: LookupSpan<'a> + 'a,
+{
+    let mut names = Vec::new();
+    let mut fields = Vec::new();
+    for span in spans {
+        if let Some(data) = span.extensions().get::<SpanData>() {
+            for (key, value) in &data.fields {
+                fields.push((format!("{}.{}", span.name(), key), value.clone()));
+            }
+        }
+        names.push(span.name());
+    }
+    fields.insert(0, ("spans".to_string(), names.join(":")));
+    fields
+}
+
+// 收集字段，`message` 单独保存
+#[derive(Default)]
+struct FieldVisitor {
+    message: Option<String>,
+    fields: Vec<(String, String)>,
+}
+
+impl FieldVisitor {
+    // span 没有单独的日志内容，`message` 也作为普通字段
+    fn into_fields(mut self) -> Vec<(String, String)> {
+        if let Some(message) = self.message {
+            self.fields.insert(0, ("message".to_string(), message));
+        }
+        self.fields
+    }
+
+    fn push(&mut self, field: &Field, value: String) {
+        if field.name() == "message" {
+            self.message = Some(value);
+        } else {
+            self.fields.push((field.name().to_string(), value));
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, format!("{:?}", value));
+    }
+}
@@ -0,0 +1,84 @@
+use crate::mmap_config::LockMode;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io;
+use std::path::Path;
+
+// `LockMode::ProcessSuffix` 最多可以同时使用的 writer 数量（包括不带后缀的那一个）
+const MAX_SLOTS: usize = 16;
+
+/// 同一个 `base_dir` 下某种日志（明文或加密，以及日志流名称）的写入锁
+///
+/// 使用 `base_dir` 下的锁文件加文件锁（Unix 为 flock，Windows 为 LockFileEx），
+/// 进程退出时由系统自动释放。`slot` 为 0 时使用不带后缀的文件，
+/// 否则缓存和日志文件名带上 `_p{slot}` 后缀。
+pub(crate) struct WriterLock {
+    slot: usize,
+    _file: File, // 持有文件即持有锁
+}
+
+impl WriterLock {
+    // `name` 区分不同种类的日志，例如 `plain`、`encrypt_{stream}`
+    pub(crate) fn acquire(base_dir: &Path, name: &str, mode: LockMode) -> io::Result<Self> {
+        match mode {
+            LockMode::FailFast => {
+                let file = open_lock_file(base_dir, name, 0)?;
+                match file.try_lock() {
+                    Ok(()) => Ok(Self::new(0, file)),
+                    Err(TryLockError::WouldBlock) => Err(locked_error()),
+                    Err(TryLockError::Error(err)) => Err(err),
+                }
+            }
+            LockMode::Wait => {
+                let file = open_lock_file(base_dir, name, 0)?;
+                file.lock()?;
+                Ok(Self::new(0, file))
+            }
+            LockMode::ProcessSuffix => {
+                // 依次尝试各个序号，进程崩溃后留下的缓存会被下一个拿到该序号的 writer 恢复
+                for slot in 0..MAX_SLOTS {
+                    let file = open_lock_file(base_dir, name, slot)?;
+                    match file.try_lock() {
+                        Ok(()) => return Ok(Self::new(slot, file)),
+                        Err(TryLockError::WouldBlock) => continue,
+                        Err(TryLockError::Error(err)) => return Err(err),
+                    }
+                }
+                Err(locked_error())
+            }
+        }
+    }
+
+    fn new(slot: usize, file: File) -> Self {
+        Self { slot, _file: file }
+    }
+
+    pub(crate) fn slot(&self) -> usize {
+        self.slot
+    }
+}
+
+/// 不同序号 writer 的文件名后缀，序号 0 没有后缀
+pub(crate) fn slot_suffix(slot: usize) -> String {
+    if slot == 0 {
+        String::new()
+    } else {
+        format!("_p{}", slot)
+    }
+}
+
+fn open_lock_file(base_dir: &Path, name: &str, slot: usize) -> io::Result<File> {
+    let name = format!("mmap_{}{}.lock", name, slot_suffix(slot));
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(base_dir.join(name))
+}
+
+fn locked_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::WouldBlock,
+        "base_dir is locked by another writer",
+    )
+}
@@ -1,25 +1,27 @@
+use chrono::{Duration, Utc};
 use logger::mmap_config::MmapConfig;
-use logger::mmap_writer::MmapWriter;
+use logger::mmap_writer::{MmapWriter, SharedMmapWriter};
 use std::cell::RefCell;
-use std::fs::{remove_dir_all, File};
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::fs::{self, remove_dir_all, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 
 pub struct BaseTest {
-    mmap_writer: Arc<RefCell<MmapWriter>>,
+    mmap_writer: Rc<RefCell<MmapWriter>>,
 }
 
 impl BaseTest {
     pub fn new(app_key: &str, base_dir: &PathBuf, is_encrypt: bool, clean: bool) -> Self {
         Self::clean_old_file(base_dir.clone(), clean);
         let config = MmapConfig::new(app_key, is_encrypt);
-        let mmap_writer = MmapWriter::try_new(&base_dir, config).unwrap();
+        let mmap_writer = MmapWriter::try_new(base_dir, config).unwrap();
         Self {
-            mmap_writer: Arc::new(RefCell::new(mmap_writer)),
+            mmap_writer: Rc::new(RefCell::new(mmap_writer)),
         }
     }
 
-    pub fn get_mmap_writer(&self) -> Arc<RefCell<MmapWriter>> {
+    pub fn get_mmap_writer(&self) -> Rc<RefCell<MmapWriter>> {
         self.mmap_writer.clone()
     }
 
@@ -31,7 +33,7 @@ impl BaseTest {
 }
 
 pub fn create_subdir_and_file(
-    base_dir: &PathBuf,
+    base_dir: &Path,
     subdir_name: &str,
     file_name: &str,
 ) -> std::io::Result<()> {
@@ -51,3 +53,76 @@ pub fn create_subdir_and_file(
 
     Ok(())
 }
+
+/// 可以导出日志的 writer
+pub trait ExportLogs {
+    fn export_buffer(&self, start_ms: i64, end_ms: i64) -> Vec<u8>;
+}
+
+impl ExportLogs for MmapWriter {
+    fn export_buffer(&self, start_ms: i64, end_ms: i64) -> Vec<u8> {
+        let mut buffer = vec![];
+        self.export_to(start_ms, end_ms, &mut buffer).unwrap();
+        buffer
+    }
+}
+
+impl ExportLogs for SharedMmapWriter {
+    fn export_buffer(&self, start_ms: i64, end_ms: i64) -> Vec<u8> {
+        let mut buffer = vec![];
+        self.export_to(start_ms, end_ms, &mut buffer).unwrap();
+        buffer
+    }
+}
+
+/// 导出时间范围 `[start_ms, end_ms]` 内的日志，按行返回
+pub fn export_lines(writer: &impl ExportLogs, start_ms: i64, end_ms: i64) -> Vec<String> {
+    let buffer = writer.export_buffer(start_ms, end_ms);
+    BufReader::new(&buffer[..])
+        .lines()
+        .map(|line| line.unwrap())
+        .collect()
+}
+
+/// 导出当前时间前后一分钟的日志，按行返回
+pub fn export_recent_lines(writer: &impl ExportLogs) -> Vec<String> {
+    let (start_ms, end_ms) = recent_range();
+    export_lines(writer, start_ms, end_ms)
+}
+
+/// 当前时间前后一分钟的时间范围，毫秒时间戳
+pub fn recent_range() -> (i64, i64) {
+    let now = Utc::now();
+    (
+        (now - Duration::minutes(1)).timestamp_millis(),
+        (now + Duration::minutes(1)).timestamp_millis(),
+    )
+}
+
+/// `dir` 下所有的日志文件，不包括导出的 `export.log`，按路径排序
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    collect_log_files(dir, &mut files);
+    files.sort();
+    files
+}
+
+/// `dir` 下唯一的日志文件
+pub fn find_log_file(dir: &Path) -> PathBuf {
+    let mut files = log_files(dir);
+    assert_eq!(files.len(), 1, "log files: {:?}", files);
+    files.remove(0)
+}
+
+fn collect_log_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect_log_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "log")
+            && path.file_name().is_some_and(|name| name != "export.log")
+        {
+            files.push(path);
+        }
+    }
+}
@@ -7,10 +7,10 @@ pub mod build_info_test {
     fn test() {
         let map: HashMap<String, String> =
             serde_json::from_str(RUST_SDK_BUILD_INFO).expect("Invalid JSON");
-        assert!(map.get("my_version").is_some());
-        assert!(map.get("my_commit").is_some());
-        assert!(map.get("my_build_time").is_some());
-        assert!(map.get("my_target").is_some());
+        assert!(map.contains_key("my_version"));
+        assert!(map.contains_key("my_commit"));
+        assert!(map.contains_key("my_build_time"));
+        assert!(map.contains_key("my_target"));
         assert_eq!(map.len(), 4);
     }
 }
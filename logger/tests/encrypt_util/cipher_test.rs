@@ -0,0 +1,282 @@
+#[cfg(test)]
+pub mod cipher_test {
+    use logger::encrypt_util::{
+        encrypt_line, generate_key_pair, parse_key, public_key_of, CipherMode, CipherParams,
+        KeyDerivation, LogCipher,
+    };
+    use logger::log_file::{record_aad, LogRecord, RECORD_FLAG_BINARY, RECORD_FLAG_ENCRYPTED};
+
+    const KDFS: [KeyDerivation; 4] = [
+        KeyDerivation::Hkdf,
+        KeyDerivation::Pbkdf2 { iterations: 1000 },
+        KeyDerivation::Argon2 {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        },
+        KeyDerivation::ARGON2,
+    ];
+
+    const MODES: [CipherMode; 3] = [
+        CipherMode::AesEcb,
+        CipherMode::Aes256Gcm,
+        CipherMode::ChaCha20Poly1305,
+    ];
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        for mode in MODES {
+            let cipher = LogCipher::new(CipherParams::new(mode), "123321").unwrap();
+            assert_eq!(cipher.params().mode, mode);
+            for text in ["", "abc", "一万年太久，只争朝夕!", "a\nb\0c"] {
+                let encrypted = cipher.encrypt(text.as_bytes()).unwrap();
+                assert_ne!(encrypted, text.as_bytes());
+                assert_eq!(cipher.decrypt(&encrypted).unwrap(), text.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_nonce() {
+        for mode in [CipherMode::Aes256Gcm, CipherMode::ChaCha20Poly1305] {
+            let cipher = LogCipher::new(CipherParams::new(mode), "123321").unwrap();
+            let first = cipher.encrypt(b"same text").unwrap();
+            let second = cipher.encrypt(b"same text").unwrap();
+            assert_ne!(first, second);
+        }
+    }
+
+    #[test]
+    fn test_tampered_or_wrong_key() {
+        for mode in [CipherMode::Aes256Gcm, CipherMode::ChaCha20Poly1305] {
+            let cipher = LogCipher::new(CipherParams::new(mode), "123321").unwrap();
+            let mut encrypted = cipher.encrypt(b"important").unwrap();
+
+            let other = LogCipher::new(CipherParams::new(mode), "654321").unwrap();
+            assert!(other.decrypt(&encrypted).is_err());
+
+            // 修改最后一个十六进制字符
+            let last = encrypted.len() - 1;
+            encrypted[last] = if encrypted[last] == b'0' { b'1' } else { b'0' };
+            assert!(cipher.decrypt(&encrypted).is_err());
+
+            assert!(cipher.decrypt(b"00").is_err());
+        }
+    }
+
+    #[test]
+    fn test_ecb_compatible() {
+        let cipher = LogCipher::new(CipherParams::default(), "123321").unwrap();
+        let encrypted = encrypt_line("123321", "旧版本日志").unwrap();
+        assert_eq!(
+            cipher.decrypt(encrypted.as_bytes()).unwrap(),
+            "旧版本日志".as_bytes()
+        );
+    }
+
+    #[test]
+    fn test_mode_str() {
+        for mode in MODES {
+            assert_eq!(mode.to_string().parse::<CipherMode>(), Ok(mode));
+        }
+        assert_eq!("AES-256-GCM".parse(), Ok(CipherMode::Aes256Gcm));
+        assert!("aes-128-cbc".parse::<CipherMode>().is_err());
+    }
+
+    #[test]
+    fn test_params_encode_decode() {
+        for mode in MODES {
+            let params = CipherParams::new(mode);
+            assert_eq!(CipherParams::decode(&params.encode()), Some(params));
+        }
+        // 不认识的字段跳过
+        let mut bytes = vec![9, 2, 0, 0xAA, 0xBB];
+        bytes.extend_from_slice(&CipherParams::new(CipherMode::ChaCha20Poly1305).encode());
+        assert_eq!(
+            CipherParams::decode(&bytes),
+            Some(CipherParams::new(CipherMode::ChaCha20Poly1305))
+        );
+        // 未知的加密模式
+        assert_eq!(CipherParams::decode(&[1, 1, 0, 99]), None);
+        assert_eq!(CipherParams::decode(&[]), None);
+    }
+
+    #[test]
+    fn test_public_key() {
+        let (private_key, public_key) = generate_key_pair().unwrap();
+        assert_eq!(public_key_of(&private_key), public_key);
+
+        for mode in [CipherMode::Aes256Gcm, CipherMode::ChaCha20Poly1305] {
+            let cipher = LogCipher::with_public_key(mode, &public_key).unwrap();
+            let params = cipher.params().clone();
+            assert_eq!(params.mode, mode);
+            assert!(params.is_public_key());
+            let encrypted = cipher.encrypt("公钥加密".as_bytes()).unwrap();
+
+            let decrypt = LogCipher::with_private_key(params.clone(), &private_key).unwrap();
+            assert_eq!(decrypt.decrypt(&encrypted).unwrap(), "公钥加密".as_bytes());
+
+            // 其他私钥、或者把临时公钥当作 app_key 的参数都无法解密
+            let (other_key, _) = generate_key_pair().unwrap();
+            let other = LogCipher::with_private_key(params.clone(), &other_key).unwrap();
+            assert!(other.decrypt(&encrypted).is_err());
+            let app_key = LogCipher::new(params, "123321").unwrap();
+            assert!(app_key.decrypt(&encrypted).is_err());
+        }
+
+        // 每次会话的临时公钥不同
+        let first = LogCipher::with_public_key(CipherMode::Aes256Gcm, &public_key).unwrap();
+        let second = LogCipher::with_public_key(CipherMode::Aes256Gcm, &public_key).unwrap();
+        assert_ne!(first.params(), second.params());
+    }
+
+    #[test]
+    fn test_public_key_invalid() {
+        let (private_key, public_key) = generate_key_pair().unwrap();
+        assert!(LogCipher::with_public_key(CipherMode::AesEcb, &public_key).is_err());
+        // 低阶点协商出的共享密钥全为 0
+        assert!(LogCipher::with_public_key(CipherMode::Aes256Gcm, &[0u8; 32]).is_err());
+        // 不是公钥加密的参数
+        let params = CipherParams::new(CipherMode::Aes256Gcm);
+        assert!(LogCipher::with_private_key(params, &private_key).is_err());
+    }
+
+    #[test]
+    fn test_params_with_ephemeral_key() {
+        let params = CipherParams {
+            mode: CipherMode::ChaCha20Poly1305,
+            ephemeral_public_key: Some([7u8; 32]),
+            ..CipherParams::default()
+        };
+        assert_eq!(CipherParams::decode(&params.encode()), Some(params));
+    }
+
+    #[test]
+    fn test_parse_key() {
+        let (private_key, _) = generate_key_pair().unwrap();
+        let hex_key = format!("  {}\n", hex::encode(private_key));
+        assert_eq!(parse_key(&hex_key).unwrap(), private_key);
+        assert!(parse_key("abcd").is_err());
+        assert!(parse_key("not hex").is_err());
+    }
+
+    #[test]
+    fn test_key_derivation() {
+        for kdf in KDFS {
+            for mode in MODES {
+                let params = CipherParams::with_key_derivation(mode, kdf).unwrap();
+                assert_eq!(params.kdf, kdf);
+                assert!(params.salt.is_some());
+                let cipher = LogCipher::new(params.clone(), "123321").unwrap();
+                let encrypted = cipher.encrypt(b"derived key").unwrap();
+
+                // 按文件头中的参数重新派生密钥
+                let decoded = CipherParams::decode(&params.encode()).unwrap();
+                assert_eq!(decoded, params);
+                let decrypt = LogCipher::new(decoded, "123321").unwrap();
+                assert_eq!(decrypt.decrypt(&encrypted).unwrap(), b"derived key");
+
+                // salt 不同时密钥不同
+                let other = CipherParams::with_key_derivation(mode, kdf).unwrap();
+                assert_ne!(other.salt, params.salt);
+                let other = LogCipher::new(other, "123321").unwrap();
+                assert_ne!(
+                    other.decrypt(&encrypted).ok(),
+                    Some(b"derived key".to_vec())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_key_derivation_invalid() {
+        let huge = KeyDerivation::Argon2 {
+            memory_kib: u32::MAX,
+            iterations: 1,
+            parallelism: 1,
+        };
+        assert!(!huge.is_valid());
+        assert!(!KeyDerivation::Pbkdf2 { iterations: 0 }.is_valid());
+        assert!(!KeyDerivation::Pbkdf2 {
+            iterations: u32::MAX
+        }
+        .is_valid());
+        assert!(!KeyDerivation::Argon2 {
+            memory_kib: 64,
+            iterations: u32::MAX,
+            parallelism: 1,
+        }
+        .is_valid());
+        assert!(KeyDerivation::ARGON2.is_valid());
+        assert!(KeyDerivation::PBKDF2.is_valid());
+
+        // 文件头中的参数无效或缺少 salt 时无法解密
+        let mut params = CipherParams::with_key_derivation(CipherMode::Aes256Gcm, KDFS[0]).unwrap();
+        params.kdf = huge;
+        assert!(LogCipher::new(params.clone(), "123321").is_err());
+        params.kdf = KeyDerivation::Hkdf;
+        params.salt = None;
+        assert!(LogCipher::new(params, "123321").is_err());
+
+        // 未知的密钥派生方式
+        let mut bytes = CipherParams::new(CipherMode::Aes256Gcm).encode();
+        bytes.extend_from_slice(&[3, 1, 0, 9]);
+        assert_eq!(CipherParams::decode(&bytes), None);
+    }
+
+    #[test]
+    fn test_key_derivation_display() {
+        assert_eq!(KeyDerivation::Legacy.to_string(), "legacy");
+        assert_eq!(
+            KeyDerivation::PBKDF2.to_string(),
+            "pbkdf2-sha256(iterations=600000)"
+        );
+        assert_eq!(
+            KeyDerivation::ARGON2.to_string(),
+            "argon2id(m=19456KB, t=2, p=1)"
+        );
+    }
+
+    #[test]
+    fn test_params_with_key_id() {
+        let params = CipherParams {
+            key_id: Some("2025-06".to_string()),
+            ..CipherParams::with_key_derivation(CipherMode::Aes256Gcm, KeyDerivation::Hkdf).unwrap()
+        };
+        assert_eq!(CipherParams::decode(&params.encode()), Some(params));
+    }
+
+    #[test]
+    fn test_binary() {
+        for mode in MODES {
+            let cipher = LogCipher::new(CipherParams::new(mode), "123321").unwrap();
+            let aad = record_aad(RECORD_FLAG_ENCRYPTED | RECORD_FLAG_BINARY, None);
+            let encrypted = cipher.encrypt_binary(b"binary", &aad).unwrap();
+            assert_eq!(cipher.decrypt_binary(&encrypted, &aad).unwrap(), b"binary");
+            // AEAD 模式的附加数据不同时无法解密
+            if mode != CipherMode::AesEcb {
+                assert!(cipher.decrypt_binary(&encrypted, &[]).is_err());
+            }
+
+            // 十六进制密文是二进制密文的两倍长
+            let hex_encrypted = cipher.encrypt(b"binary").unwrap();
+            assert_eq!(hex_encrypted.len(), encrypted.len() * 2);
+
+            let records = [
+                LogRecord {
+                    flags: RECORD_FLAG_ENCRYPTED | RECORD_FLAG_BINARY,
+                    timestamp_ms: None,
+                    payload: &encrypted,
+                },
+                LogRecord {
+                    flags: RECORD_FLAG_ENCRYPTED,
+                    timestamp_ms: None,
+                    payload: &hex_encrypted,
+                },
+            ];
+            for record in records {
+                assert_eq!(cipher.decrypt_record(&record).unwrap(), b"binary");
+            }
+        }
+    }
+}
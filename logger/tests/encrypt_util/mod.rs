@@ -1 +1,2 @@
+mod cipher_test;
 mod encrypt_test;
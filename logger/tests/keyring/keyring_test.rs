@@ -0,0 +1,87 @@
+#[cfg(test)]
+pub mod keyring_test {
+    use logger::keyring::Keyring;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_new() {
+        let keyring = Keyring::new("v2", "new-key")
+            .with_retired_key("v1", "old-key")
+            .with_retired_key("v0", "older-key");
+        assert!(keyring.is_valid());
+        assert_eq!(keyring.active_id(), "v2");
+        assert_eq!(keyring.active_key(), "new-key");
+        assert_eq!(keyring.get("v1"), Some("old-key"));
+        assert_eq!(keyring.get("v3"), None);
+        assert_eq!(
+            keyring.key_ids().collect::<Vec<_>>(),
+            vec!["v2", "v1", "v0"]
+        );
+
+        // 相同 id 替换
+        let keyring = keyring.with_retired_key("v1", "replaced");
+        assert_eq!(keyring.get("v1"), Some("replaced"));
+        assert_eq!(keyring.key_ids().count(), 3);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(!Keyring::new("", "key").is_valid());
+        assert!(!Keyring::new("v 1", "key").is_valid());
+        assert!(!Keyring::new("active", "key").is_valid());
+        assert!(!Keyring::new("v1", "").is_valid());
+        assert!(!Keyring::new("v1", "key")
+            .with_retired_key("a=b", "key")
+            .is_valid());
+        assert!(!Keyring::new(&"v".repeat(129), "key").is_valid());
+        assert!(Keyring::new(&"v".repeat(128), "key").is_valid());
+    }
+
+    #[test]
+    fn test_parse() {
+        let keyring: Keyring = "
+            # 当前使用的密钥
+            v1 = old-key
+            active = v2
+            v2 = new=key
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(keyring.active_id(), "v2");
+        assert_eq!(keyring.active_key(), "new=key");
+        assert_eq!(keyring.get("v1"), Some("old-key"));
+        assert_eq!(keyring.key_ids().collect::<Vec<_>>(), vec!["v2", "v1"]);
+
+        assert!("v1 = key".parse::<Keyring>().is_err());
+        assert!("active = v2\nv1 = key".parse::<Keyring>().is_err());
+        assert!("active = v1\nv1 = key\nv1 = other"
+            .parse::<Keyring>()
+            .is_err());
+        assert!("active = v1\nv1".parse::<Keyring>().is_err());
+        assert!("active = v1\nv1 =".parse::<Keyring>().is_err());
+    }
+
+    #[test]
+    fn test_load() {
+        let base_dir = PathBuf::from("../target/tmp_log_keyring_file");
+        let _ = fs::remove_dir_all(&base_dir);
+        fs::create_dir_all(&base_dir).unwrap();
+        let path = base_dir.join("keyring.txt");
+        fs::write(&path, "active = v1\nv1 = key\n").unwrap();
+        assert_eq!(Keyring::load(&path).unwrap(), Keyring::new("v1", "key"));
+
+        fs::write(&path, "v1 = key\n").unwrap();
+        let err = Keyring::load(&path).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_debug_hides_keys() {
+        let keyring = Keyring::new("v2", "secret-new").with_retired_key("v1", "secret-old");
+        let debug = format!("{:?}", keyring);
+        assert!(debug.contains("v2") && debug.contains("v1"));
+        assert!(!debug.contains("secret"));
+    }
+}
@@ -0,0 +1 @@
+mod keyring_test;
@@ -1,4 +1,14 @@
+#![allow(clippy::module_inception)]
+
 mod base;
 mod encrypt_util;
+mod keyring;
+mod log_backend;
+mod log_file;
+mod log_reader;
 mod mmap_config;
 mod mmap_writer;
+mod naming;
+mod record;
+mod time_zone;
+mod tracing_layer;
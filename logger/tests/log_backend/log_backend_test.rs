@@ -0,0 +1,84 @@
+/// 测试 `log` crate 后端，需要开启 `log` feature
+#[cfg(all(test, feature = "log"))]
+pub mod log_backend_test {
+    use crate::base::base_test::export_recent_lines;
+    use log::{LevelFilter, Log};
+    use logger::log_backend::{init, MmapLogger};
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::SharedMmapWriter;
+    use logger::record::LineFormat;
+    use std::fs::remove_dir_all;
+    use std::path::PathBuf;
+
+    fn new_config() -> MmapConfig {
+        let mut config = MmapConfig::new("12345", false);
+        config.set_line_format(LineFormat::new("{level} {tag} {message}"));
+        config
+    }
+
+    fn log_record(logger: &MmapLogger, level: log::Level, target: &str, message: &str) {
+        logger.log(
+            &log::Record::builder()
+                .level(level)
+                .target(target)
+                .args(format_args!("{}", message))
+                .build(),
+        );
+    }
+
+    #[test]
+    fn test_target_levels() {
+        let base_dir = PathBuf::from("../target/tmp_log_log_backend_levels");
+        let _ = remove_dir_all(&base_dir);
+        let writer = SharedMmapWriter::try_new(&base_dir, new_config()).unwrap();
+        let logger = MmapLogger::new(writer.clone())
+            .with_max_level(LevelFilter::Info)
+            .with_target_level("hyper", LevelFilter::Warn)
+            .with_target_level("app::db", LevelFilter::Trace);
+
+        assert_eq!(logger.level_for("app"), LevelFilter::Info);
+        assert_eq!(logger.level_for("hyper::client"), LevelFilter::Warn);
+        assert_eq!(logger.level_for("hyperx"), LevelFilter::Info);
+        assert_eq!(logger.level_for("app::db::pool"), LevelFilter::Trace);
+
+        log_record(&logger, log::Level::Debug, "app", "skipped");
+        log_record(&logger, log::Level::Info, "app", "started");
+        log_record(&logger, log::Level::Info, "hyper::client", "skipped");
+        log_record(&logger, log::Level::Error, "hyper::client", "reset");
+        log_record(&logger, log::Level::Trace, "app::db::pool", "checkout");
+        logger.flush();
+
+        assert_eq!(
+            export_recent_lines(&writer),
+            vec![
+                "INFO app started",
+                "ERROR hyper::client reset",
+                "TRACE app::db::pool checkout",
+            ]
+        );
+        drop(logger);
+        drop(writer);
+        let _ = remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_install() {
+        let base_dir = PathBuf::from("../target/tmp_log_log_backend_install");
+        let _ = remove_dir_all(&base_dir);
+        init(&base_dir, new_config(), LevelFilter::Debug).unwrap();
+        assert_eq!(log::max_level(), LevelFilter::Debug);
+
+        log::debug!(target: "install", "hello {}", 42);
+        log::trace!(target: "install", "skipped");
+        log::logger().flush();
+
+        // 全局 logger 只能设置一次
+        let writer = SharedMmapWriter::try_new(&base_dir, new_config()).unwrap();
+        let err = MmapLogger::new(writer.clone()).install().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+
+        assert_eq!(export_recent_lines(&writer), vec!["DEBUG install hello 42"]);
+        drop(writer);
+        let _ = remove_dir_all(&base_dir);
+    }
+}
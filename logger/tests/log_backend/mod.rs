@@ -0,0 +1 @@
+mod log_backend_test;
@@ -0,0 +1,207 @@
+#[cfg(test)]
+pub mod log_header_test {
+    use crate::base::base_test::BaseTest;
+    use chrono::{Datelike, Timelike, Utc};
+    use chrono_tz::Asia::Shanghai;
+    use logger::build_info::{RUST_SDK_COMMIT, RUST_SDK_TARGET, RUST_SDK_VER};
+    use logger::encrypt_util::{CipherMode, CipherParams};
+    use logger::log_file::{
+        encode_timed_record, read_log_data, read_log_file, Compression, LogHeader, LOG_HEADER_SIZE,
+        LOG_VERSION,
+    };
+    use logger::time_zone::LogTimeZone;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn current_log_file(base_dir: &Path) -> PathBuf {
+        let now = Utc::now().with_timezone(&Shanghai);
+        let day = format!("{:04}{:02}{:02}", now.year(), now.month(), now.day());
+        let file_name = format!("{}_{:02}_plain.log", day, now.hour());
+        base_dir.join(day).join(file_name)
+    }
+
+    #[test]
+    fn test_encode_parse() {
+        let header = LogHeader {
+            committed_len: 1234,
+            ..LogHeader::default()
+        };
+        let bytes = header.encode();
+        assert_eq!(bytes.len(), LOG_HEADER_SIZE);
+        assert_eq!(LogHeader::parse(&bytes), Some(header));
+
+        // 没有 magic 的旧版本文件
+        assert_eq!(LogHeader::parse(b"start-abc-end\nstart-def-end\n"), None);
+        assert_eq!(LogHeader::parse(&bytes[..LOG_HEADER_SIZE - 1]), None);
+    }
+
+    #[test]
+    fn test_encode_parse_time_zone() {
+        let time_zone: LogTimeZone = "America/New_York".parse().unwrap();
+        let header = LogHeader {
+            committed_len: 1234,
+            time_zone: Some(time_zone),
+            ..LogHeader::default()
+        };
+        let bytes = header.encode();
+        assert!(bytes.len() > LOG_HEADER_SIZE);
+
+        let parsed = LogHeader::parse(&bytes).unwrap();
+        assert_eq!(parsed.header_len, bytes.len());
+        assert_eq!(parsed.committed_len, 1234);
+        assert_eq!(parsed.time_zone, Some(time_zone));
+
+        // 只有固定部分时仍能得到数据位置
+        let fixed = LogHeader::parse(&bytes[..LOG_HEADER_SIZE]).unwrap();
+        assert_eq!(fixed.header_len, bytes.len());
+        assert_eq!(fixed.time_zone, None);
+    }
+
+    #[test]
+    fn test_encode_parse_cipher() {
+        let header = LogHeader {
+            committed_len: 1234,
+            cipher: Some(CipherParams::new(CipherMode::ChaCha20Poly1305)),
+            ..LogHeader::default()
+        };
+        let bytes = header.encode();
+        let parsed = LogHeader::parse(&bytes).unwrap();
+        assert_eq!(parsed.header_len, bytes.len());
+        assert_eq!(parsed.committed_len, 1234);
+        assert_eq!(parsed.cipher, header.cipher);
+
+        // 没有记录加密参数
+        let parsed = LogHeader::parse(&LogHeader::default().encode()).unwrap();
+        assert_eq!(parsed.cipher, None);
+    }
+
+    #[test]
+    fn test_encode_parse_writer_info() {
+        let header = LogHeader {
+            committed_len: 1234,
+            encrypted: Some(true),
+            compression: Some(Compression::None),
+            created_at: Some(1_747_734_330_123),
+            pid: Some(4321),
+            sdk_version: Some("0.1.0".to_string()),
+            sdk_commit: Some("abc1234".to_string()),
+            sdk_target: Some("aarch64-apple-ios".to_string()),
+            ..LogHeader::default()
+        };
+        let bytes = header.encode();
+        let parsed = LogHeader::parse(&bytes).unwrap();
+        assert_eq!(
+            parsed,
+            LogHeader {
+                header_len: bytes.len(),
+                ..header
+            }
+        );
+
+        // 不认识的压缩方式
+        let mut bytes = LogHeader {
+            compression: Some(Compression::None),
+            ..LogHeader::default()
+        }
+        .encode();
+        let last = bytes.len() - 1;
+        bytes[last] = 0xFF;
+        assert_eq!(LogHeader::parse(&bytes).unwrap().compression, None);
+    }
+
+    #[test]
+    fn test_committed_len() {
+        let base_dir = PathBuf::from("../target/tmp_log_header");
+        let base_test = BaseTest::new("12345", &base_dir, false, true);
+        let rc_writer = base_test.get_mmap_writer();
+        let mut writer = rc_writer.borrow_mut();
+
+        writer.write("start-0-end").unwrap();
+        writer.flush().unwrap();
+        writer.write("start-1-end").unwrap();
+        writer.flush().unwrap();
+
+        // 每条记录都带有写入时间
+        let timestamps: Vec<_> = read_log_file(&current_log_file(&base_dir))
+            .unwrap()
+            .records(0)
+            .map(|r| r.unwrap().timestamp_ms.unwrap())
+            .collect();
+        assert_eq!(timestamps.len(), 2);
+        assert!(timestamps[0] <= timestamps[1]);
+        assert!((Utc::now().timestamp_millis() - timestamps[1]).abs() < 60_000);
+        let mut expected = encode_timed_record(0, timestamps[0], b"start-0-end");
+        expected.extend_from_slice(&encode_timed_record(0, timestamps[1], b"start-1-end"));
+
+        let content = fs::read(current_log_file(&base_dir)).unwrap();
+        let header = LogHeader::parse(&content).unwrap();
+        assert_eq!(header.version, LOG_VERSION);
+        assert!(header.header_len > LOG_HEADER_SIZE);
+        assert_eq!(header.committed_len, expected.len() as u64);
+        assert_eq!(header.time_zone, Some(LogTimeZone::default()));
+        assert_eq!(header.encrypted, Some(false));
+        assert_eq!(header.compression, Some(Compression::None));
+        assert_eq!(header.cipher, None);
+        assert_eq!(header.pid, Some(std::process::id()));
+        let created_at = header.created_at.unwrap();
+        assert!((Utc::now().timestamp_millis() - created_at).abs() < 60_000);
+        assert_eq!(header.sdk_version.as_deref(), Some(RUST_SDK_VER));
+        assert_eq!(header.sdk_commit.as_deref(), Some(RUST_SDK_COMMIT));
+        assert_eq!(header.sdk_target.as_deref(), Some(RUST_SDK_TARGET));
+
+        // 文件尾部仍然是 mmap 预留的空间
+        assert!(content.len() > header.header_len + expected.len());
+        let data = read_log_data(&current_log_file(&base_dir)).unwrap();
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_trailing_zero_record() {
+        let base_dir = PathBuf::from("../target/tmp_log_header_zero");
+        let base_test = BaseTest::new("12345", &base_dir, false, true);
+        let rc_writer = base_test.get_mmap_writer();
+        let mut writer = rc_writer.borrow_mut();
+
+        // 数据以 0x00 结尾也不会被当作填充
+        writer.write_bytes(b"start-0-end\0\0").unwrap();
+        writer.flush().unwrap();
+
+        let content = read_log_file(&current_log_file(&base_dir)).unwrap();
+        let payloads: Vec<_> = content.records(0).map(|r| r.unwrap().payload).collect();
+        assert_eq!(payloads, vec![&b"start-0-end\0\0"[..]]);
+    }
+
+    #[test]
+    fn test_append_to_legacy_file() {
+        let base_dir = PathBuf::from("../target/tmp_log_header_legacy");
+        let base_test = BaseTest::new("12345", &base_dir, false, true);
+
+        // 旧版本文件：没有文件头，尾部用 0x00 填充
+        let path = current_log_file(&base_dir);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let mut legacy = b"start-old-end\n".to_vec();
+        legacy.resize(1024, 0);
+        fs::write(&path, &legacy).unwrap();
+        assert_eq!(read_log_data(&path).unwrap(), b"start-old-end\n");
+
+        let rc_writer = base_test.get_mmap_writer();
+        let mut writer = rc_writer.borrow_mut();
+        writer.write("start-new-end").unwrap();
+        writer.flush().unwrap();
+
+        // 旧版本的行转换为记录后继续追加
+        let content = read_log_file(&path).unwrap();
+        assert!(content.is_framed());
+        let payloads: Vec<_> = content.records(0).map(|r| r.unwrap().payload).collect();
+        assert_eq!(payloads, vec![&b"start-old-end"[..], &b"start-new-end"[..]]);
+
+        // 转换后的文件头按 writer 的配置记录时区、周期长度和创建文件的进程
+        let header = content.header().unwrap();
+        assert_eq!(header.time_zone, Some(LogTimeZone::default()));
+        assert_eq!(header.window_minutes, Some(60));
+        assert_eq!(header.encrypted, Some(false));
+        assert_eq!(header.cipher, None);
+        assert_eq!(header.pid, Some(std::process::id()));
+        assert_eq!(header.sdk_version.as_deref(), Some(RUST_SDK_VER));
+    }
+}
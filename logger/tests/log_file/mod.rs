@@ -0,0 +1,2 @@
+mod log_header_test;
+mod record_test;
@@ -0,0 +1,298 @@
+#[cfg(test)]
+pub mod record_test {
+    use crate::base::base_test::BaseTest;
+    use chrono::{Duration, Utc};
+    use logger::log_file::{
+        encode_record, encode_timed_record, read_log_file, LogHeader, RecordReader,
+        RECORD_FLAG_ENCRYPTED, RECORD_FLAG_TIMESTAMP, RECORD_HEADER_SIZE,
+    };
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::MmapWriter;
+    use logger::record::{unescape_line, ExportFormat};
+    use std::fs;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    fn framed_file(path: &Path, data: &[u8]) {
+        let header = LogHeader {
+            committed_len: data.len() as u64,
+            ..LogHeader::default()
+        };
+        let mut content = header.encode().to_vec();
+        content.extend_from_slice(data);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let path = PathBuf::from("../target/tmp_log_record/encode.log");
+        let mut data = encode_record(0, b"line1\nline2");
+        data.extend_from_slice(&encode_record(RECORD_FLAG_ENCRYPTED, b"\0\x01\x02\0"));
+        data.extend_from_slice(&encode_record(0, b""));
+        framed_file(&path, &data);
+
+        let content = read_log_file(&path).unwrap();
+        let records: Vec<_> = content.records(0).map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].payload, b"line1\nline2");
+        assert!(!records[0].is_encrypted());
+        assert_eq!(records[1].payload, b"\0\x01\x02\0");
+        assert!(records[1].is_encrypted());
+        assert_eq!(records[2].payload, b"");
+    }
+
+    #[test]
+    fn test_crc_mismatch() {
+        let path = PathBuf::from("../target/tmp_log_record/crc.log");
+        let mut data = encode_record(0, b"start-0-end");
+        let mut broken = encode_record(0, b"start-1-end");
+        broken[RECORD_HEADER_SIZE] ^= 0xff;
+        data.extend_from_slice(&broken);
+        data.extend_from_slice(&encode_record(0, b"start-2-end"));
+        framed_file(&path, &data);
+
+        // 损坏的记录返回一次错误，之后不再继续读取
+        let content = read_log_file(&path).unwrap();
+        let mut records = content.records(0);
+        assert_eq!(records.next().unwrap().unwrap().payload, b"start-0-end");
+        assert!(records.next().unwrap().is_err());
+        assert!(records.next().is_none());
+    }
+
+    // 用 `RecordReader` 逐条读取，返回 flag 和 payload
+    fn stream_records(path: &Path, line_flags: u8) -> Vec<io::Result<(u8, Vec<u8>)>> {
+        let mut reader = RecordReader::open(path, line_flags).unwrap();
+        let mut records = vec![];
+        while let Some(record) = reader.next_record() {
+            records.push(record.map(|record| (record.flags, record.payload.to_vec())));
+        }
+        records
+    }
+
+    #[test]
+    fn test_stream_records() {
+        let path = PathBuf::from("../target/tmp_log_record/stream.log");
+        let mut data = encode_record(0, b"line1\nline2");
+        data.extend_from_slice(&encode_record(RECORD_FLAG_ENCRYPTED, b"\0\x01\x02\0"));
+        data.extend_from_slice(&encode_record(0, b""));
+        framed_file(&path, &data);
+        // 文件头之后还有 mmap 预留的空间，只读取已提交的数据
+        let mut content = fs::read(&path).unwrap();
+        content.resize(content.len() + 1024, 0);
+        fs::write(&path, content).unwrap();
+
+        let records: Vec<_> = stream_records(&path, 0)
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(
+            records,
+            vec![
+                (0, b"line1\nline2".to_vec()),
+                (RECORD_FLAG_ENCRYPTED, b"\0\x01\x02\0".to_vec()),
+                (0, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stream_crc_mismatch() {
+        let path = PathBuf::from("../target/tmp_log_record/stream_crc.log");
+        let mut data = encode_record(0, b"start-0-end");
+        let mut broken = encode_record(0, b"start-1-end");
+        broken[RECORD_HEADER_SIZE] ^= 0xff;
+        data.extend_from_slice(&broken);
+        data.extend_from_slice(&encode_record(0, b"start-2-end"));
+        framed_file(&path, &data);
+
+        let records = stream_records(&path, 0);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].as_ref().unwrap().1, b"start-0-end");
+        assert_eq!(
+            records[1].as_ref().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+
+        // 记录长度超过已提交的数据
+        let path = PathBuf::from("../target/tmp_log_record/stream_truncated.log");
+        let data = encode_record(0, b"start-0-end");
+        framed_file(&path, &data[..data.len() - 1]);
+        let records = stream_records(&path, 0);
+        assert_eq!(records.len(), 1);
+        assert!(records[0].is_err());
+    }
+
+    #[test]
+    fn test_stream_legacy_lines() {
+        let path = PathBuf::from("../target/tmp_log_record/stream_legacy.log");
+        let mut legacy = b"line1\n\nline2\0\0\nline3".to_vec();
+        legacy.resize(4096, 0);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &legacy).unwrap();
+
+        // 与一次读入内存的结果相同：跳过空行，行内的 0x00 保留，末尾的填充丢弃
+        let records: Vec<_> = stream_records(&path, RECORD_FLAG_ENCRYPTED)
+            .into_iter()
+            .map(|r| r.unwrap())
+            .collect();
+        let content = read_log_file(&path).unwrap();
+        let expected: Vec<_> = content
+            .records(RECORD_FLAG_ENCRYPTED)
+            .map(|r| {
+                let r = r.unwrap();
+                (r.flags, r.payload.to_vec())
+            })
+            .collect();
+        assert_eq!(records, expected);
+        assert_eq!(
+            records,
+            vec![
+                (RECORD_FLAG_ENCRYPTED, b"line1".to_vec()),
+                (RECORD_FLAG_ENCRYPTED, b"line2\0\0".to_vec()),
+                (RECORD_FLAG_ENCRYPTED, b"line3".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_timed_records() {
+        let path = PathBuf::from("../target/tmp_log_record/timed.log");
+        let mut data = encode_timed_record(0, 1_700_000_000_123, b"timed");
+        data.extend_from_slice(&encode_timed_record(RECORD_FLAG_ENCRYPTED, -1, b"\0\x01"));
+        data.extend_from_slice(&encode_record(0, b"untimed"));
+        framed_file(&path, &data);
+
+        // payload 不包括写入时间，一次读入内存和逐条读取的结果相同
+        let content = read_log_file(&path).unwrap();
+        let records: Vec<_> = content.records(0).map(|r| r.unwrap()).collect();
+        let timed: Vec<_> = records
+            .iter()
+            .map(|r| (r.timestamp_ms, r.payload.to_vec()))
+            .collect();
+        assert_eq!(
+            timed,
+            vec![
+                (Some(1_700_000_000_123), b"timed".to_vec()),
+                (Some(-1), b"\0\x01".to_vec()),
+                (None, b"untimed".to_vec()),
+            ]
+        );
+        assert!(records[1].is_encrypted());
+
+        let mut reader = RecordReader::open(&path, 0).unwrap();
+        let mut streamed = vec![];
+        while let Some(record) = reader.next_record() {
+            let record = record.unwrap();
+            streamed.push((record.timestamp_ms, record.payload.to_vec()));
+        }
+        assert_eq!(streamed, timed);
+
+        // 带写入时间 flag 的记录不足 8 字节
+        let path = PathBuf::from("../target/tmp_log_record/timed_short.log");
+        framed_file(&path, &encode_record(RECORD_FLAG_TIMESTAMP, b"short"));
+        let content = read_log_file(&path).unwrap();
+        assert!(content.records(0).next().unwrap().is_err());
+        let records = stream_records(&path, 0);
+        assert_eq!(records.len(), 1);
+        assert_eq!(
+            records[0].as_ref().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+
+    fn check_round_trip(is_encrypt: bool, base_dir: &str) {
+        let base_dir = PathBuf::from(base_dir);
+        let base_test = BaseTest::new("12345", &base_dir, is_encrypt, true);
+        let rc_writer = base_test.get_mmap_writer();
+        let mut writer = rc_writer.borrow_mut();
+
+        let payloads: Vec<&[u8]> = vec![
+            b"at main.rs:1\n  at lib.rs:2\n  at mod.rs:3",
+            b"{\"key\":\n\"value\"}",
+            b"\0\x01binary\0\xff\xfe\0\0",
+            b"start-plain-end",
+        ];
+        for payload in &payloads {
+            writer.write_bytes(payload).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let now = Utc::now();
+        let output = base_dir.join("export.log");
+        writer
+            .export_logs(
+                (now - Duration::hours(1)).timestamp_millis(),
+                (now + Duration::hours(1)).timestamp_millis(),
+                &output,
+            )
+            .unwrap();
+
+        let mut expected = vec![];
+        for payload in &payloads {
+            expected.extend_from_slice(payload);
+            expected.push(b'\n');
+        }
+        assert_eq!(fs::read(&output).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_round_trip_plain() {
+        check_round_trip(false, "../target/tmp_log_record_plain");
+    }
+
+    #[test]
+    fn test_round_trip_encrypt() {
+        check_round_trip(true, "../target/tmp_log_record_encrypt");
+    }
+
+    fn check_escaped_round_trip(is_encrypt: bool, base_dir: &str) {
+        let base_dir = PathBuf::from(base_dir);
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("12345", is_encrypt);
+        config.set_export_format(ExportFormat::Escaped);
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+
+        let payloads: Vec<&[u8]> = vec![
+            b"at main.rs:1\n  at lib.rs:2\r\n  at mod.rs:3",
+            b"C:\\logs\\n\\",
+            b"\n",
+            b"",
+            b"\0\x01binary\0\xff\xfe\0\0",
+        ];
+        for payload in &payloads {
+            writer.write_bytes(payload).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let now = Utc::now();
+        let mut buffer = vec![];
+        writer
+            .export_to(
+                (now - Duration::hours(1)).timestamp_millis(),
+                (now + Duration::hours(1)).timestamp_millis(),
+                &mut buffer,
+            )
+            .unwrap();
+
+        // 每条记录恰好一行，逐行还原后和写入的内容相同
+        assert_eq!(buffer.last(), Some(&b'\n'));
+        let lines: Vec<_> = buffer[..buffer.len() - 1].split(|&b| b == b'\n').collect();
+        assert_eq!(lines.len(), payloads.len());
+        for (line, payload) in lines.iter().zip(&payloads) {
+            assert_eq!(&unescape_line(line), payload);
+        }
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_escaped_round_trip_plain() {
+        check_escaped_round_trip(false, "../target/tmp_log_record_escaped_plain");
+    }
+
+    #[test]
+    fn test_escaped_round_trip_encrypt() {
+        check_escaped_round_trip(true, "../target/tmp_log_record_escaped_encrypt");
+    }
+}
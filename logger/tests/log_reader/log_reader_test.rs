@@ -0,0 +1,247 @@
+/// 测试不创建 writer，用 `LogReader` 逐条读取日志
+#[cfg(test)]
+pub mod log_reader_test {
+    use crate::base::base_test::find_log_file;
+    use chrono::{Duration, Utc};
+    use logger::encrypt_util::{generate_key_pair, KeyDerivation};
+    use logger::log_file::{read_log_header, RECORD_HEADER_SIZE, RECORD_TIMESTAMP_SIZE};
+    use logger::log_reader::{LogEntry, LogReader};
+    use logger::mmap_config::{LockMode, MmapConfig};
+    use logger::mmap_writer::MmapWriter;
+    use logger::record::{Level, LineFormat, Record};
+    use std::fs;
+    use std::io;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::{Duration as StdDuration, Instant};
+
+    fn read_all(reader: &LogReader) -> Vec<LogEntry> {
+        let now = Utc::now();
+        reader
+            .entries(
+                (now - Duration::hours(1)).timestamp_millis(),
+                (now + Duration::hours(1)).timestamp_millis(),
+            )
+            .unwrap()
+            .map(|entry| entry.unwrap())
+            .collect()
+    }
+
+    fn check_read(is_encrypt: bool, base_dir: &str) {
+        let base_dir = PathBuf::from(base_dir);
+        let _ = fs::remove_dir_all(&base_dir);
+        let config = MmapConfig::new("12345", is_encrypt);
+        let record = Record::new(Level::Warn, "main", "structured").with_field("id", "7");
+
+        let mut writer = MmapWriter::try_new(&base_dir, config.clone()).unwrap();
+        writer.write("first").unwrap();
+        writer.write_record(&record).unwrap();
+        writer.write_bytes(b"a\nb\0c").unwrap();
+        drop(writer);
+
+        let reader = LogReader::new(&base_dir, config);
+        assert_eq!(
+            reader
+                .files(0, Utc::now().timestamp_millis())
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            read_all(&reader),
+            vec![
+                LogEntry::Text(b"first".to_vec()),
+                LogEntry::Record(record.clone()),
+                LogEntry::Text(b"a\nb\0c".to_vec()),
+            ]
+        );
+
+        // 结构化日志按格式输出一行
+        let format = LineFormat::new("{level} {tag}: {message}{fields}");
+        let entry = LogEntry::Record(record);
+        let rendered = entry.render(&format, &Default::default());
+        assert_eq!(&rendered[..], b"WARN main: structured id=7");
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_read_plain() {
+        check_read(false, "../target/tmp_log_reader_plain");
+    }
+
+    #[test]
+    fn test_read_encrypt() {
+        check_read(true, "../target/tmp_log_reader_encrypt");
+    }
+
+    #[test]
+    fn test_read_current_file() {
+        let base_dir = PathBuf::from("../target/tmp_log_reader_current");
+        let _ = fs::remove_dir_all(&base_dir);
+        let config = MmapConfig::new("12345", false);
+        let mut writer = MmapWriter::try_new(&base_dir, config.clone()).unwrap();
+        writer.write("flushed").unwrap();
+        writer.flush().unwrap();
+        writer.write("buffered").unwrap();
+
+        // 正在写入的文件只能读到已经刷新的部分
+        let reader = LogReader::new(&base_dir, config);
+        assert_eq!(read_all(&reader), vec![LogEntry::Text(b"flushed".to_vec())]);
+
+        writer.flush().unwrap();
+        assert_eq!(
+            read_all(&reader),
+            vec![
+                LogEntry::Text(b"flushed".to_vec()),
+                LogEntry::Text(b"buffered".to_vec()),
+            ]
+        );
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_time_range() {
+        let base_dir = PathBuf::from("../target/tmp_log_reader_range");
+        let _ = fs::remove_dir_all(&base_dir);
+        let config = MmapConfig::new("12345", false);
+        let mut writer = MmapWriter::try_new(&base_dir, config.clone()).unwrap();
+        writer.write("now").unwrap();
+        drop(writer);
+
+        let reader = LogReader::new(&base_dir, config);
+        let now = Utc::now();
+        let yesterday = now - Duration::days(1);
+        let entries = reader
+            .entries(
+                (yesterday - Duration::hours(1)).timestamp_millis(),
+                yesterday.timestamp_millis(),
+            )
+            .unwrap();
+        assert_eq!(entries.count(), 0);
+
+        let err = reader.entries(i64::MAX, i64::MAX).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_corrupted_record() {
+        let base_dir = PathBuf::from("../target/tmp_log_reader_corrupted");
+        let _ = fs::remove_dir_all(&base_dir);
+        let config = MmapConfig::new("12345", false);
+        let mut writer = MmapWriter::try_new(&base_dir, config.clone()).unwrap();
+        writer.write("first").unwrap();
+        writer.write("second").unwrap();
+        writer.write("third").unwrap();
+        drop(writer);
+
+        // 改写第二条记录的 payload
+        let path = find_log_file(&base_dir);
+        let header = read_log_header(&path).unwrap().unwrap();
+        let mut content = fs::read(&path).unwrap();
+        let record_len =
+            |payload: &[u8]| RECORD_HEADER_SIZE + RECORD_TIMESTAMP_SIZE + payload.len();
+        let second = header.header_len + record_len(b"first");
+        content[second + record_len(b"")] ^= 0xff;
+        fs::write(&path, content).unwrap();
+
+        let reader = LogReader::new(&base_dir, config);
+        let now = Utc::now();
+        let mut entries = reader
+            .entries(
+                (now - Duration::hours(1)).timestamp_millis(),
+                (now + Duration::hours(1)).timestamp_millis(),
+            )
+            .unwrap();
+        assert_eq!(
+            entries.next().unwrap().unwrap(),
+            LogEntry::Text(b"first".to_vec())
+        );
+        let err = entries.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(entries.next().is_none());
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_public_key_not_decrypted() {
+        let base_dir = PathBuf::from("../target/tmp_log_reader_public_key");
+        let _ = fs::remove_dir_all(&base_dir);
+        let (_, public_key) = generate_key_pair().unwrap();
+        let mut config = MmapConfig::new("12345", true);
+        config.set_public_key(&hex::encode(public_key)).unwrap();
+        let mut writer = MmapWriter::try_new(&base_dir, config.clone()).unwrap();
+        writer.write("secret").unwrap();
+        drop(writer);
+
+        // 设备上没有私钥，返回十六进制密文
+        let entries = read_all(&LogReader::new(&base_dir, config));
+        assert_eq!(entries.len(), 1);
+        let LogEntry::Encrypted(ciphertext) = &entries[0] else {
+            panic!("unexpected entry {:?}", entries[0]);
+        };
+        assert!(hex::decode(ciphertext).is_ok());
+        assert!(!ciphertext.windows(6).any(|window| window == b"secret"));
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_merge_by_write_time() {
+        let base_dir = PathBuf::from("../target/tmp_log_reader_merge");
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("12345", false);
+        config.set_lock_mode(LockMode::ProcessSuffix);
+        let mut writers = [
+            MmapWriter::try_new(&base_dir, config.clone()).unwrap(),
+            MmapWriter::try_new(&base_dir, config.clone()).unwrap(),
+        ];
+        for (i, message) in ["a1", "b1", "a2", "b2"].iter().enumerate() {
+            writers[i % 2].write(message).unwrap();
+            thread::sleep(StdDuration::from_millis(5));
+        }
+        for writer in writers.iter_mut() {
+            writer.flush().unwrap();
+        }
+
+        // 两个 writer 同一时间周期的文件按写入时间交替输出
+        let entries = read_all(&LogReader::new(&base_dir, config));
+        let expected: Vec<LogEntry> = ["a1", "b1", "a2", "b2"]
+            .iter()
+            .map(|message| LogEntry::Text(message.as_bytes().to_vec()))
+            .collect();
+        assert_eq!(entries, expected);
+        drop(writers);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_oversized_key_derivation() {
+        let base_dir = PathBuf::from("../target/tmp_log_reader_oversized_kdf");
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("12345", true);
+        config.set_key_derivation(KeyDerivation::Hkdf);
+        let mut writer = MmapWriter::try_new(&base_dir, config.clone()).unwrap();
+        writer.write("secret").unwrap();
+        drop(writer);
+
+        // 改写文件头中的密钥派生参数，解密需要几小时
+        let path = find_log_file(&base_dir);
+        let mut header = read_log_header(&path).unwrap().unwrap();
+        let content = fs::read(&path).unwrap();
+        let data = &content[header.header_len..];
+        let cipher = header.cipher.as_mut().unwrap();
+        cipher.kdf = KeyDerivation::Pbkdf2 {
+            iterations: u32::MAX,
+        };
+        fs::write(&path, [header.encode(), data.to_vec()].concat()).unwrap();
+
+        // 参数超过上限，不派生密钥，直接返回十六进制密文
+        let start = Instant::now();
+        let entries = read_all(&LogReader::new(&base_dir, config));
+        assert!(start.elapsed() < StdDuration::from_secs(5));
+        assert_eq!(entries.len(), 1);
+        assert!(matches!(entries[0], LogEntry::Encrypted(_)));
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+}
@@ -0,0 +1 @@
+mod log_reader_test;
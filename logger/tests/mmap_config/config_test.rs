@@ -1,6 +1,12 @@
 #[cfg(test)]
 pub mod config_test {
-    use logger::mmap_config::MmapConfig;
+    use logger::encrypt_util::{CipherMode, KeyDerivation};
+    use logger::keyring::Keyring;
+    use logger::mmap_config::{LockMode, MmapConfig};
+    use logger::naming::NamingTemplate;
+    use logger::record::{ExportFormat, LineFormat};
+    use logger::rotation::{RotationPeriod, RotationPolicy};
+    use logger::time_zone::LogTimeZone;
 
     #[test]
     fn test_new() {
@@ -14,6 +20,9 @@ pub mod config_test {
         assert_eq!(16 * 1024, conf.get_flush_size());
         assert_eq!(5, conf.get_flush_interval());
         assert_eq!(7, conf.get_expiration_days());
+        assert!(!conf.is_background_flush());
+        assert_eq!(LockMode::ProcessSuffix, conf.get_lock_mode());
+        assert_eq!(ExportFormat::Raw, conf.get_export_format());
     }
 
     #[test]
@@ -166,4 +175,206 @@ pub mod config_test {
         conf.set_expiration_days(expiration_days);
         assert_eq!(expiration_days, conf.get_expiration_days());
     }
+
+    #[test]
+    fn test_background_flush() {
+        let app_key = "123321";
+        let is_encrypt = true;
+        let mut conf = MmapConfig::new(app_key, is_encrypt);
+
+        conf.set_background_flush(true);
+        assert!(conf.is_background_flush());
+
+        conf.set_background_flush(false);
+        assert!(!conf.is_background_flush());
+    }
+
+    #[test]
+    fn test_lock_mode() {
+        let app_key = "123321";
+        let is_encrypt = true;
+        let mut conf = MmapConfig::new(app_key, is_encrypt);
+
+        conf.set_lock_mode(LockMode::FailFast);
+        assert_eq!(LockMode::FailFast, conf.get_lock_mode());
+
+        conf.set_lock_mode(LockMode::Wait);
+        assert_eq!(LockMode::Wait, conf.get_lock_mode());
+    }
+
+    #[test]
+    fn test_time_zone() {
+        let app_key = "123321";
+        let is_encrypt = true;
+        let mut conf = MmapConfig::new(app_key, is_encrypt);
+        assert_eq!(LogTimeZone::default(), conf.get_time_zone());
+
+        conf.set_time_zone(LogTimeZone::Utc);
+        assert_eq!(LogTimeZone::Utc, conf.get_time_zone());
+    }
+
+    #[test]
+    fn test_rotation_policy() {
+        let app_key = "123321";
+        let is_encrypt = true;
+        let mut conf = MmapConfig::new(app_key, is_encrypt);
+        assert_eq!(
+            RotationPeriod::Hourly,
+            conf.get_rotation_policy().get_period()
+        );
+
+        let policy = RotationPolicy::every_minutes(15).with_max_bytes(1024);
+        conf.set_rotation_policy(policy);
+        assert_eq!(policy, conf.get_rotation_policy());
+        assert_eq!(Some(1024), conf.get_rotation_policy().get_max_file_bytes());
+
+        // 不合法的策略不生效
+        conf.set_rotation_policy(RotationPolicy::every_minutes(0));
+        assert_eq!(policy, conf.get_rotation_policy());
+        conf.set_rotation_policy(RotationPolicy::every_minutes(1441));
+        assert_eq!(policy, conf.get_rotation_policy());
+        conf.set_rotation_policy(RotationPolicy::max_bytes(0));
+        assert_eq!(policy, conf.get_rotation_policy());
+    }
+
+    #[test]
+    fn test_naming_template() {
+        let app_key = "123321";
+        let is_encrypt = true;
+        let mut conf = MmapConfig::new(app_key, is_encrypt);
+
+        // 未设置时跟随切分策略
+        assert_eq!(
+            NamingTemplate::default_for(RotationPeriod::Hourly),
+            conf.get_naming_template()
+        );
+        conf.set_rotation_policy(RotationPolicy::daily());
+        assert_eq!(
+            NamingTemplate::default_for(RotationPeriod::Daily),
+            conf.get_naming_template()
+        );
+
+        let template: NamingTemplate = "{yyyy}{MM}{dd}/{HH}_{mode}{slot}{part}.log"
+            .parse()
+            .unwrap();
+        conf.set_naming_template(template.clone());
+        assert_eq!(template, conf.get_naming_template());
+    }
+
+    #[test]
+    fn test_stream_name() {
+        let app_key = "123321";
+        let is_encrypt = true;
+        let mut conf = MmapConfig::new(app_key, is_encrypt);
+        assert_eq!("", conf.get_stream_name());
+
+        conf.set_stream_name("app-main");
+        assert_eq!("app-main", conf.get_stream_name());
+
+        // 不合法的名称不生效
+        conf.set_stream_name("app/main");
+        assert_eq!("app-main", conf.get_stream_name());
+        conf.set_stream_name("app_main");
+        assert_eq!("app-main", conf.get_stream_name());
+    }
+
+    #[test]
+    fn test_max_total_bytes() {
+        let app_key = "123321";
+        let is_encrypt = true;
+        let mut conf = MmapConfig::new(app_key, is_encrypt);
+        assert_eq!(None, conf.get_max_total_bytes());
+
+        conf.set_max_total_bytes(1024 * 1024);
+        assert_eq!(Some(1024 * 1024), conf.get_max_total_bytes());
+
+        // 0 不生效
+        conf.set_max_total_bytes(0);
+        assert_eq!(Some(1024 * 1024), conf.get_max_total_bytes());
+    }
+
+    #[test]
+    fn test_line_format() {
+        let app_key = "123321";
+        let is_encrypt = true;
+        let mut conf = MmapConfig::new(app_key, is_encrypt);
+        assert_eq!(LineFormat::default(), *conf.get_line_format());
+
+        conf.set_line_format(LineFormat::new("{level} {message}"));
+        assert_eq!("{level} {message}", conf.get_line_format().pattern());
+    }
+
+    #[test]
+    fn test_file_grow_size() {
+        let app_key = "123321";
+        let is_encrypt = true;
+        let mut conf = MmapConfig::new(app_key, is_encrypt);
+        assert_eq!(None, conf.get_file_grow_size());
+
+        conf.set_file_grow_size(4 * 1024 * 1024);
+        assert_eq!(Some(4 * 1024 * 1024), conf.get_file_grow_size());
+
+        // 小于 1024 不生效
+        conf.set_file_grow_size(1023);
+        assert_eq!(Some(4 * 1024 * 1024), conf.get_file_grow_size());
+    }
+
+    #[test]
+    fn test_cipher_mode() {
+        let mut conf = MmapConfig::new("123321", true);
+        assert_eq!(CipherMode::Aes256Gcm, conf.get_cipher_mode());
+
+        conf.set_cipher_mode(CipherMode::ChaCha20Poly1305);
+        assert_eq!(CipherMode::ChaCha20Poly1305, conf.get_cipher_mode());
+    }
+
+    #[test]
+    fn test_public_key() {
+        let mut conf = MmapConfig::new("123321", true);
+        assert_eq!(None, conf.get_public_key());
+
+        let hex_key = "11".repeat(32);
+        conf.set_public_key(&hex_key).unwrap();
+        assert_eq!(Some([0x11u8; 32]), conf.get_public_key());
+
+        // 长度不对或不是十六进制时返回错误，配置保持不变
+        assert!(conf.set_public_key("1122").is_err());
+        assert!(conf.set_public_key(&"zz".repeat(32)).is_err());
+        assert_eq!(Some([0x11u8; 32]), conf.get_public_key());
+    }
+
+    #[test]
+    fn test_key_derivation() {
+        let mut conf = MmapConfig::new("123321", true);
+        assert_eq!(KeyDerivation::ARGON2, conf.get_key_derivation());
+
+        conf.set_key_derivation(KeyDerivation::PBKDF2);
+        assert_eq!(KeyDerivation::PBKDF2, conf.get_key_derivation());
+
+        // 参数无效时不生效
+        conf.set_key_derivation(KeyDerivation::Pbkdf2 { iterations: 0 });
+        assert_eq!(KeyDerivation::PBKDF2, conf.get_key_derivation());
+    }
+
+    #[test]
+    fn test_keyring() {
+        let mut conf = MmapConfig::new("123321", true);
+        assert!(conf.get_keyring().is_none());
+
+        conf.set_keyring(Keyring::new("v2", "new-key").with_retired_key("v1", "old-key"))
+            .unwrap();
+        assert_eq!(conf.get_keyring().unwrap().active_id(), "v2");
+
+        // 无效的密钥环返回错误，配置保持不变
+        let err = conf.set_keyring(Keyring::new("v3", "")).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert_eq!(conf.get_keyring().unwrap().active_id(), "v2");
+    }
+
+    #[test]
+    fn test_export_format() {
+        let mut conf = MmapConfig::new("123321", true);
+        conf.set_export_format(ExportFormat::Escaped);
+        assert_eq!(ExportFormat::Escaped, conf.get_export_format());
+    }
 }
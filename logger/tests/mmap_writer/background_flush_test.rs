@@ -0,0 +1,75 @@
+#[cfg(test)]
+pub mod background_flush_test {
+    use chrono::{Datelike, Timelike, Utc};
+    use chrono_tz::Asia::Shanghai;
+    use logger::log_file::read_log_file;
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::{MmapWriter, SharedMmapWriter};
+    use std::fs::remove_dir_all;
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::thread;
+    use std::time::Duration;
+
+    // 读取当前小时的日志文件，返回日志条数
+    fn current_line_count(base_dir: &Path) -> usize {
+        let now = Utc::now().with_timezone(&Shanghai);
+        let day = format!("{:04}{:02}{:02}", now.year(), now.month(), now.day());
+        let file_name = format!("{}_{:02}_plain.log", day, now.hour());
+        let Ok(content) = read_log_file(&base_dir.join(day).join(file_name)) else {
+            return 0;
+        };
+        content.records(0).count()
+    }
+
+    #[test]
+    fn test_flush_while_idle() {
+        let base_dir = PathBuf::from("../target/tmp_log_background_idle");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut config = MmapConfig::new("12345", false);
+        config.set_flush_interval(1);
+        config.set_background_flush(true);
+        let writer = SharedMmapWriter::try_new(&base_dir, config).unwrap();
+
+        writer.write("start-idle-end").unwrap();
+        assert_eq!(current_line_count(&base_dir), 0);
+
+        // 不再写入，后台线程也会在 flush_interval 后落盘
+        thread::sleep(Duration::from_millis(2500));
+        assert_eq!(current_line_count(&base_dir), 1);
+    }
+
+    #[test]
+    fn test_flush_on_drop() {
+        let base_dir = PathBuf::from("../target/tmp_log_background_drop");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut config = MmapConfig::new("12345", false);
+        config.set_background_flush(true);
+        let writer = SharedMmapWriter::try_new(&base_dir, config).unwrap();
+        let other = writer.clone();
+
+        writer.write("start-drop-0-end").unwrap();
+        other.write("start-drop-1-end").unwrap();
+        drop(writer);
+        assert_eq!(current_line_count(&base_dir), 0);
+
+        // 最后一个句柄销毁时刷新剩余日志
+        drop(other);
+        assert_eq!(current_line_count(&base_dir), 2);
+    }
+
+    #[test]
+    fn test_rejected_by_mmap_writer() {
+        let base_dir = PathBuf::from("../target/tmp_log_background_rejected");
+        let _ = remove_dir_all(&base_dir);
+
+        // MmapWriter 无法在后台线程刷新，开启后直接报错，而不是忽略
+        let mut config = MmapConfig::new("12345", false);
+        config.set_background_flush(true);
+        let err = MmapWriter::try_new(&base_dir, config).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!base_dir.exists());
+    }
+}
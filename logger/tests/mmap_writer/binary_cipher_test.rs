@@ -0,0 +1,122 @@
+/// 测试加密记录以二进制密文保存，以及旧版本十六进制密文记录的兼容
+#[cfg(test)]
+pub mod binary_cipher_test {
+    use crate::base::base_test::{export_recent_lines, find_log_file};
+    use chrono::{Duration, Utc};
+    use logger::encrypt_util::{CipherMode, LogCipher};
+    use logger::log_file::{
+        encode_record, encode_timed_record, read_log_file, RECORD_FLAG_ENCRYPTED,
+    };
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::MmapWriter;
+    use std::fs;
+    use std::path::PathBuf;
+
+    // AES-256-GCM 的 nonce 和认证标签长度
+    const GCM_OVERHEAD: usize = 12 + 16;
+
+    fn new_writer(base_dir: &PathBuf) -> MmapWriter {
+        let mut config = MmapConfig::new("12345", true);
+        config.set_cipher_mode(CipherMode::Aes256Gcm);
+        MmapWriter::try_new(base_dir, config).unwrap()
+    }
+
+    #[test]
+    fn test_binary_records() {
+        let base_dir = PathBuf::from("../target/tmp_log_binary_cipher");
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut writer = new_writer(&base_dir);
+        let lines = ["first", "二进制密文", "a\nb\0c"];
+        for line in lines {
+            writer.write_bytes(line.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+
+        // 密文不再转为十六进制，只比明文多 nonce 和认证标签
+        let content = read_log_file(&find_log_file(&base_dir)).unwrap();
+        let records: Vec<_> = content.records(0).map(|record| record.unwrap()).collect();
+        assert_eq!(records.len(), lines.len());
+        for (record, line) in records.iter().zip(lines) {
+            assert!(record.is_encrypted() && record.is_binary());
+            assert_eq!(record.payload.len(), line.len() + GCM_OVERHEAD);
+        }
+        assert_eq!(
+            export_recent_lines(&writer),
+            vec!["first", "二进制密文", "a", "b\0c"]
+        );
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_hex_records_compatible() {
+        let base_dir = PathBuf::from("../target/tmp_log_binary_cipher_hex");
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut writer = new_writer(&base_dir);
+        writer.write("binary").unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        // 在同一个文件中追加旧版本的十六进制密文记录
+        let path = find_log_file(&base_dir);
+        let content = read_log_file(&path).unwrap();
+        let mut header = content.header().unwrap().clone();
+        let cipher = LogCipher::new(header.cipher.clone().unwrap(), "12345").unwrap();
+        let mut data = content.data().to_vec();
+        let encrypted = cipher.encrypt(b"hex").unwrap();
+        data.extend_from_slice(&encode_record(RECORD_FLAG_ENCRYPTED, &encrypted));
+        header.committed_len = data.len() as u64;
+        let mut bytes = header.encode();
+        bytes.extend_from_slice(&data);
+        fs::write(&path, bytes).unwrap();
+
+        let writer = new_writer(&base_dir);
+        assert_eq!(export_recent_lines(&writer), vec!["binary", "hex"]);
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_tampered_timestamp() {
+        let base_dir = PathBuf::from("../target/tmp_log_binary_cipher_tampered");
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut writer = new_writer(&base_dir);
+        writer.write("first").unwrap();
+        writer.write("second").unwrap();
+        drop(writer);
+
+        // 修改第二条记录的写入时间并重新计算 CRC，密文不变
+        let path = find_log_file(&base_dir);
+        let content = read_log_file(&path).unwrap();
+        let mut header = content.header().unwrap().clone();
+        let mut data = vec![];
+        for (i, record) in content.records(0).enumerate() {
+            let record = record.unwrap();
+            let timestamp_ms = record.timestamp_ms.unwrap() - 1000 * i as i64;
+            data.extend_from_slice(&encode_timed_record(
+                record.flags,
+                timestamp_ms,
+                record.payload,
+            ));
+        }
+        header.committed_len = data.len() as u64;
+        fs::write(&path, [header.encode(), data].concat()).unwrap();
+
+        // 写入时间被认证，修改后按无法解密的记录导出十六进制密文
+        let writer = new_writer(&base_dir);
+        let now = Utc::now();
+        let mut out = vec![];
+        let summary = writer
+            .export_to(
+                (now - Duration::hours(1)).timestamp_millis(),
+                (now + Duration::hours(1)).timestamp_millis(),
+                &mut out,
+            )
+            .unwrap();
+        assert_eq!(summary.lines, 2);
+        assert_eq!(summary.decrypt_failures, 1);
+        assert!(out.starts_with(b"first\n"));
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+}
@@ -0,0 +1,166 @@
+/// 测试进程崩溃后，从缓存文件中恢复未刷新的日志
+#[cfg(test)]
+pub mod cache_recover_test {
+    use crate::base::base_test::{export_lines, BaseTest};
+    use crate::base::random_tool::string_by_length;
+    use chrono::{Datelike, Duration, TimeZone, Timelike, Utc};
+    use chrono_tz::Asia::Shanghai;
+    use logger::log_file::{encode_record, read_log_data};
+    use std::fs;
+
+    use std::path::{Path, PathBuf};
+
+    fn export_line_count(base_test: &BaseTest) -> usize {
+        let now = Utc::now();
+        let rc_writer = base_test.get_mmap_writer();
+        let lines = export_lines(
+            &*rc_writer.borrow(),
+            (now - Duration::days(1)).timestamp_millis(),
+            (now + Duration::hours(1)).timestamp_millis(),
+        );
+        for line in &lines {
+            assert!(line.starts_with("start"));
+            assert!(line.ends_with("end"));
+        }
+        lines.len()
+    }
+
+    fn copy_dir(from: &Path, to: &Path) {
+        fs::create_dir_all(to).unwrap();
+        for entry in fs::read_dir(from).unwrap() {
+            let path = entry.unwrap().path();
+            let target = to.join(path.file_name().unwrap());
+            if path.is_dir() {
+                copy_dir(&path, &target);
+            } else {
+                fs::copy(&path, &target).unwrap();
+            }
+        }
+    }
+
+    fn check_recover(is_encrypt: bool, base_dir: &str) {
+        let count = 100;
+        let length = 100;
+        let app_key = "12345";
+        let running_dir = PathBuf::from(format!("{}_running", base_dir));
+        let base_dir = PathBuf::from(base_dir);
+        let _ = fs::remove_dir_all(&base_dir);
+        let base_test = BaseTest::new(app_key, &running_dir, is_encrypt, true);
+
+        {
+            let rc_writer = base_test.get_mmap_writer();
+            let mut writer = rc_writer.borrow_mut();
+            for _ in 0..count {
+                writer.write(string_by_length(length).as_str()).unwrap();
+            }
+        }
+        // 不调用 flush，复制此时磁盘上的文件，模拟进程被杀掉后留下的状态
+        copy_dir(&running_dir, &base_dir);
+        drop(base_test);
+
+        // 重新创建 writer 时恢复缓存中的日志
+        let base_test = BaseTest::new(app_key, &base_dir, is_encrypt, false);
+        assert_eq!(export_line_count(&base_test), count);
+        drop(base_test);
+
+        // 缓存已经清空，再次创建不会重复写入
+        let base_test = BaseTest::new(app_key, &base_dir, is_encrypt, false);
+        assert_eq!(export_line_count(&base_test), count);
+    }
+
+    // 标记上次进程正在把缓存中的全部数据追加到已提交 `file_len` 字节的日志文件
+    fn mark_pending_flush(cache_path: &Path, file_len: u64) {
+        let mut cache = fs::read(cache_path).unwrap();
+        let len = cache[8..16].to_vec();
+        cache[34..42].copy_from_slice(&len);
+        cache[42..50].copy_from_slice(&file_len.to_le_bytes());
+        fs::write(cache_path, cache).unwrap();
+    }
+
+    #[test]
+    fn test_recover_after_append() {
+        let count = 100;
+        let app_key = "12345";
+        let running_dir = PathBuf::from("../target/tmp_log_recover_append_running");
+        let before_dir = PathBuf::from("../target/tmp_log_recover_append_before");
+        let after_dir = PathBuf::from("../target/tmp_log_recover_append_after");
+        let _ = fs::remove_dir_all(&before_dir);
+        let _ = fs::remove_dir_all(&after_dir);
+        let base_test = BaseTest::new(app_key, &running_dir, false, true);
+        {
+            let rc_writer = base_test.get_mmap_writer();
+            let mut writer = rc_writer.borrow_mut();
+            for _ in 0..count {
+                writer.write(string_by_length(100).as_str()).unwrap();
+            }
+        }
+        // 追加到日志文件之前的状态
+        copy_dir(&running_dir, &before_dir);
+        base_test.get_mmap_writer().borrow_mut().flush().unwrap();
+        // 已经追加到日志文件、还没有清空缓存的状态
+        copy_dir(&running_dir, &after_dir);
+        let cache_name = "mmap_plain.cache";
+        fs::copy(before_dir.join(cache_name), after_dir.join(cache_name)).unwrap();
+        drop(base_test);
+        mark_pending_flush(&before_dir.join(cache_name), 0);
+        mark_pending_flush(&after_dir.join(cache_name), 0);
+
+        // 还没有追加的数据重新写入，已经追加的数据不会重复写入
+        let base_test = BaseTest::new(app_key, &before_dir, false, false);
+        assert_eq!(export_line_count(&base_test), count);
+        let base_test = BaseTest::new(app_key, &after_dir, false, false);
+        assert_eq!(export_line_count(&base_test), count);
+    }
+
+    #[test]
+    fn test_recover_plain() {
+        check_recover(false, "../target/tmp_log_recover_plain");
+    }
+
+    #[test]
+    fn test_recover_encrypt() {
+        check_recover(true, "../target/tmp_log_recover_encrypt");
+    }
+
+    #[test]
+    fn test_recover_to_written_hour() {
+        let app_key = "12345";
+        let base_dir = PathBuf::from("../target/tmp_log_recover_hour");
+        let _ = fs::remove_dir_all(&base_dir);
+        fs::create_dir_all(&base_dir).unwrap();
+
+        // 构造一个昨天 22 点写入、还没刷新的缓存文件
+        let yesterday = Utc::now().with_timezone(&Shanghai) - Duration::days(1);
+        let written_at = Shanghai
+            .with_ymd_and_hms(
+                yesterday.year(),
+                yesterday.month(),
+                yesterday.day(),
+                22,
+                30,
+                0,
+            )
+            .single()
+            .unwrap();
+        let data = encode_record(0, b"start-yesterday-end");
+        let mut cache = vec![0u8; 256 + 1024];
+        cache[..4].copy_from_slice(b"MLCH");
+        cache[4] = 1;
+        cache[8..16].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        cache[16..24].copy_from_slice(&written_at.timestamp_millis().to_le_bytes());
+        cache[256..256 + data.len()].copy_from_slice(&data);
+        fs::write(base_dir.join("mmap_plain.cache"), cache).unwrap();
+
+        let _base_test = BaseTest::new(app_key, &base_dir, false, false);
+
+        let day = format!(
+            "{:04}{:02}{:02}",
+            written_at.year(),
+            written_at.month(),
+            written_at.day()
+        );
+        let file_name = format!("{}_{:02}_plain.log", day, written_at.hour());
+        let content = read_log_data(&base_dir.join(day).join(file_name)).unwrap();
+        assert_eq!(content, data);
+    }
+}
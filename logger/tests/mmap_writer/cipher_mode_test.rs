@@ -0,0 +1,170 @@
+/// 测试按 `MmapConfig::set_cipher_mode` 加密，以及加密模式改变后的写入和导出
+#[cfg(test)]
+pub mod cipher_mode_test {
+    use crate::base::base_test::{export_recent_lines, log_files};
+    use logger::encrypt_util::{CipherMode, CipherParams, KeyDerivation};
+    use logger::log_file::read_log_header;
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::MmapWriter;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn new_writer(base_dir: &PathBuf, mode: CipherMode) -> MmapWriter {
+        let mut config = MmapConfig::new("12345", true);
+        config.set_cipher_mode(mode);
+        MmapWriter::try_new(base_dir, config).unwrap()
+    }
+
+    // 所有日志文件头中的加密参数，按文件路径排序
+    fn file_params(dir: &Path) -> Vec<CipherParams> {
+        log_files(dir)
+            .iter()
+            .map(|file| {
+                let header = read_log_header(file).unwrap().unwrap();
+                header.cipher.unwrap_or_default()
+            })
+            .collect()
+    }
+
+    fn file_modes(dir: &Path) -> Vec<CipherMode> {
+        file_params(dir).iter().map(|params| params.mode).collect()
+    }
+
+    fn copy_dir(from: &Path, to: &Path) {
+        fs::create_dir_all(to).unwrap();
+        for entry in fs::read_dir(from).unwrap() {
+            let path = entry.unwrap().path();
+            let target = to.join(path.file_name().unwrap());
+            if path.is_dir() {
+                copy_dir(&path, &target);
+            } else {
+                fs::copy(&path, &target).unwrap();
+            }
+        }
+    }
+
+    fn check_mode(mode: CipherMode, base_dir: &str) {
+        let base_dir = PathBuf::from(base_dir);
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut writer = new_writer(&base_dir, mode);
+        writer.write("first").unwrap();
+        writer.write("first").unwrap();
+        writer.write_bytes(b"second\0line").unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(
+            export_recent_lines(&writer),
+            vec!["first", "first", "second\0line"]
+        );
+        assert_eq!(file_modes(&base_dir), vec![mode]);
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_aes_256_gcm() {
+        check_mode(CipherMode::Aes256Gcm, "../target/tmp_log_cipher_gcm");
+    }
+
+    #[test]
+    fn test_chacha20_poly1305() {
+        check_mode(
+            CipherMode::ChaCha20Poly1305,
+            "../target/tmp_log_cipher_chacha",
+        );
+    }
+
+    #[test]
+    fn test_aes_ecb() {
+        check_mode(CipherMode::AesEcb, "../target/tmp_log_cipher_ecb");
+    }
+
+    #[test]
+    fn test_mode_changed() {
+        let base_dir = PathBuf::from("../target/tmp_log_cipher_changed");
+        let _ = fs::remove_dir_all(&base_dir);
+
+        let mut writer = new_writer(&base_dir, CipherMode::AesEcb);
+        writer.write("ecb").unwrap();
+        drop(writer);
+
+        // 同一个时间周期内换了加密模式，写入新的分片
+        let mut writer = new_writer(&base_dir, CipherMode::Aes256Gcm);
+        writer.write("gcm").unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(export_recent_lines(&writer), vec!["ecb", "gcm"]);
+        assert_eq!(
+            file_modes(&base_dir),
+            vec![CipherMode::AesEcb, CipherMode::Aes256Gcm]
+        );
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_recover_cache_with_other_mode() {
+        let running_dir = PathBuf::from("../target/tmp_log_cipher_recover_running");
+        let base_dir = PathBuf::from("../target/tmp_log_cipher_recover");
+        let _ = fs::remove_dir_all(&running_dir);
+        let _ = fs::remove_dir_all(&base_dir);
+
+        let mut writer = new_writer(&running_dir, CipherMode::ChaCha20Poly1305);
+        writer.write("flushed").unwrap();
+        writer.flush().unwrap();
+        writer.write("cached").unwrap();
+        // 不调用 flush，复制此时磁盘上的文件，模拟进程被杀掉后留下的状态
+        copy_dir(&running_dir, &base_dir);
+        drop(writer);
+
+        // 缓存中的日志按写入时的加密模式追加，新的日志写入新的分片
+        let mut writer = new_writer(&base_dir, CipherMode::Aes256Gcm);
+        writer.write("restarted").unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(
+            export_recent_lines(&writer),
+            vec!["flushed", "cached", "restarted"]
+        );
+        assert_eq!(
+            file_modes(&base_dir),
+            vec![CipherMode::ChaCha20Poly1305, CipherMode::Aes256Gcm]
+        );
+        drop(writer);
+        let _ = fs::remove_dir_all(&running_dir);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_key_derivation() {
+        let base_dir = PathBuf::from("../target/tmp_log_cipher_kdf");
+        let _ = fs::remove_dir_all(&base_dir);
+        let kdf = KeyDerivation::Pbkdf2 { iterations: 1000 };
+        let new_writer = || {
+            let mut config = MmapConfig::new("12345", true);
+            config.set_key_derivation(kdf);
+            MmapWriter::try_new(&base_dir, config).unwrap()
+        };
+
+        let mut writer = new_writer();
+        writer.write("first session").unwrap();
+        drop(writer);
+
+        // 每次会话使用新的 salt，写入新的分片
+        let mut writer = new_writer();
+        writer.write("second session").unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(
+            export_recent_lines(&writer),
+            vec!["first session", "second session"]
+        );
+        let params = file_params(&base_dir);
+        assert_eq!(params.len(), 2);
+        assert!(params.iter().all(|params| params.kdf == kdf));
+        assert!(params.iter().all(|params| params.salt.is_some()));
+        assert_ne!(params[0].salt, params[1].salt);
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+}
@@ -0,0 +1,122 @@
+/// 测试关闭 writer 时刷新缓冲区并截断日志文件
+#[cfg(test)]
+pub mod close_test {
+    use crate::base::base_test::log_files;
+    use logger::log_file::{read_log_file, read_log_header};
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::MmapWriter;
+
+    use logger::rotation::RotationPolicy;
+    use std::fs::{self, remove_dir_all};
+    use std::path::{Path, PathBuf};
+
+    fn messages(path: &Path) -> Vec<String> {
+        let content = read_log_file(path).unwrap();
+        content
+            .records(0)
+            .map(|record| String::from_utf8(record.unwrap().payload.to_vec()).unwrap())
+            .collect()
+    }
+
+    // 文件长度等于文件头加上已提交的数据，末尾没有预留的空间
+    fn assert_sealed(path: &Path) {
+        let header = read_log_header(path).unwrap().unwrap();
+        let len = fs::metadata(path).unwrap().len();
+        assert_eq!(len, header.header_len as u64 + header.committed_len);
+    }
+
+    fn new_config() -> MmapConfig {
+        let mut config = MmapConfig::new("12345", false);
+        // 不会因为缓冲区大小自动刷新
+        config.set_buffer_size(64 * 1024);
+        config.set_flush_size(64 * 1024);
+        config.set_flush_interval(3600);
+        config
+    }
+
+    #[test]
+    fn test_close() {
+        let base_dir = PathBuf::from("../target/tmp_log_close");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut writer = MmapWriter::try_new(&base_dir, new_config()).unwrap();
+        writer.write("first").unwrap();
+        writer.flush().unwrap();
+        writer.write("second").unwrap();
+        writer.close().unwrap();
+
+        let files = log_files(&base_dir);
+        assert_eq!(files.len(), 1);
+        assert_sealed(&files[0]);
+        assert_eq!(messages(&files[0]), vec!["first", "second"]);
+
+        // 截断后的文件可以继续追加
+        let mut writer = MmapWriter::try_new(&base_dir, new_config()).unwrap();
+        writer.write("third").unwrap();
+        writer.close().unwrap();
+        assert_sealed(&files[0]);
+        assert_eq!(messages(&files[0]), vec!["first", "second", "third"]);
+
+        let _ = remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_drop() {
+        let base_dir = PathBuf::from("../target/tmp_log_close_drop");
+        let _ = remove_dir_all(&base_dir);
+
+        {
+            let mut writer = MmapWriter::try_new(&base_dir, new_config()).unwrap();
+            writer.write("flushed").unwrap();
+            writer.flush().unwrap();
+            writer.write("buffered").unwrap();
+        }
+
+        let files = log_files(&base_dir);
+        assert_eq!(files.len(), 1);
+        assert_sealed(&files[0]);
+        assert_eq!(messages(&files[0]), vec!["flushed", "buffered"]);
+
+        // 没有写入过日志文件时，Drop 只刷新缓冲区
+        let _ = remove_dir_all(&base_dir);
+        {
+            let mut writer = MmapWriter::try_new(&base_dir, new_config()).unwrap();
+            writer.write("only buffered").unwrap();
+        }
+        let files = log_files(&base_dir);
+        assert_eq!(files.len(), 1);
+        assert_sealed(&files[0]);
+        assert_eq!(messages(&files[0]), vec!["only buffered"]);
+
+        let _ = remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_seal_on_rollover() {
+        let base_dir = PathBuf::from("../target/tmp_log_close_rollover");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut config = new_config();
+        config.set_rotation_policy(RotationPolicy::max_bytes(100));
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+        for i in 0..10 {
+            writer.write(&format!("start-{}-end", i)).unwrap();
+            writer.flush().unwrap();
+        }
+
+        // 之前的分片在切换时已经截断，正在写入的分片还有预留空间
+        let files = log_files(&base_dir);
+        assert!(files.len() >= 2);
+        let (current, sealed) = files.split_last().unwrap();
+        sealed.iter().for_each(|path| assert_sealed(path));
+        let header = read_log_header(current).unwrap().unwrap();
+        assert!(
+            fs::metadata(current).unwrap().len() > header.header_len as u64 + header.committed_len
+        );
+
+        writer.close().unwrap();
+        files.iter().for_each(|path| assert_sealed(path));
+
+        let _ = remove_dir_all(&base_dir);
+    }
+}
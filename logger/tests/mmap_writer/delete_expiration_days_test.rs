@@ -4,7 +4,7 @@ pub mod delete_expiration_days_test {
     use chrono::{Datelike, FixedOffset, Local, Timelike};
     use logger::mmap_writer::delete_expired_directories;
     use std::fs;
-    use std::fs::File;
+
     use std::path::PathBuf;
 
     #[test]
@@ -18,7 +18,7 @@ pub mod delete_expiration_days_test {
         let app_key = "12345";
         let is_encrypt = true;
         let base_dir = PathBuf::from("../target/tmp_log");
-        let base_test = BaseTest::new(app_key, &base_dir, is_encrypt, true);
+        let _base_test = BaseTest::new(app_key, &base_dir, is_encrypt, true);
 
         // 创建超过 7 天的目录和文件
         create_subdir_and_file(&base_dir, "20220501", "test.log").unwrap();
@@ -30,14 +30,17 @@ pub mod delete_expiration_days_test {
         create_subdir_and_file(&base_dir, "20220507", "test.log").unwrap();
 
         // 创建当天的目录和文件
-        let (year, month, day, hour) = current_time();
+        let (year, month, day, _hour) = current_time();
         let today_dir = format!("{:04}{:02}{:02}", year, month, day);
         create_subdir_and_file(&base_dir, today_dir.as_str(), "test.log").unwrap();
 
         // 检查创建了 8 个目录
         let mut all_dir_count = 0;
         for entry in fs::read_dir(&base_dir).unwrap() {
-            all_dir_count += 1;
+            // base_dir 下还有 writer 的缓存文件，只统计目录
+            if entry.unwrap().path().is_dir() {
+                all_dir_count += 1;
+            }
         }
         assert_eq!(all_dir_count, 8);
 
@@ -49,6 +52,9 @@ pub mod delete_expiration_days_test {
         for entry in fs::read_dir(&base_dir).unwrap() {
             let entry = entry.unwrap();
             let path = entry.path();
+            if !path.is_dir() {
+                continue;
+            }
             if let Some(name) = path.file_name().and_then(|os| os.to_str()) {
                 assert!(name.eq(today_dir.as_str()));
             }
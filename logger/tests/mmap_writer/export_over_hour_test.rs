@@ -7,7 +7,7 @@ pub mod export_over_hour_test {
     use logger::encrypt_util::encrypt_line;
     use std::fs::{File, OpenOptions};
     use std::io::{BufRead, BufReader, Write};
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
 
     fn get_today() -> (i32, u32, u32, u32) {
         let now_utc = chrono::Utc::now();
@@ -27,17 +27,12 @@ pub mod export_over_hour_test {
         )
     }
 
-    fn create_file_and_insert(
-        base_dir: &PathBuf,
-        subdir_name: &str,
-        file_name: &str,
-        content: &str,
-    ) {
+    fn create_file_and_insert(base_dir: &Path, subdir_name: &str, file_name: &str, content: &str) {
         // 创建目录和文件
-        create_subdir_and_file(&base_dir, &subdir_name, &file_name).unwrap();
+        create_subdir_and_file(base_dir, subdir_name, file_name).unwrap();
 
         // 写入内容
-        let file_path = base_dir.join(&subdir_name).join(&file_name);
+        let file_path = base_dir.join(subdir_name).join(file_name);
         let mut file = OpenOptions::new()
             .create(true) // 文件不存在就创建
             .append(true) // 在文件末尾追加
@@ -46,9 +41,9 @@ pub mod export_over_hour_test {
         writeln!(file, "{}", content).unwrap(); // 写入并自动添加换行符
     }
 
-    fn create_file_over_days(base_dir: &PathBuf, app_key: &str, is_encrypt: bool) -> Vec<String> {
+    fn create_file_over_days(base_dir: &Path, app_key: &str, is_encrypt: bool) -> Vec<String> {
         // 创建昨天 22 ~ 23 点的目录和日志
-        let (yesterday_year, yesterday_month, yesterday_day, yesterday_hour) = get_yesterday();
+        let (yesterday_year, yesterday_month, yesterday_day, _yesterday_hour) = get_yesterday();
         let yesterday = format!(
             "{:04}{:02}{:02}",
             yesterday_year, yesterday_month, yesterday_day
@@ -58,115 +53,115 @@ pub mod export_over_hour_test {
 
         let yesterday_22 = format!("{}_{:02}_{}.log", yesterday, 22, encrypt_str);
         if !is_encrypt {
-            create_file_and_insert(&base_dir, &yesterday, &yesterday_22, &yesterday_22);
+            create_file_and_insert(base_dir, &yesterday, &yesterday_22, &yesterday_22);
         } else {
             let encrypt_content = encrypt_line(app_key, &yesterday_22).unwrap();
-            create_file_and_insert(&base_dir, &yesterday, &yesterday_22, &encrypt_content);
+            create_file_and_insert(base_dir, &yesterday, &yesterday_22, &encrypt_content);
         }
 
         let yesterday_23 = format!("{}_{:02}_{}.log", yesterday, 23, encrypt_str);
         if !is_encrypt {
-            create_file_and_insert(&base_dir, &yesterday, &yesterday_23, &yesterday_23);
+            create_file_and_insert(base_dir, &yesterday, &yesterday_23, &yesterday_23);
         } else {
             let encrypt_content = encrypt_line(app_key, &yesterday_23).unwrap();
-            create_file_and_insert(&base_dir, &yesterday, &yesterday_23, &encrypt_content);
+            create_file_and_insert(base_dir, &yesterday, &yesterday_23, &encrypt_content);
         }
 
         // 创建今天 0 ~ 8 点的目录和日志
-        let (now_year, now_month, now_day, now_hour) = get_today();
+        let (now_year, now_month, now_day, _now_hour) = get_today();
         let today = format!("{:04}{:02}{:02}", now_year, now_month, now_day);
 
         let today_0 = format!("{}_{:02}_{}.log", today, 0, encrypt_str);
         if !is_encrypt {
-            create_file_and_insert(&base_dir, &today, &today_0, &today_0);
+            create_file_and_insert(base_dir, &today, &today_0, &today_0);
         } else {
             let encrypt_content = encrypt_line(app_key, &today_0).unwrap();
-            create_file_and_insert(&base_dir, &today, &today_0, &encrypt_content);
+            create_file_and_insert(base_dir, &today, &today_0, &encrypt_content);
         }
 
         let today_1 = format!("{}_{:02}_{}.log", today, 1, encrypt_str);
         if !is_encrypt {
-            create_file_and_insert(&base_dir, &today, &today_1, &today_1);
+            create_file_and_insert(base_dir, &today, &today_1, &today_1);
         } else {
             let encrypt_content = encrypt_line(app_key, &today_1).unwrap();
-            create_file_and_insert(&base_dir, &today, &today_1, &encrypt_content);
+            create_file_and_insert(base_dir, &today, &today_1, &encrypt_content);
         }
 
         let today_2 = format!("{}_{:02}_{}.log", today, 2, encrypt_str);
         if !is_encrypt {
-            create_file_and_insert(&base_dir, &today, &today_2, &today_2);
+            create_file_and_insert(base_dir, &today, &today_2, &today_2);
         } else {
             let encrypt_content = encrypt_line(app_key, &today_2).unwrap();
-            create_file_and_insert(&base_dir, &today, &today_2, &encrypt_content);
+            create_file_and_insert(base_dir, &today, &today_2, &encrypt_content);
         }
 
         let today_3 = format!("{}_{:02}_{}.log", today, 3, encrypt_str);
         if !is_encrypt {
-            create_file_and_insert(&base_dir, &today, &today_3, &today_3);
+            create_file_and_insert(base_dir, &today, &today_3, &today_3);
         } else {
             let encrypt_content = encrypt_line(app_key, &today_3).unwrap();
-            create_file_and_insert(&base_dir, &today, &today_3, &encrypt_content);
+            create_file_and_insert(base_dir, &today, &today_3, &encrypt_content);
         }
 
         let today_4 = format!("{}_{:02}_{}.log", today, 4, encrypt_str);
         if !is_encrypt {
-            create_file_and_insert(&base_dir, &today, &today_4, &today_4);
+            create_file_and_insert(base_dir, &today, &today_4, &today_4);
         } else {
             let encrypt_content = encrypt_line(app_key, &today_4).unwrap();
-            create_file_and_insert(&base_dir, &today, &today_4, &encrypt_content);
+            create_file_and_insert(base_dir, &today, &today_4, &encrypt_content);
         }
 
         let today_5 = format!("{}_{:02}_{}.log", today, 5, encrypt_str);
         if !is_encrypt {
-            create_file_and_insert(&base_dir, &today, &today_5, &today_5);
+            create_file_and_insert(base_dir, &today, &today_5, &today_5);
         } else {
             let encrypt_content = encrypt_line(app_key, &today_5).unwrap();
-            create_file_and_insert(&base_dir, &today, &today_5, &encrypt_content);
+            create_file_and_insert(base_dir, &today, &today_5, &encrypt_content);
         }
 
         let today_6 = format!("{}_{:02}_{}.log", today, 6, encrypt_str);
         if !is_encrypt {
-            create_file_and_insert(&base_dir, &today, &today_6, &today_6);
+            create_file_and_insert(base_dir, &today, &today_6, &today_6);
         } else {
             let encrypt_content = encrypt_line(app_key, &today_6).unwrap();
-            create_file_and_insert(&base_dir, &today, &today_6, &encrypt_content);
+            create_file_and_insert(base_dir, &today, &today_6, &encrypt_content);
         }
 
         let today_7 = format!("{}_{:02}_{}.log", today, 7, encrypt_str);
         if !is_encrypt {
-            create_file_and_insert(&base_dir, &today, &today_7, &today_7);
+            create_file_and_insert(base_dir, &today, &today_7, &today_7);
         } else {
             let encrypt_content = encrypt_line(app_key, &today_7).unwrap();
-            create_file_and_insert(&base_dir, &today, &today_7, &encrypt_content);
+            create_file_and_insert(base_dir, &today, &today_7, &encrypt_content);
         }
 
         let today_8 = format!("{}_{:02}_{}.log", today, 8, encrypt_str);
         if !is_encrypt {
-            create_file_and_insert(&base_dir, &today, &today_8, &today_8);
+            create_file_and_insert(base_dir, &today, &today_8, &today_8);
         } else {
             let encrypt_content = encrypt_line(app_key, &today_8).unwrap();
-            create_file_and_insert(&base_dir, &today, &today_8, &encrypt_content);
+            create_file_and_insert(base_dir, &today, &today_8, &encrypt_content);
         }
 
-        let mut content_vec = vec![];
-        content_vec.push(yesterday_22);
-        content_vec.push(yesterday_23);
-        content_vec.push(today_0);
-        content_vec.push(today_1);
-        content_vec.push(today_2);
-        content_vec.push(today_3);
-        content_vec.push(today_4);
-        content_vec.push(today_5);
-        content_vec.push(today_6);
-        content_vec.push(today_7);
-        content_vec.push(today_8);
-        content_vec
+        vec![
+            yesterday_22,
+            yesterday_23,
+            today_0,
+            today_1,
+            today_2,
+            today_3,
+            today_4,
+            today_5,
+            today_6,
+            today_7,
+            today_8,
+        ]
     }
 
     fn check_file_over_days(
         base_test: &BaseTest,
-        app_key: &str,
-        is_encrypt: bool,
+        _app_key: &str,
+        _is_encrypt: bool,
         content_vec: Vec<String>,
     ) {
         // 导出昨天 22 ~ 今天 8 点的日志
@@ -174,7 +169,7 @@ pub mod export_over_hour_test {
         let output = PathBuf::from("../target/tmp_log/encrypt_log.log");
 
         let arc_writer = base_test.get_mmap_writer();
-        let mut writer = arc_writer.borrow_mut();
+        let writer = arc_writer.borrow_mut();
         writer
             .export_logs(start_millis, end_millis, &output)
             .unwrap();
@@ -196,8 +191,8 @@ pub mod export_over_hour_test {
 
     #[test]
     fn test_export_over_hour_plain() {
-        let count = 1 * 100;
-        let length = 100;
+        let _count = 100;
+        let _length = 100;
         let app_key = "12345";
         let is_encrypt = false;
         let base_dir = PathBuf::from("../target/tmp_log");
@@ -210,8 +205,8 @@ pub mod export_over_hour_test {
 
     #[test]
     fn test_export_over_hour_encrypt() {
-        let count = 1 * 100;
-        let length = 100;
+        let _count = 100;
+        let _length = 100;
         let app_key = "12345";
         let is_encrypt = true;
         let base_dir = PathBuf::from("../target/tmp_log");
@@ -0,0 +1,148 @@
+/// 测试导出时按每条日志的写入时间精确过滤
+#[cfg(test)]
+pub mod export_range_test {
+    use crate::base::base_test::{export_lines, find_log_file, log_files};
+    use chrono::Utc;
+    use logger::encrypt_util::generate_key_pair;
+    use logger::log_file::{encode_record, read_log_file};
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::MmapWriter;
+    use logger::record::{Level, LineFormat, Record};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn check_millisecond_range(is_encrypt: bool, base_dir: &str) {
+        let base_dir = PathBuf::from(base_dir);
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("12345", is_encrypt);
+        config.set_line_format(LineFormat::new("{message}"));
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+
+        let before = Utc::now().timestamp_millis();
+        writer.write("text").unwrap();
+        let after = Utc::now().timestamp_millis();
+        // 结构化日志按日志中的时间记录写入时间
+        let base = before - 10;
+        for (i, message) in ["a", "b", "c"].into_iter().enumerate() {
+            let mut record = Record::new(Level::Info, "main", message);
+            record.timestamp_ms = base + i as i64;
+            writer.write_record(&record).unwrap();
+        }
+        writer.flush().unwrap();
+
+        assert_eq!(export_lines(&writer, before, after), vec!["text"]);
+        assert_eq!(export_lines(&writer, base + 1, base + 1), vec!["b"]);
+        assert_eq!(export_lines(&writer, base, base + 1), vec!["a", "b"]);
+        assert_eq!(
+            export_lines(&writer, base, after),
+            vec!["text", "a", "b", "c"]
+        );
+        assert_eq!(
+            export_lines(&writer, after + 1, after + 1),
+            Vec::<String>::new()
+        );
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_millisecond_range_plain() {
+        check_millisecond_range(false, "../target/tmp_log_export_range_plain");
+    }
+
+    #[test]
+    fn test_millisecond_range_encrypt() {
+        check_millisecond_range(true, "../target/tmp_log_export_range_encrypt");
+    }
+
+    #[test]
+    fn test_untimed_records() {
+        let base_dir = PathBuf::from("../target/tmp_log_export_range_untimed");
+        let _ = fs::remove_dir_all(&base_dir);
+        let config = MmapConfig::new("12345", false);
+        let mut writer = MmapWriter::try_new(&base_dir, config.clone()).unwrap();
+        writer.write("timed").unwrap();
+        drop(writer);
+        let after = Utc::now().timestamp_millis();
+
+        // 追加一条旧版本没有写入时间的记录
+        let path = find_log_file(&base_dir);
+        let content = read_log_file(&path).unwrap();
+        let mut header = content.header().unwrap().clone();
+        let mut data = content.data().to_vec();
+        data.extend_from_slice(&encode_record(0, b"untimed"));
+        header.committed_len = data.len() as u64;
+        let mut bytes = header.encode();
+        bytes.extend_from_slice(&data);
+        fs::write(&path, bytes).unwrap();
+
+        // 没有写入时间的记录按所在文件的时间周期过滤
+        let writer = MmapWriter::try_new(&base_dir, config).unwrap();
+        assert_eq!(export_lines(&writer, after + 1, after + 1), vec!["untimed"]);
+        assert_eq!(export_lines(&writer, 0, after), vec!["timed", "untimed"]);
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_skip_before_decrypt() {
+        let base_dir = PathBuf::from("../target/tmp_log_export_range_public_key");
+        let _ = fs::remove_dir_all(&base_dir);
+        let (_, public_key) = generate_key_pair().unwrap();
+        let mut config = MmapConfig::new("12345", true);
+        config.set_public_key(&hex::encode(public_key)).unwrap();
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+        writer.write("secret").unwrap();
+        writer.flush().unwrap();
+        let after = Utc::now().timestamp_millis();
+
+        // 不在范围内的记录不需要解密，不计入解密失败
+        let mut buffer = vec![];
+        let summary = writer.export_to(after + 1, after + 1, &mut buffer).unwrap();
+        assert!(buffer.is_empty());
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.lines, 0);
+        assert_eq!(summary.decrypt_failures, 0);
+
+        let summary = writer.export_to(0, after, &mut buffer).unwrap();
+        assert_eq!(summary.decrypt_failures, 1);
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_buffer_spans_windows() {
+        let base_dir = PathBuf::from("../target/tmp_log_export_range_windows");
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("12345", false);
+        config.set_line_format(LineFormat::new("{message}"));
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+
+        // 同一次刷新中的日志属于不同的时间周期
+        let now = Utc::now().timestamp_millis();
+        let earlier = now - 3600 * 1000;
+        let mut record = Record::new(Level::Info, "main", "earlier");
+        record.timestamp_ms = earlier;
+        writer.write_record(&record).unwrap();
+        writer.write("now").unwrap();
+        writer.flush().unwrap();
+
+        // 每条日志按写入时间写入所属周期的文件，按精确的时间范围也能导出
+        assert_eq!(log_files(&base_dir).len(), 2);
+        let mut buffer = vec![];
+        let summary = writer
+            .export_to(earlier - 1000, earlier + 1000, &mut buffer)
+            .unwrap();
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.lines, 1);
+        assert_eq!(String::from_utf8(buffer).unwrap(), "earlier\n");
+        let after = Utc::now().timestamp_millis();
+        assert_eq!(export_lines(&writer, now, after), vec!["now"]);
+        assert_eq!(
+            export_lines(&writer, earlier, after),
+            vec!["earlier", "now"]
+        );
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+}
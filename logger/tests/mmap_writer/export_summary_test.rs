@@ -0,0 +1,116 @@
+/// 测试导出到任意 `Write`，以及导出结果的统计
+#[cfg(test)]
+pub mod export_summary_test {
+    use crate::base::base_test::find_log_file;
+    use chrono::{Duration, Utc};
+    use logger::encrypt_util::generate_key_pair;
+    use logger::log_file::{
+        encode_record, read_log_file, RECORD_FLAG_STRUCTURED, RECORD_HEADER_SIZE,
+    };
+    use logger::log_reader::{ExportSummary, LogReader};
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::{MmapWriter, SharedMmapWriter};
+    use logger::record::{Level, LineFormat, Record};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn time_range() -> (i64, i64) {
+        let now = Utc::now();
+        (
+            (now - Duration::hours(1)).timestamp_millis(),
+            (now + Duration::hours(1)).timestamp_millis(),
+        )
+    }
+
+    #[test]
+    fn test_export_to_buffer() {
+        let base_dir = PathBuf::from("../target/tmp_log_export_summary");
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("12345", true);
+        config.set_line_format(LineFormat::new("{level} {message}"));
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+        writer.write("first").unwrap();
+        writer
+            .write_record(&Record::new(Level::Info, "main", "second"))
+            .unwrap();
+        writer.flush().unwrap();
+
+        let (start, end) = time_range();
+        let mut buffer = vec![];
+        let summary = writer.export_to(start, end, &mut buffer).unwrap();
+        assert_eq!(buffer, b"first\nINFO second\n");
+        assert_eq!(
+            summary,
+            ExportSummary {
+                files: 1,
+                lines: 2,
+                bytes: buffer.len() as u64,
+                decrypt_failures: 0,
+                decode_failures: 0,
+            }
+        );
+
+        // 导出到文件的内容和统计相同
+        let output = base_dir.join("export.log");
+        assert_eq!(writer.export_logs(start, end, &output).unwrap(), summary);
+        assert_eq!(fs::read(&output).unwrap(), buffer);
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_decode_failures() {
+        let base_dir = PathBuf::from("../target/tmp_log_export_summary_decode");
+        let _ = fs::remove_dir_all(&base_dir);
+        let config = MmapConfig::new("12345", false);
+        let mut writer = MmapWriter::try_new(&base_dir, config.clone()).unwrap();
+        writer.write("first").unwrap();
+        drop(writer);
+
+        // 追加一条无法解析的结构化日志和一条损坏的记录
+        let path = find_log_file(&base_dir);
+        let content = read_log_file(&path).unwrap();
+        let mut header = content.header().unwrap().clone();
+        let mut data = content.data().to_vec();
+        data.extend_from_slice(&encode_record(RECORD_FLAG_STRUCTURED, b"\xff"));
+        let mut broken = encode_record(0, b"broken");
+        broken[RECORD_HEADER_SIZE] ^= 0xff;
+        data.extend_from_slice(&broken);
+        header.committed_len = data.len() as u64;
+        let mut bytes = header.encode();
+        bytes.extend_from_slice(&data);
+        fs::write(&path, bytes).unwrap();
+
+        // 不创建 writer，避免启动时截断损坏的记录
+        let reader = LogReader::new(&base_dir, config);
+        let (start, end) = time_range();
+        let mut buffer = vec![];
+        let summary = reader.export_to(start, end, &mut buffer).unwrap();
+        assert_eq!(buffer, b"first\n\xff\n");
+        assert_eq!(summary.lines, 2);
+        assert_eq!(summary.decode_failures, 2);
+        assert_eq!(summary.decrypt_failures, 0);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_decrypt_failures() {
+        let base_dir = PathBuf::from("../target/tmp_log_export_summary_decrypt");
+        let _ = fs::remove_dir_all(&base_dir);
+        let (_, public_key) = generate_key_pair().unwrap();
+        let mut config = MmapConfig::new("12345", true);
+        config.set_public_key(&hex::encode(public_key)).unwrap();
+        let writer = SharedMmapWriter::try_new(&base_dir, config).unwrap();
+        writer.write("first").unwrap();
+        writer.write("second").unwrap();
+
+        // 共享的 writer 导出前先刷新暂存区
+        let (start, end) = time_range();
+        let summary = writer.export_to(start, end, std::io::sink()).unwrap();
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.lines, 2);
+        assert_eq!(summary.decrypt_failures, 2);
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+}
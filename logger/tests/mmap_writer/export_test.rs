@@ -9,7 +9,7 @@ pub mod export_test {
 
     #[test]
     fn test_export_plain_log() {
-        let count = 1 * 100;
+        let count = 100;
         let length = 100;
         let app_key = "12345";
         let is_encrypt = false;
@@ -18,7 +18,7 @@ pub mod export_test {
 
         let arc_writer = base_test.get_mmap_writer();
         let mut writer = arc_writer.borrow_mut();
-        for _ in 0..1 * count {
+        for _ in 0..count {
             let _ = writer.write(string_by_length(length).as_str());
         }
         writer.flush().unwrap();
@@ -39,12 +39,8 @@ pub mod export_test {
             let line = line_result.unwrap();
             line_count += 1;
 
-            if !line.starts_with("start") {
-                assert!(false);
-            }
-            if !line.ends_with("end") {
-                assert!(false);
-            }
+            assert!(line.starts_with("start"));
+            assert!(line.ends_with("end"));
         }
 
         assert_eq!(line_count, 100)
@@ -52,7 +48,7 @@ pub mod export_test {
 
     #[test]
     fn test_export_encrypt_log() {
-        let count = 1 * 100;
+        let count = 100;
         let length = 100;
         let app_key = "12345";
         let is_encrypt = true;
@@ -61,7 +57,7 @@ pub mod export_test {
 
         let arc_writer = base_test.get_mmap_writer();
         let mut writer = arc_writer.borrow_mut();
-        for _ in 0..1 * count {
+        for _ in 0..count {
             let _ = writer.write(string_by_length(length).as_str());
         }
         writer.flush().unwrap();
@@ -82,12 +78,8 @@ pub mod export_test {
             let line = line_result.unwrap();
             line_count += 1;
 
-            if !line.starts_with("start") {
-                assert!(false);
-            }
-            if !line.ends_with("end") {
-                assert!(false);
-            }
+            assert!(line.starts_with("start"));
+            assert!(line.ends_with("end"));
         }
 
         assert_eq!(line_count, 100)
@@ -0,0 +1,95 @@
+/// 测试日志文件的扩展策略
+#[cfg(test)]
+pub mod file_growth_test {
+    use crate::base::base_test::export_recent_lines;
+    use logger::log_file::read_log_header;
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::MmapWriter;
+    use logger::naming::NamingTemplate;
+    use logger::rotation::RotationPeriod;
+    use std::collections::BTreeSet;
+    use std::fs::{self, remove_dir_all};
+    use std::path::{Path, PathBuf};
+
+    fn log_file(base_dir: &Path) -> PathBuf {
+        let template = NamingTemplate::default_for(RotationPeriod::Hourly);
+        let files = template.find_files(base_dir).unwrap();
+        assert_eq!(files.len(), 1);
+        files[0].0.clone()
+    }
+
+    // 每次写入并刷新一条日志，返回每次刷新后的文件大小
+    fn write_and_collect_sizes(writer: &mut MmapWriter, base_dir: &Path, count: usize) -> Vec<u64> {
+        (0..count)
+            .map(|i| {
+                writer
+                    .write(&format!("start-{}-{}-end", i, "x".repeat(1000)))
+                    .unwrap();
+                writer.flush().unwrap();
+                fs::metadata(log_file(base_dir)).unwrap().len()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_geometric_growth() {
+        let base_dir = PathBuf::from("../target/tmp_log_growth_geometric");
+        let _ = remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("12345", false);
+        config.set_buffer_size(1024);
+        config.set_flush_size(1024);
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+
+        // 约 500 KB 数据，每次只扩展 1 KB 需要扩展几百次，成倍扩展只需要十次左右
+        let sizes = write_and_collect_sizes(&mut writer, &base_dir, 500);
+        let grows = sizes.iter().collect::<BTreeSet<_>>().len();
+        assert!(grows <= 12, "{} grows", grows);
+
+        // 预留的空间不超过已有数据的大小
+        let header = read_log_header(&log_file(&base_dir)).unwrap().unwrap();
+        let used = header.header_len as u64 + header.committed_len;
+        assert!(sizes[sizes.len() - 1] <= used * 2 + 2048);
+
+        assert_eq!(export_recent_lines(&writer).len(), 500);
+        drop(writer);
+        let _ = remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_fixed_grow_size() {
+        let base_dir = PathBuf::from("../target/tmp_log_growth_fixed");
+        let _ = remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("12345", false);
+        config.set_buffer_size(1024);
+        config.set_flush_size(1024);
+        config.set_file_grow_size(64 * 1024);
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+
+        let sizes = write_and_collect_sizes(&mut writer, &base_dir, 200);
+        // 每次扩展 64 KB
+        let grows: Vec<_> = sizes.iter().collect::<BTreeSet<_>>().into_iter().collect();
+        assert!(grows.len() >= 3);
+        for pair in grows.windows(2) {
+            let step = pair[1] - pair[0];
+            assert!((64 * 1024..64 * 1024 + 2048).contains(&step), "{}", step);
+        }
+
+        assert_eq!(export_recent_lines(&writer).len(), 200);
+
+        // 关闭后截断为实际长度，重新打开后继续追加
+        writer.close().unwrap();
+        let header = read_log_header(&log_file(&base_dir)).unwrap().unwrap();
+        let used = header.header_len as u64 + header.committed_len;
+        assert_eq!(fs::metadata(log_file(&base_dir)).unwrap().len(), used);
+
+        let mut config = MmapConfig::new("12345", false);
+        config.set_file_grow_size(64 * 1024);
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+        writer.write("after reopen").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(export_recent_lines(&writer).len(), 201);
+
+        drop(writer);
+        let _ = remove_dir_all(&base_dir);
+    }
+}
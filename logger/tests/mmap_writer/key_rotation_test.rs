@@ -0,0 +1,99 @@
+/// 测试使用密钥环更换 `app_key`：新日志使用新密钥，旧日志按文件头中的密钥 id 解密
+#[cfg(test)]
+pub mod key_rotation_test {
+    use crate::base::base_test::{export_recent_lines, log_files};
+    use logger::encrypt_util::KeyDerivation;
+    use logger::keyring::Keyring;
+    use logger::log_file::read_log_header;
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::MmapWriter;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn new_writer(base_dir: &PathBuf, keyring: Option<Keyring>) -> MmapWriter {
+        let mut config = MmapConfig::new("legacy-key", true);
+        config.set_key_derivation(KeyDerivation::Hkdf);
+        if let Some(keyring) = keyring {
+            config.set_keyring(keyring).unwrap();
+        }
+        MmapWriter::try_new(base_dir, config).unwrap()
+    }
+
+    // 所有日志文件头中的密钥 id，按文件路径排序
+    fn file_key_ids(dir: &Path) -> Vec<Option<String>> {
+        log_files(dir)
+            .iter()
+            .map(|file| {
+                let header = read_log_header(file).unwrap().unwrap();
+                header.cipher.unwrap().key_id
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rotate_key() {
+        let base_dir = PathBuf::from("../target/tmp_log_key_rotation");
+        let _ = fs::remove_dir_all(&base_dir);
+
+        // 使用密钥环之前的日志
+        let mut writer = new_writer(&base_dir, None);
+        writer.write("app key").unwrap();
+        drop(writer);
+
+        let mut writer = new_writer(&base_dir, Some(Keyring::new("v1", "first-key")));
+        writer.write("key v1").unwrap();
+        drop(writer);
+
+        let keyring = Keyring::new("v2", "second-key").with_retired_key("v1", "first-key");
+        let mut writer = new_writer(&base_dir, Some(keyring));
+        writer.write("key v2").unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(
+            export_recent_lines(&writer),
+            vec!["app key", "key v1", "key v2"]
+        );
+        assert_eq!(
+            file_key_ids(&base_dir),
+            vec![None, Some("v1".to_string()), Some("v2".to_string())]
+        );
+        drop(writer);
+
+        // 密钥环中没有旧密钥时，旧日志无法解密，原样输出密文
+        let writer = new_writer(&base_dir, Some(Keyring::new("v2", "second-key")));
+        let lines = export_recent_lines(&writer);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "app key");
+        assert_ne!(lines[1], "key v1");
+        assert_eq!(lines[2], "key v2");
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_longest_key_id() {
+        let base_dir = PathBuf::from("../target/tmp_log_key_rotation_longest_id");
+        let _ = fs::remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("legacy-key", true);
+        assert!(config
+            .set_keyring(Keyring::new(&"v".repeat(129), "key"))
+            .is_err());
+
+        // 最长的密钥 id 和编码最长的密钥派生参数，加密参数仍然可以写入缓存文件头
+        let key_id = "v".repeat(128);
+        config.set_key_derivation(KeyDerivation::Argon2 {
+            memory_kib: 64,
+            iterations: 1,
+            parallelism: 1,
+        });
+        config.set_keyring(Keyring::new(&key_id, "key")).unwrap();
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+        writer.write("longest key id").unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(export_recent_lines(&writer), vec!["longest key id"]);
+        assert_eq!(file_key_ids(&base_dir), vec![Some(key_id)]);
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+}
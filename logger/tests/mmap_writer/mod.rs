@@ -1,4 +1,22 @@
+mod background_flush_test;
+mod binary_cipher_test;
+mod cache_recover_test;
+mod cipher_mode_test;
+mod close_test;
 mod delete_expiration_days_test;
 mod export_over_hour_test;
+mod export_range_test;
+mod export_summary_test;
 mod export_test;
+mod file_growth_test;
+mod key_rotation_test;
+mod naming_template_test;
+mod public_key_test;
+mod quota_test;
+mod rotation_test;
+mod shared_write_test;
+mod structured_record_test;
+mod time_zone_test;
+mod torn_write_test;
 mod write_test;
+mod writer_lock_test;
@@ -0,0 +1,99 @@
+/// 测试自定义日志文件路径模板
+#[cfg(test)]
+pub mod naming_template_test {
+    use crate::base::base_test::export_recent_lines;
+    use chrono::{NaiveDate, Utc};
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::{delete_expired_files, MmapWriter};
+    use logger::naming::{LogFileName, NamingTemplate};
+    use logger::rotation::RotationPolicy;
+    use logger::time_zone::LogTimeZone;
+    use std::fs::{self, remove_dir_all};
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+
+    const TEMPLATE: &str = "{stream}/{yyyy}-{MM}-{dd}/{HH}h.{pid}.{mode}{slot}{part}.log";
+
+    fn new_writer(base_dir: &PathBuf, stream: &str) -> MmapWriter {
+        let mut config = MmapConfig::new("12345", false);
+        config.set_naming_template(TEMPLATE.parse().unwrap());
+        config.set_stream_name(stream);
+        MmapWriter::try_new(base_dir, config).unwrap()
+    }
+
+    #[test]
+    fn test_streams() {
+        let base_dir = PathBuf::from("../target/tmp_log_naming_streams");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut app = new_writer(&base_dir, "app");
+        let mut net = new_writer(&base_dir, "net");
+        app.write("start-app-end").unwrap();
+        app.flush().unwrap();
+        net.write("start-net-end").unwrap();
+        net.flush().unwrap();
+
+        // 两个日志流各自写入自己的目录，不会互相占用写入锁
+        let template: NamingTemplate = TEMPLATE.parse().unwrap();
+        let files = template.find_files(&base_dir).unwrap();
+        assert_eq!(files.len(), 2);
+        for (path, file_name) in &files {
+            assert!(path.starts_with(base_dir.join(&file_name.stream)));
+            assert_eq!(file_name.pid, Some(std::process::id()));
+            assert_eq!(file_name.slot, 0);
+        }
+
+        assert_eq!(export_recent_lines(&app), vec!["start-app-end"]);
+        assert_eq!(export_recent_lines(&net), vec!["start-net-end"]);
+    }
+
+    #[test]
+    fn test_template_without_hour() {
+        let base_dir = PathBuf::from("../target/tmp_log_naming_without_hour");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut config = MmapConfig::new("12345", false);
+        config.set_naming_template("{yyyy}{MM}{dd}_{mode}{slot}{part}.log".parse().unwrap());
+        let err = MmapWriter::try_new(&base_dir, config.clone())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        // 按天切分时不需要小时
+        config.set_rotation_policy(RotationPolicy::daily());
+        assert!(MmapWriter::try_new(&base_dir, config).is_ok());
+    }
+
+    #[test]
+    fn test_delete_expired_files() {
+        let base_dir = PathBuf::from("../target/tmp_log_naming_expire");
+        let _ = remove_dir_all(&base_dir);
+
+        let template: NamingTemplate = TEMPLATE.parse().unwrap();
+        let create = |date: NaiveDate| {
+            let name = LogFileName {
+                date,
+                hour: Some(1),
+                minute: None,
+                stream: "app".to_string(),
+                pid: Some(1),
+                is_encrypt: false,
+                slot: 0,
+                part: 0,
+            };
+            let path = base_dir.join(template.render(&name));
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, b"").unwrap();
+            path
+        };
+        let old = create(NaiveDate::from_ymd_opt(2022, 5, 1).unwrap());
+        fs::write(format!("{}.torn", old.display()), b"").unwrap();
+        let today = create(Utc::now().date_naive());
+
+        delete_expired_files(&base_dir, 7, LogTimeZone::Utc, &template).unwrap();
+        assert!(!old.exists());
+        // 空目录一起删除
+        assert!(!old.parent().unwrap().exists());
+        assert!(today.exists());
+    }
+}
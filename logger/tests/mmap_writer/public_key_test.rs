@@ -0,0 +1,92 @@
+/// 测试公钥加密：设备上只有服务端公钥，只有持有私钥才能解密
+#[cfg(test)]
+pub mod public_key_test {
+    use crate::base::base_test::log_files;
+    use chrono::{Duration, Utc};
+    use logger::encrypt_util::{generate_key_pair, CipherMode, LogCipher, KEY_SIZE};
+    use logger::log_file::read_log_file;
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::MmapWriter;
+    use std::fs::{self, File};
+    use std::io::{BufRead, BufReader};
+    use std::path::{Path, PathBuf};
+
+    fn new_writer(base_dir: &PathBuf, public_key: &[u8; KEY_SIZE]) -> MmapWriter {
+        let mut config = MmapConfig::new("12345", true);
+        config.set_public_key(&hex::encode(public_key)).unwrap();
+        MmapWriter::try_new(base_dir, config).unwrap()
+    }
+
+    // 用私钥解密所有日志文件，按文件路径排序
+    fn decrypt_all(base_dir: &Path, private_key: &[u8; KEY_SIZE]) -> Vec<Vec<String>> {
+        log_files(base_dir)
+            .iter()
+            .map(|file| {
+                let content = read_log_file(file).unwrap();
+                let params = content.header().unwrap().cipher.clone().unwrap();
+                let cipher = LogCipher::with_private_key(params, private_key).unwrap();
+                content
+                    .records(0)
+                    .map(|record| {
+                        let record = record.unwrap();
+                        assert!(record.is_encrypted());
+                        String::from_utf8(cipher.decrypt_record(&record).unwrap()).unwrap()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_public_key_encrypt() {
+        let base_dir = PathBuf::from("../target/tmp_log_public_key");
+        let _ = fs::remove_dir_all(&base_dir);
+        let (private_key, public_key) = generate_key_pair().unwrap();
+
+        let mut writer = new_writer(&base_dir, &public_key);
+        writer.write("first session").unwrap();
+        drop(writer);
+
+        // 每次会话使用新的临时密钥，写入新的分片
+        let mut writer = new_writer(&base_dir, &public_key);
+        writer.write("second session").unwrap();
+        writer.flush().unwrap();
+
+        assert_eq!(
+            decrypt_all(&base_dir, &private_key),
+            vec![vec!["first session"], vec!["second session"]]
+        );
+
+        // 设备上导出时无法解密，原样输出密文
+        let now = Utc::now();
+        let output = base_dir.join("export.log");
+        writer
+            .export_logs(
+                (now - Duration::hours(1)).timestamp_millis(),
+                (now + Duration::hours(1)).timestamp_millis(),
+                &output,
+            )
+            .unwrap();
+        let lines: Vec<String> = BufReader::new(File::open(&output).unwrap())
+            .lines()
+            .map(|line| line.unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| !line.contains("session")));
+        drop(writer);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+
+    #[test]
+    fn test_public_key_requires_aead() {
+        let base_dir = PathBuf::from("../target/tmp_log_public_key_ecb");
+        let _ = fs::remove_dir_all(&base_dir);
+        let (_, public_key) = generate_key_pair().unwrap();
+        let mut config = MmapConfig::new("12345", true);
+        config.set_public_key(&hex::encode(public_key)).unwrap();
+        config.set_cipher_mode(CipherMode::AesEcb);
+        let err = MmapWriter::try_new(&base_dir, config).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        let _ = fs::remove_dir_all(&base_dir);
+    }
+}
@@ -0,0 +1,94 @@
+/// 测试日志文件总大小配额
+#[cfg(test)]
+pub mod quota_test {
+    use crate::base::base_test::{export_recent_lines, log_files};
+    use chrono::{Duration, Utc};
+    use chrono_tz::Asia::Shanghai;
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::MmapWriter;
+
+    use logger::rotation::RotationPolicy;
+    use std::fs::{self, remove_dir_all};
+    use std::path::{Path, PathBuf};
+
+    // 所有日志文件和大小，按路径排序
+    fn file_sizes(base_dir: &Path) -> Vec<(PathBuf, u64)> {
+        log_files(base_dir)
+            .into_iter()
+            .map(|path| {
+                let size = fs::metadata(&path).unwrap().len();
+                (path, size)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_evict_on_startup() {
+        let base_dir = PathBuf::from("../target/tmp_log_quota_startup");
+        let _ = remove_dir_all(&base_dir);
+
+        // 昨天 1 ~ 3 点的日志文件，每个 1000 字节
+        let yesterday = (Utc::now().with_timezone(&Shanghai) - Duration::days(1)).date_naive();
+        let day = yesterday.format("%Y%m%d").to_string();
+        fs::create_dir_all(base_dir.join(&day)).unwrap();
+        let old_files: Vec<_> = (1..=3)
+            .map(|hour| {
+                let path = base_dir
+                    .join(&day)
+                    .join(format!("{}_{:02}_plain.log", day, hour));
+                fs::write(&path, vec![b'a'; 1000]).unwrap();
+                path
+            })
+            .collect();
+
+        let mut config = MmapConfig::new("12345", false);
+        config.set_max_total_bytes(2500);
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+        writer.flush().unwrap();
+
+        // 只删除最早的一个
+        assert!(!old_files[0].exists());
+        assert!(old_files[1].exists());
+        assert!(old_files[2].exists());
+
+        let lines = export_recent_lines(&writer);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("[logger] quota evicted"));
+        assert!(lines[0].contains("_01_plain.log"));
+        assert!(lines[0].contains("1000 bytes"));
+    }
+
+    #[test]
+    fn test_evict_on_rollover() {
+        let base_dir = PathBuf::from("../target/tmp_log_quota_rollover");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut config = MmapConfig::new("12345", false);
+        config.set_buffer_size(1024);
+        config.set_flush_size(1024);
+        config.set_rotation_policy(RotationPolicy::max_bytes(100));
+        // 写满的分片会截断为实际长度，正在写入的分片预留 buffer_size 的空间
+        config.set_max_total_bytes(1500);
+        let mut writer = MmapWriter::try_new(&base_dir, config).unwrap();
+
+        for i in 0..20 {
+            writer.write(&format!("start-{}-end", i)).unwrap();
+            writer.flush().unwrap();
+
+            let files = file_sizes(&base_dir);
+            let total: u64 = files.iter().map(|(_, size)| size).sum();
+            assert!(total <= 1500, "{:?}", files);
+        }
+
+        // 最早的分片被删除，正在写入的分片保留
+        let files = file_sizes(&base_dir);
+        assert!(files.len() >= 2, "{:?}", files);
+        assert!(files.iter().all(|(path, _)| !path.ends_with("_plain.log")));
+
+        let lines = export_recent_lines(&writer);
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("[logger] quota evicted")));
+        assert_eq!(lines.last().map(String::as_str), Some("start-19-end"));
+    }
+}
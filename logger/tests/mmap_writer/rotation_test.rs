@@ -0,0 +1,132 @@
+/// 测试日志文件切分策略
+#[cfg(test)]
+pub mod rotation_test {
+    use crate::base::base_test::export_recent_lines;
+    use chrono::{DateTime, Datelike, Timelike, Utc};
+    use chrono_tz::Asia::Shanghai;
+    use logger::log_file::{read_log_file, read_log_header};
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::MmapWriter;
+    use logger::rotation::RotationPolicy;
+    use std::fs::remove_dir_all;
+    use std::path::{Path, PathBuf};
+
+    fn new_writer(base_dir: &PathBuf, rotation_policy: RotationPolicy) -> MmapWriter {
+        let mut config = MmapConfig::new("12345", false);
+        config.set_rotation_policy(rotation_policy);
+        MmapWriter::try_new(base_dir, config).unwrap()
+    }
+
+    fn day_dir(base_dir: &Path, now: &DateTime<Utc>) -> (PathBuf, String) {
+        let now = now.with_timezone(&Shanghai);
+        let day = format!("{:04}{:02}{:02}", now.year(), now.month(), now.day());
+        (base_dir.join(&day), day)
+    }
+
+    #[test]
+    fn test_daily() {
+        let base_dir = PathBuf::from("../target/tmp_log_rotation_daily");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut writer = new_writer(&base_dir, RotationPolicy::daily());
+        writer.write("start-0-end").unwrap();
+        writer.flush().unwrap();
+
+        let (dir, day) = day_dir(&base_dir, &Utc::now());
+        let path = dir.join(format!("{}_plain.log", day));
+        let header = read_log_header(&path).unwrap().unwrap();
+        assert_eq!(header.window_minutes, Some(24 * 60));
+
+        assert_eq!(export_recent_lines(&writer), vec!["start-0-end"]);
+    }
+
+    #[test]
+    fn test_every_minutes() {
+        let base_dir = PathBuf::from("../target/tmp_log_rotation_minutes");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut writer = new_writer(&base_dir, RotationPolicy::every_minutes(15));
+        let before = Utc::now();
+        writer.write("start-0-end").unwrap();
+        writer.flush().unwrap();
+        let after = Utc::now();
+
+        // 写入时可能正好跨过周期边界，两个时间点对应的文件有一个存在即可
+        let path_of = |now: &DateTime<Utc>| {
+            let (dir, day) = day_dir(&base_dir, now);
+            let local = now.with_timezone(&Shanghai);
+            let minute = local.minute() / 15 * 15;
+            dir.join(format!(
+                "{}_{:02}{:02}_plain.log",
+                day,
+                local.hour(),
+                minute
+            ))
+        };
+        let path = [path_of(&before), path_of(&after)]
+            .into_iter()
+            .find(|path| path.exists())
+            .unwrap();
+        let header = read_log_header(&path).unwrap().unwrap();
+        assert_eq!(header.window_minutes, Some(15));
+
+        assert_eq!(export_recent_lines(&writer), vec!["start-0-end"]);
+    }
+
+    #[test]
+    fn test_max_bytes() {
+        let base_dir = PathBuf::from("../target/tmp_log_rotation_max_bytes");
+        let _ = remove_dir_all(&base_dir);
+
+        // 每条记录 29 字节，每个文件最多放下 2 条
+        let mut writer = new_writer(&base_dir, RotationPolicy::daily().with_max_bytes(60));
+        let mut expected = vec![];
+        for i in 0..5 {
+            let message = format!("start-{}-end", i);
+            writer.write(&message).unwrap();
+            writer.flush().unwrap();
+            expected.push(message);
+        }
+
+        let (dir, day) = day_dir(&base_dir, &Utc::now());
+        let payloads = |name: String| {
+            let content = read_log_file(&dir.join(name)).unwrap();
+            content
+                .records(0)
+                .map(|r| r.unwrap().payload.to_vec())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(payloads(format!("{}_plain.log", day)).len(), 2);
+        assert_eq!(payloads(format!("{}_plain_part1.log", day)).len(), 2);
+        assert_eq!(payloads(format!("{}_plain_part2.log", day)).len(), 1);
+
+        assert_eq!(export_recent_lines(&writer), expected);
+
+        // 重新打开后继续写最后一个分片
+        drop(writer);
+        let mut writer = new_writer(&base_dir, RotationPolicy::daily().with_max_bytes(60));
+        writer.write("start-5-end").unwrap();
+        writer.flush().unwrap();
+        assert_eq!(payloads(format!("{}_plain_part2.log", day)).len(), 2);
+        assert!(!dir.join(format!("{}_plain_part3.log", day)).exists());
+    }
+
+    #[test]
+    fn test_export_after_policy_change() {
+        let base_dir = PathBuf::from("../target/tmp_log_rotation_change");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut writer = new_writer(&base_dir, RotationPolicy::hourly());
+        writer.write("start-0-end").unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        let mut writer = new_writer(&base_dir, RotationPolicy::every_minutes(5));
+        writer.write("start-1-end").unwrap();
+        writer.flush().unwrap();
+
+        let mut lines = export_recent_lines(&writer);
+        lines.sort();
+        assert_eq!(lines, vec!["start-0-end", "start-1-end"]);
+    }
+}
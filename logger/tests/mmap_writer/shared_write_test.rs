@@ -0,0 +1,115 @@
+#[cfg(test)]
+pub mod shared_write_test {
+    use crate::base::random_tool::string_by_length;
+    use chrono::{Duration, Utc};
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::SharedMmapWriter;
+    use std::fs::{self, remove_dir_all, File};
+    use std::io::{BufRead, BufReader};
+    use std::path::PathBuf;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<SharedMmapWriter>();
+    }
+
+    #[test]
+    fn test_write_plain_log_multi_thread() {
+        check_multi_thread(false, "../target/tmp_log_shared_plain");
+    }
+
+    #[test]
+    fn test_write_encrypt_log_multi_thread() {
+        check_multi_thread(true, "../target/tmp_log_shared_encrypt");
+    }
+
+    fn check_multi_thread(is_encrypt: bool, base_dir: &str) {
+        let thread_count = 8;
+        let count = 500;
+        let length = 100;
+        let app_key = "12345";
+        let base_dir = PathBuf::from(base_dir);
+        let _ = remove_dir_all(&base_dir);
+
+        let mut config = MmapConfig::new(app_key, is_encrypt);
+        // 调小刷新尺寸，让各线程的暂存区频繁合并
+        config.set_flush_size(1024);
+        let writer = SharedMmapWriter::try_new(&base_dir, config).unwrap();
+
+        let handles: Vec<_> = (0..thread_count)
+            .map(|_| {
+                let writer = writer.clone();
+                thread::spawn(move || {
+                    for _ in 0..count {
+                        writer.write(string_by_length(length).as_str()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let now = Utc::now();
+        let start_millis = (now - Duration::hours(1)).timestamp_millis();
+        let end_millis = (now + Duration::hours(1)).timestamp_millis();
+        let output = base_dir.join("export.log");
+        writer
+            .export_logs(start_millis, end_millis, &output)
+            .unwrap();
+
+        let file = File::open(&output).unwrap();
+        let reader = BufReader::new(file);
+        let mut line_count = 0;
+        for line_result in reader.lines() {
+            let line = line_result.unwrap();
+            line_count += 1;
+
+            // 每一行都应该是完整的
+            assert!(line.starts_with("start"));
+            assert!(line.ends_with("end"));
+            assert_eq!(line.len(), "start--end".len() + length as usize);
+        }
+        assert_eq!(line_count, thread_count * count);
+    }
+
+    #[test]
+    fn test_short_lived_threads() {
+        let base_dir = PathBuf::from("../target/tmp_log_shared_short_lived");
+        let _ = remove_dir_all(&base_dir);
+        let writer = SharedMmapWriter::try_new(&base_dir, MmapConfig::new("12345", false)).unwrap();
+
+        let thread_count = 200;
+        for i in 0..thread_count {
+            let writer = writer.clone();
+            thread::spawn(move || writer.write(&format!("thread-{}-end", i)).unwrap())
+                .join()
+                .unwrap();
+        }
+
+        // 线程退出时暂存区已经合并到 writer 的缓存文件，不需要等到 flush
+        let cache = fs::read(base_dir.join("mmap_plain.cache")).unwrap();
+        for i in 0..thread_count {
+            let message = format!("thread-{}-end", i);
+            assert!(cache
+                .windows(message.len())
+                .any(|window| window == message.as_bytes()));
+        }
+
+        let now = Utc::now();
+        let mut buffer = vec![];
+        let summary = writer
+            .export_to(
+                (now - Duration::hours(1)).timestamp_millis(),
+                (now + Duration::hours(1)).timestamp_millis(),
+                &mut buffer,
+            )
+            .unwrap();
+        assert_eq!(summary.lines, thread_count);
+        drop(writer);
+        let _ = remove_dir_all(&base_dir);
+    }
+}
@@ -0,0 +1,92 @@
+/// 测试结构化日志的写入和导出
+#[cfg(test)]
+pub mod structured_record_test {
+    use crate::base::base_test::export_recent_lines;
+    use chrono::{Duration, Utc};
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::{MmapWriter, SharedMmapWriter};
+    use logger::record::{current_thread_id, Level, LineFormat, Record};
+    use std::fs::{remove_dir_all, File};
+    use std::io::{BufRead, BufReader};
+    use std::path::PathBuf;
+    use std::thread;
+
+    fn write_and_export(base_dir: &PathBuf, is_encrypt: bool) {
+        let _ = remove_dir_all(base_dir);
+        let mut config = MmapConfig::new("12345", is_encrypt);
+        config.set_line_format(LineFormat::new("{level}|{tag}|{thread}|{message}"));
+        let mut writer = MmapWriter::try_new(base_dir, config).unwrap();
+
+        writer.write("plain text").unwrap();
+        writer
+            .write_record(&Record::new(Level::Info, "main", "started"))
+            .unwrap();
+        writer
+            .write_record(&Record::new(Level::Error, "db", "connection lost"))
+            .unwrap();
+        writer.flush().unwrap();
+
+        let thread_id = current_thread_id();
+        assert_eq!(
+            export_recent_lines(&writer),
+            vec![
+                "plain text".to_string(),
+                format!("INFO|main|{}|started", thread_id),
+                format!("ERROR|db|{}|connection lost", thread_id),
+            ]
+        );
+        drop(writer);
+        let _ = remove_dir_all(base_dir);
+    }
+
+    #[test]
+    fn test_export_plain_records() {
+        write_and_export(&PathBuf::from("../target/tmp_log_record_plain"), false);
+    }
+
+    #[test]
+    fn test_export_encrypt_records() {
+        write_and_export(&PathBuf::from("../target/tmp_log_record_encrypt"), true);
+    }
+
+    #[test]
+    fn test_shared_write_record() {
+        let base_dir = PathBuf::from("../target/tmp_log_record_shared");
+        let _ = remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("12345", false);
+        config.set_line_format(LineFormat::new("{thread} {message}"));
+        let writer = SharedMmapWriter::try_new(&base_dir, config).unwrap();
+
+        let handles: Vec<_> = (0..2)
+            .map(|i| {
+                let writer = writer.clone();
+                thread::spawn(move || {
+                    let message = format!("thread {}", i);
+                    writer
+                        .write_record(&Record::new(Level::Debug, "worker", &message))
+                        .unwrap();
+                    format!("{} {}", current_thread_id(), message)
+                })
+            })
+            .collect();
+        let mut expected: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+        let now = Utc::now();
+        let output = base_dir.join("export.log");
+        writer
+            .export_logs(
+                (now - Duration::minutes(1)).timestamp_millis(),
+                (now + Duration::minutes(1)).timestamp_millis(),
+                &output,
+            )
+            .unwrap();
+        let reader = BufReader::new(File::open(&output).unwrap());
+        let mut lines: Vec<_> = reader.lines().map(|line| line.unwrap()).collect();
+
+        lines.sort();
+        expected.sort();
+        assert_eq!(lines, expected);
+        drop(writer);
+        let _ = remove_dir_all(&base_dir);
+    }
+}
@@ -0,0 +1,156 @@
+/// 测试配置日志时区
+#[cfg(test)]
+pub mod time_zone_test {
+    use crate::base::base_test::{create_subdir_and_file, export_lines, find_log_file};
+    use chrono::{Datelike, Duration, NaiveDate, TimeZone, Timelike, Utc};
+    use chrono_tz::America::Santiago;
+    use logger::log_file::read_log_header;
+    use logger::log_reader::LogReader;
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::{delete_expired_directories_in, MmapWriter};
+    use logger::naming::LogFileName;
+    use logger::rotation::RotationPolicy;
+    use logger::time_zone::LogTimeZone;
+    use std::fs::{self, remove_dir_all};
+    use std::path::PathBuf;
+
+    fn new_writer(base_dir: &PathBuf, time_zone: LogTimeZone) -> MmapWriter {
+        let mut config = MmapConfig::new("12345", false);
+        config.set_time_zone(time_zone);
+        MmapWriter::try_new(base_dir, config).unwrap()
+    }
+
+    #[test]
+    fn test_utc_file_name() {
+        let base_dir = PathBuf::from("../target/tmp_log_time_zone_utc");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut writer = new_writer(&base_dir, LogTimeZone::Utc);
+        writer.write("start-0-end").unwrap();
+        writer.flush().unwrap();
+
+        let now = Utc::now();
+        let day = format!("{:04}{:02}{:02}", now.year(), now.month(), now.day());
+        let path = base_dir
+            .join(&day)
+            .join(format!("{}_{:02}_plain.log", day, now.hour()));
+        let header = read_log_header(&path).unwrap().unwrap();
+        assert_eq!(header.time_zone, Some(LogTimeZone::Utc));
+    }
+
+    #[test]
+    fn test_export_after_time_zone_change() {
+        let base_dir = PathBuf::from("../target/tmp_log_time_zone_change");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut writer = new_writer(&base_dir, LogTimeZone::default());
+        writer.write("start-0-end").unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+
+        // 修改时区后，之前的日志文件按它记录的时区导出
+        let offset = LogTimeZone::from_offset_seconds(-(5 * 3600 + 30 * 60)).unwrap();
+        let mut writer = new_writer(&base_dir, offset);
+        writer.write("start-1-end").unwrap();
+        writer.flush().unwrap();
+
+        let now = Utc::now();
+        let mut lines = export_lines(
+            &writer,
+            (now - Duration::minutes(1)).timestamp_millis(),
+            (now + Duration::minutes(1)).timestamp_millis(),
+        );
+        lines.sort();
+        assert_eq!(lines, vec!["start-0-end", "start-1-end"]);
+
+        // 时间范围之外的文件不导出
+        let lines = export_lines(
+            &writer,
+            (now + Duration::hours(2)).timestamp_millis(),
+            (now + Duration::hours(3)).timestamp_millis(),
+        );
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn test_delete_expired_in_time_zone() {
+        let now = Utc::now();
+        let yesterday = (now - Duration::days(1)).format("%Y%m%d").to_string();
+
+        // 东 23 区的昨天 00:00 是 UTC 的前天 01:00，已经过期
+        let east_dir = PathBuf::from("../target/tmp_log_time_zone_expire_east");
+        let _ = remove_dir_all(&east_dir);
+        create_subdir_and_file(&east_dir, &yesterday, "test.log").unwrap();
+        let east: LogTimeZone = "+23:00".parse().unwrap();
+        delete_expired_directories_in(&east_dir, 1, east).unwrap();
+        assert!(!east_dir.join(&yesterday).exists());
+
+        // 西 23 区的昨天 00:00 是 UTC 的昨天 23:00，UTC 当天 23 点之前都未过期
+        let west_dir = PathBuf::from("../target/tmp_log_time_zone_expire_west");
+        let _ = remove_dir_all(&west_dir);
+        create_subdir_and_file(&west_dir, &yesterday, "test.log").unwrap();
+        let west: LogTimeZone = "-23:00".parse().unwrap();
+        delete_expired_directories_in(&west_dir, 1, west).unwrap();
+        if now.hour() < 23 {
+            assert!(fs::metadata(west_dir.join(&yesterday)).unwrap().is_dir());
+        }
+    }
+
+    #[test]
+    fn test_dst_gap_at_midnight() {
+        // 2024-09-08 America/Santiago 从 00:00 跳到 01:00，当天从 UTC 04:00 开始
+        let time_zone = LogTimeZone::Named(Santiago);
+        let gap_day = NaiveDate::from_ymd_opt(2024, 9, 8).unwrap();
+        let day_start = Utc.with_ymd_and_hms(2024, 9, 8, 4, 0, 0).unwrap();
+        let midnight = gap_day.and_hms_opt(0, 0, 0).unwrap();
+        assert_eq!(time_zone.to_utc(&midnight), Some(day_start));
+
+        let base_dir = PathBuf::from("../target/tmp_log_time_zone_dst_gap");
+        let _ = remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("12345", false);
+        config.set_time_zone(time_zone);
+        config.set_rotation_policy(RotationPolicy::daily());
+        let mut writer = MmapWriter::try_new(&base_dir, config.clone()).unwrap();
+        writer.write("start-0-end").unwrap();
+        drop(writer);
+
+        // 把日志文件移到跳过 00:00 的那一天
+        let name = LogFileName {
+            date: gap_day,
+            hour: None,
+            minute: None,
+            stream: String::new(),
+            pid: None,
+            is_encrypt: false,
+            slot: 0,
+            part: 0,
+        };
+        let gap_path = base_dir.join(config.get_naming_template().render(&name));
+        fs::create_dir_all(gap_path.parent().unwrap()).unwrap();
+        fs::rename(find_log_file(&base_dir), &gap_path).unwrap();
+
+        let reader = LogReader::new(&base_dir, config);
+        let files = reader
+            .files(
+                day_start.timestamp_millis(),
+                (day_start + Duration::minutes(30)).timestamp_millis(),
+            )
+            .unwrap();
+        assert_eq!(files, vec![gap_path]);
+        let files = reader
+            .files(
+                (day_start - Duration::hours(1)).timestamp_millis(),
+                day_start.timestamp_millis() - 1,
+            )
+            .unwrap();
+        assert!(files.is_empty());
+        let _ = remove_dir_all(&base_dir);
+
+        // 这一天的目录按 UTC 04:00 判断是否过期
+        let expire_dir = PathBuf::from("../target/tmp_log_time_zone_dst_gap_expire");
+        let _ = remove_dir_all(&expire_dir);
+        create_subdir_and_file(&expire_dir, "20240908", "test.log").unwrap();
+        delete_expired_directories_in(&expire_dir, 1, time_zone).unwrap();
+        assert!(!expire_dir.join("20240908").exists());
+    }
+}
@@ -0,0 +1,107 @@
+/// 测试进程在写入日志文件时退出，启动时截断写入不完整的数据
+#[cfg(test)]
+pub mod torn_write_test {
+    use crate::base::base_test::BaseTest;
+    use chrono::{Datelike, Timelike, Utc};
+    use chrono_tz::Asia::Shanghai;
+    use logger::log_file::{encode_record, read_log_file, LogHeader};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn current_log_file(base_dir: &Path) -> PathBuf {
+        let now = Utc::now().with_timezone(&Shanghai);
+        let day = format!("{:04}{:02}{:02}", now.year(), now.month(), now.day());
+        let file_name = format!("{}_{:02}_plain.log", day, now.hour());
+        base_dir.join(day).join(file_name)
+    }
+
+    fn write_file(path: &Path, content: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    // 恢复后的日志内容，最后一条是恢复信息
+    fn check_recovered(base_test: &BaseTest, path: &Path, expected: &[&[u8]]) {
+        let rc_writer = base_test.get_mmap_writer();
+        rc_writer.borrow_mut().flush().unwrap();
+
+        let content = read_log_file(path).unwrap();
+        let payloads: Vec<_> = content.records(0).map(|r| r.unwrap().payload).collect();
+        assert_eq!(payloads.len(), expected.len() + 1);
+        assert_eq!(&payloads[..expected.len()], expected);
+
+        let marker = String::from_utf8(payloads[expected.len()].to_vec()).unwrap();
+        assert!(marker.starts_with("[logger] recovered torn write"));
+        assert!(marker.contains("discarded 7 bytes"));
+    }
+
+    #[test]
+    fn test_torn_record() {
+        let base_dir = PathBuf::from("../target/tmp_log_torn_record");
+        let _ = fs::remove_dir_all(&base_dir);
+        let path = current_log_file(&base_dir);
+
+        // 第三条记录只写入了 7 个字节，但文件头的长度已经更新
+        let mut data = encode_record(0, b"start-0-end");
+        data.extend_from_slice(&encode_record(0, b"start-1-end"));
+        let torn = encode_record(0, b"start-2-end")[..7].to_vec();
+        data.extend_from_slice(&torn);
+        let header = LogHeader {
+            committed_len: data.len() as u64,
+            ..LogHeader::default()
+        };
+        let mut content = header.encode().to_vec();
+        content.extend_from_slice(&data);
+        content.resize(content.len() + 1024, 0);
+        write_file(&path, &content);
+
+        let base_test = BaseTest::new("12345", &base_dir, false, false);
+        check_recovered(&base_test, &path, &[b"start-0-end", b"start-1-end"]);
+
+        // 丢弃的数据保存在 .torn 文件中
+        let quarantine = fs::read(path.with_extension("log.torn")).unwrap();
+        assert_eq!(quarantine, torn);
+    }
+
+    #[test]
+    fn test_torn_legacy_line() {
+        let base_dir = PathBuf::from("../target/tmp_log_torn_line");
+        let _ = fs::remove_dir_all(&base_dir);
+        let path = current_log_file(&base_dir);
+
+        // 旧版本文件，最后一行没有写完
+        let mut content = b"start-0-end\nstart-1-end\nstart-2".to_vec();
+        content.resize(content.len() + 1024, 0);
+        write_file(&path, &content);
+
+        let base_test = BaseTest::new("12345", &base_dir, false, false);
+        check_recovered(&base_test, &path, &[b"start-0-end", b"start-1-end"]);
+
+        let quarantine = fs::read(path.with_extension("log.torn")).unwrap();
+        assert_eq!(quarantine, b"start-2");
+
+        // 转换后的文件头记录了 writer 的时区和进程
+        let header = read_log_file(&path).unwrap().header().unwrap().clone();
+        assert!(header.time_zone.is_some());
+        assert_eq!(header.pid, Some(std::process::id()));
+    }
+
+    #[test]
+    fn test_no_torn_write() {
+        let base_dir = PathBuf::from("../target/tmp_log_torn_none");
+        let base_test = BaseTest::new("12345", &base_dir, false, true);
+        let path = current_log_file(&base_dir);
+        {
+            let rc_writer = base_test.get_mmap_writer();
+            let mut writer = rc_writer.borrow_mut();
+            writer.write("start-0-end").unwrap();
+            writer.flush().unwrap();
+        }
+
+        // 文件完整时不会写入恢复信息
+        let _base_test = BaseTest::new("12345", &base_dir, false, false);
+        let content = read_log_file(&path).unwrap();
+        assert_eq!(content.records(0).count(), 1);
+        assert!(!path.with_extension("log.torn").exists());
+    }
+}
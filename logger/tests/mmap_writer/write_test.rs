@@ -7,7 +7,7 @@ pub mod write_test {
 
     #[test]
     fn test_write_plain_log() {
-        let count = 1 * 10000;
+        let count = 10000;
         let length = 100;
         let app_key = "12345";
         let is_encrypt = false;
@@ -18,7 +18,7 @@ pub mod write_test {
         let mut writer = rc_writer.borrow_mut();
         // 添加计时开始点
         let start = Instant::now();
-        for _ in 0..1 * count {
+        for _ in 0..count {
             let _ = writer.write(string_by_length(length).as_str());
         }
         // 计算总耗时
@@ -32,7 +32,7 @@ pub mod write_test {
 
     #[test]
     fn test_write_encrypt_log() {
-        let count = 1 * 10000;
+        let count = 10000;
         let length = 100;
         let app_key = "12345";
         let is_encrypt = true;
@@ -43,7 +43,7 @@ pub mod write_test {
         let mut writer = rc_writer.borrow_mut();
         // 添加计时开始点
         let start = Instant::now();
-        for _ in 0..1 * count {
+        for _ in 0..count {
             let _ = writer.write(string_by_length(length).as_str());
         }
         // 计算总耗时
@@ -0,0 +1,86 @@
+/// 测试多个 writer 使用同一个 base_dir
+#[cfg(test)]
+pub mod writer_lock_test {
+    use crate::base::base_test::export_recent_lines;
+    use chrono::{Datelike, Timelike, Utc};
+    use chrono_tz::Asia::Shanghai;
+    use logger::mmap_config::{LockMode, MmapConfig};
+    use logger::mmap_writer::MmapWriter;
+    use std::fs::remove_dir_all;
+    use std::io::ErrorKind;
+    use std::path::PathBuf;
+    use std::sync::mpsc;
+    use std::thread;
+
+    fn new_writer(base_dir: &PathBuf, lock_mode: LockMode) -> std::io::Result<MmapWriter> {
+        let mut config = MmapConfig::new("12345", false);
+        config.set_lock_mode(lock_mode);
+        MmapWriter::try_new(base_dir, config)
+    }
+
+    #[test]
+    fn test_fail_fast() {
+        let base_dir = PathBuf::from("../target/tmp_log_lock_fail_fast");
+        let _ = remove_dir_all(&base_dir);
+
+        let first = new_writer(&base_dir, LockMode::FailFast).unwrap();
+        let err = new_writer(&base_dir, LockMode::FailFast).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::WouldBlock);
+
+        // 释放后可以再次创建
+        drop(first);
+        assert!(new_writer(&base_dir, LockMode::FailFast).is_ok());
+    }
+
+    #[test]
+    fn test_wait() {
+        let base_dir = PathBuf::from("../target/tmp_log_lock_wait");
+        let _ = remove_dir_all(&base_dir);
+
+        let first = new_writer(&base_dir, LockMode::Wait).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let thread_dir = base_dir.clone();
+        let handle = thread::spawn(move || {
+            let writer = new_writer(&thread_dir, LockMode::Wait);
+            sender.send(writer.is_ok()).unwrap();
+        });
+
+        // 第一个 writer 释放前一直等待
+        assert!(receiver
+            .recv_timeout(std::time::Duration::from_millis(300))
+            .is_err());
+        drop(first);
+        assert!(receiver
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .unwrap());
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_process_suffix() {
+        let base_dir = PathBuf::from("../target/tmp_log_lock_suffix");
+        let _ = remove_dir_all(&base_dir);
+
+        let mut first = new_writer(&base_dir, LockMode::ProcessSuffix).unwrap();
+        let mut second = new_writer(&base_dir, LockMode::ProcessSuffix).unwrap();
+        first.write("start-first-end").unwrap();
+        first.flush().unwrap();
+        second.write("start-second-end").unwrap();
+        second.flush().unwrap();
+
+        // 第二个 writer 使用带后缀的文件
+        let now = Utc::now().with_timezone(&Shanghai);
+        let day = format!("{:04}{:02}{:02}", now.year(), now.month(), now.day());
+        let dir = base_dir.join(&day);
+        assert!(dir
+            .join(format!("{}_{:02}_plain.log", day, now.hour()))
+            .exists());
+        assert!(dir
+            .join(format!("{}_{:02}_plain_p1.log", day, now.hour()))
+            .exists());
+
+        // 导出时合并两个 writer 的日志
+        let lines = export_recent_lines(&first);
+        assert_eq!(lines, vec!["start-first-end", "start-second-end"]);
+    }
+}
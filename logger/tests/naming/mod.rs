@@ -0,0 +1 @@
+mod naming_test;
@@ -0,0 +1,134 @@
+#[cfg(test)]
+pub mod naming_test {
+    use chrono::NaiveDate;
+    use logger::naming::{LogFileName, NamingTemplate};
+    use logger::rotation::RotationPeriod;
+    use std::fs::{self, remove_dir_all};
+    use std::path::{Path, PathBuf};
+
+    fn file_name() -> LogFileName {
+        LogFileName {
+            date: NaiveDate::from_ymd_opt(2025, 5, 20).unwrap(),
+            hour: Some(10),
+            minute: Some(45),
+            stream: String::new(),
+            pid: None,
+            is_encrypt: false,
+            slot: 0,
+            part: 0,
+        }
+    }
+
+    #[test]
+    fn test_default_templates() {
+        let render = |period| NamingTemplate::default_for(period).render(&file_name());
+        assert_eq!(
+            render(RotationPeriod::Hourly),
+            Path::new("20250520/20250520_10_plain.log")
+        );
+        assert_eq!(
+            render(RotationPeriod::Daily),
+            Path::new("20250520/20250520_plain.log")
+        );
+        assert_eq!(
+            render(RotationPeriod::Minutes(15)),
+            Path::new("20250520/20250520_1045_plain.log")
+        );
+
+        let hourly = NamingTemplate::default_for(RotationPeriod::Hourly);
+        let parsed = hourly
+            .parse(Path::new("20250520/20250520_10_encrypt_p2_part3.log"))
+            .unwrap();
+        assert_eq!(parsed.hour, Some(10));
+        assert_eq!(parsed.minute, None);
+        assert!(parsed.is_encrypt);
+        assert_eq!((parsed.slot, parsed.part), (2, 3));
+
+        // 目录和文件名中的日期不一致、小时不合法、多余的后缀都不是日志文件
+        assert!(hourly
+            .parse(Path::new("20250521/20250520_10_plain.log"))
+            .is_none());
+        assert!(hourly
+            .parse(Path::new("20250520/20250520_24_plain.log"))
+            .is_none());
+        assert!(hourly
+            .parse(Path::new("20250520/20250520_10_plain.log.torn"))
+            .is_none());
+        assert!(hourly
+            .parse(Path::new("20250520/20250520_10_plain_x.log"))
+            .is_none());
+    }
+
+    #[test]
+    fn test_custom_template() {
+        let template: NamingTemplate =
+            "{stream}/{yyyy}/{MM}{dd}/{HH}{mm}-{pid}-{mode}{slot}{part}.log"
+                .parse()
+                .unwrap();
+        assert!(template.supports(RotationPeriod::Minutes(5)));
+
+        let mut name = file_name();
+        name.stream = "app-main".to_string();
+        name.pid = Some(4321);
+        name.is_encrypt = true;
+        name.slot = 1;
+        name.part = 12;
+
+        let path = template.render(&name);
+        assert_eq!(
+            path,
+            Path::new("app-main/2025/0520/1045-4321-encrypt_p1_part12.log")
+        );
+        assert_eq!(template.parse(&path), Some(name));
+    }
+
+    #[test]
+    fn test_invalid_template() {
+        for pattern in [
+            "{yyyy}{MM}{dd}_{mode}{slot}{part}.txt",
+            "{yyyy}{MM}{dd}_{mode}{slot}.log",
+            "{yyyy}{MM}_{mode}{slot}{part}.log",
+            "{yyyy}{MM}{dd}_{level}_{mode}{slot}{part}.log",
+            "{yyyy}{MM}{dd}_{mode{slot}{part}.log",
+            "{yyyy}{MM}{dd}//{mode}{slot}{part}.log",
+            "../{yyyy}{MM}{dd}_{mode}{slot}{part}.log",
+            "{yyyy}{MM}{dd}_{stream}{pid}_{mode}{slot}{part}.log",
+        ] {
+            assert!(pattern.parse::<NamingTemplate>().is_err(), "{}", pattern);
+        }
+
+        let daily = NamingTemplate::default_for(RotationPeriod::Daily);
+        assert!(daily.supports(RotationPeriod::Daily));
+        assert!(!daily.supports(RotationPeriod::Hourly));
+        assert!(!NamingTemplate::default_for(RotationPeriod::Hourly)
+            .supports(RotationPeriod::Minutes(30)));
+    }
+
+    #[test]
+    fn test_find_files() {
+        let base_dir = PathBuf::from("../target/tmp_log_naming_find");
+        let _ = remove_dir_all(&base_dir);
+
+        let template: NamingTemplate = "{yyyy}/{MM}/{dd}/{HH}_{mode}{slot}{part}.log"
+            .parse()
+            .unwrap();
+        let mut expected = vec![];
+        for hour in [1, 2] {
+            let mut name = file_name();
+            name.hour = Some(hour);
+            name.minute = None;
+            let path = base_dir.join(template.render(&name));
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, b"").unwrap();
+            expected.push((path, name));
+        }
+        // 不符合模板的文件和目录
+        fs::write(base_dir.join("2025/05/20/01_plain.log.torn"), b"").unwrap();
+        fs::create_dir_all(base_dir.join("2025/05/xx")).unwrap();
+        fs::write(base_dir.join("2025/05/xx/01_plain.log"), b"").unwrap();
+
+        let mut files = template.find_files(&base_dir).unwrap();
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(files, expected);
+    }
+}
@@ -0,0 +1,115 @@
+/// 测试结构化日志的编码和输出格式
+#[cfg(test)]
+pub mod record_test {
+    use chrono::{TimeZone, Utc};
+    use logger::record::{current_thread_id, Level, LineFormat, Record};
+    use logger::time_zone::LogTimeZone;
+    use std::thread;
+
+    fn record() -> Record {
+        Record {
+            timestamp_ms: Utc
+                .with_ymd_and_hms(2025, 5, 20, 2, 45, 1)
+                .unwrap()
+                .timestamp_millis()
+                + 123,
+            level: Level::Warn,
+            tag: "network".to_string(),
+            thread_id: 7,
+            message: "请求超时\n重试 3 次".to_string(),
+            fields: vec![("url".to_string(), "/api".to_string())],
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let record = record();
+        assert_eq!(Record::decode(&record.encode()).unwrap(), record);
+
+        let empty = Record::new(Level::Trace, "", "");
+        assert_eq!(Record::decode(&empty.encode()).unwrap(), empty);
+
+        let with_fields = Record::new(Level::Info, "tag", "message")
+            .with_field("a", "1")
+            .with_field("", "")
+            .with_field("中文", "值");
+        assert_eq!(Record::decode(&with_fields.encode()).unwrap(), with_fields);
+
+        // 长度不足或 tag 长度越界
+        let bytes = record.encode();
+        assert!(Record::decode(&bytes[..10]).is_err());
+        let mut bytes = Record::new(Level::Info, "tag", "").encode();
+        bytes.pop();
+        assert!(Record::decode(&bytes).is_err());
+        assert!(Record::decode(b"hello world, plain text").is_err());
+        assert!(Record::decode(b"").is_err());
+    }
+
+    #[test]
+    fn test_decode_unknown_version() {
+        let mut bytes = record().encode();
+        bytes[0] = 2;
+        assert!(Record::decode(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_level() {
+        for level in [
+            Level::Trace,
+            Level::Debug,
+            Level::Info,
+            Level::Warn,
+            Level::Error,
+        ] {
+            assert_eq!(level.to_string().parse::<Level>().unwrap(), level);
+        }
+        assert_eq!("warn".parse::<Level>().unwrap(), Level::Warn);
+        assert!("fatal".parse::<Level>().is_err());
+        assert!(Level::Debug < Level::Error);
+        assert_eq!(format!("{:<5}|", Level::Info), "INFO |");
+    }
+
+    #[test]
+    fn test_render() {
+        let record = record();
+        let shanghai = LogTimeZone::default();
+        assert_eq!(
+            LineFormat::default().render(&record, &shanghai),
+            "2025-05-20 10:45:01.123 WARN [7] network: 请求超时\n重试 3 次 url=/api"
+        );
+        let without_fields = Record {
+            fields: Vec::new(),
+            ..record.clone()
+        };
+        assert_eq!(
+            LineFormat::new("{message}{fields}|").render(&without_fields, &shanghai),
+            "请求超时\n重试 3 次|"
+        );
+
+        let utc = "UTC".parse::<LogTimeZone>().unwrap();
+        let format = LineFormat::new("{timestamp}|{time}|{level}|{unknown}|{tag}");
+        assert_eq!(
+            format.render(&record, &utc),
+            format!(
+                "{}|2025-05-20 02:45:01.123|WARN|{{unknown}}|network",
+                record.timestamp_ms
+            )
+        );
+
+        // 未闭合的占位符原样输出
+        assert_eq!(
+            LineFormat::new("{level} {message").render(&record, &utc),
+            "WARN {message"
+        );
+    }
+
+    #[test]
+    fn test_thread_id() {
+        let id = current_thread_id();
+        assert_eq!(id, current_thread_id());
+        assert_eq!(Record::new(Level::Info, "", "").thread_id, id);
+
+        let other = thread::spawn(current_thread_id).join().unwrap();
+        assert_ne!(id, other);
+    }
+}
@@ -0,0 +1 @@
+mod time_zone_test;
@@ -0,0 +1,86 @@
+#[cfg(test)]
+pub mod time_zone_test {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use logger::time_zone::LogTimeZone;
+
+    #[test]
+    fn test_parse_display() {
+        for name in [
+            "Asia/Shanghai",
+            "Europe/Berlin",
+            "+08:00",
+            "-05:30",
+            "+00:00",
+            "UTC",
+        ] {
+            let time_zone: LogTimeZone = name.parse().unwrap();
+            assert_eq!(time_zone.to_string(), name);
+        }
+        assert_eq!(
+            LogTimeZone::from_offset_seconds(-(5 * 3600 + 30 * 60)),
+            Some("-05:30".parse().unwrap())
+        );
+        assert_eq!(LogTimeZone::default().to_string(), "Asia/Shanghai");
+
+        assert!("Mars/Olympus".parse::<LogTimeZone>().is_err());
+        assert!("+08:60".parse::<LogTimeZone>().is_err());
+        assert!("+25:00".parse::<LogTimeZone>().is_err());
+        assert!(LogTimeZone::from_offset_seconds(25 * 3600).is_none());
+    }
+
+    #[test]
+    fn test_to_local() {
+        let utc = Utc.with_ymd_and_hms(2025, 5, 20, 18, 30, 0).unwrap();
+        let local = |name: &str| name.parse::<LogTimeZone>().unwrap().to_local(&utc);
+
+        let expected = NaiveDate::from_ymd_opt(2025, 5, 21).unwrap();
+        assert_eq!(
+            local("Asia/Shanghai"),
+            expected.and_hms_opt(2, 30, 0).unwrap()
+        );
+        assert_eq!(
+            local("-05:30"),
+            utc.naive_utc() - chrono::Duration::minutes(330)
+        );
+        assert_eq!(local("UTC"), utc.naive_utc());
+
+        for name in ["Asia/Shanghai", "-05:30", "UTC"] {
+            let time_zone: LogTimeZone = name.parse().unwrap();
+            assert_eq!(time_zone.to_utc(&local(name)), Some(utc));
+        }
+    }
+
+    #[test]
+    fn test_daylight_saving() {
+        let berlin: LogTimeZone = "Europe/Berlin".parse().unwrap();
+        let date = |d| NaiveDate::from_ymd_opt(2025, 3, d).unwrap();
+
+        // 冬令时 +01:00，夏令时 +02:00
+        let winter = berlin.to_utc(&date(1).and_hms_opt(12, 0, 0).unwrap());
+        assert_eq!(
+            winter,
+            Some(Utc.with_ymd_and_hms(2025, 3, 1, 11, 0, 0).unwrap())
+        );
+        let summer = berlin.to_utc(&date(31).and_hms_opt(12, 0, 0).unwrap());
+        assert_eq!(
+            summer,
+            Some(Utc.with_ymd_and_hms(2025, 3, 31, 10, 0, 0).unwrap())
+        );
+
+        // 2025-03-30 02:00 ~ 03:00 不存在，取 03:00（UTC 01:00）
+        assert_eq!(
+            berlin.to_utc(&date(30).and_hms_opt(2, 30, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2025, 3, 30, 1, 0, 0).unwrap())
+        );
+
+        // 2025-10-26 02:00 ~ 03:00 出现两次，取较早的一个
+        let repeated = NaiveDate::from_ymd_opt(2025, 10, 26)
+            .unwrap()
+            .and_hms_opt(2, 30, 0)
+            .unwrap();
+        assert_eq!(
+            berlin.to_utc(&repeated),
+            Some(Utc.with_ymd_and_hms(2025, 10, 26, 0, 30, 0).unwrap())
+        );
+    }
+}
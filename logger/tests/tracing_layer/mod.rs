@@ -0,0 +1 @@
+mod tracing_layer_test;
@@ -0,0 +1,68 @@
+/// 测试 `tracing` Layer，需要开启 `tracing` feature
+#[cfg(all(test, feature = "tracing"))]
+pub mod tracing_layer_test {
+    use crate::base::base_test::export_recent_lines;
+    use logger::mmap_config::MmapConfig;
+    use logger::mmap_writer::SharedMmapWriter;
+    use logger::record::LineFormat;
+    use logger::tracing_layer::MmapLayer;
+    use std::fs::remove_dir_all;
+    use std::path::PathBuf;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_events_and_spans() {
+        let base_dir = PathBuf::from("../target/tmp_log_tracing_layer");
+        let _ = remove_dir_all(&base_dir);
+        let mut config = MmapConfig::new("12345", true);
+        config.set_line_format(LineFormat::new("{level} {tag} {message}{fields}"));
+        let writer = SharedMmapWriter::try_new(&base_dir, config).unwrap();
+
+        let subscriber = tracing_subscriber::registry().with(MmapLayer::new(writer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(target: "app", "no span");
+            let request = tracing::info_span!(
+                target: "app",
+                "request",
+                id = 42,
+                status = tracing::field::Empty
+            );
+            let entered = request.enter();
+            tracing::info!(target: "app", user = "bob", "handled {}", 1);
+            {
+                let db = tracing::debug_span!(target: "app::db", "db", table = "users");
+                let _entered = db.enter();
+                tracing::warn!(target: "app::db", "slow");
+            }
+            request.record("status", 200);
+            drop(entered);
+        });
+
+        let lines = export_recent_lines(&writer);
+        assert_eq!(
+            lines[..5],
+            [
+                "INFO app no span",
+                "INFO app enter request spans=request request.id=42",
+                "INFO app handled 1 spans=request request.id=42 user=bob",
+                "DEBUG app::db enter db spans=request:db request.id=42 db.table=users",
+                "WARN app::db slow spans=request:db request.id=42 db.table=users",
+            ]
+        );
+        assert!(lines[5].starts_with(
+            "DEBUG app::db exit db spans=request:db request.id=42 db.table=users time.elapsed="
+        ));
+        assert!(lines[6].starts_with(
+            "DEBUG app::db close db spans=request:db request.id=42 db.table=users time.busy="
+        ));
+        assert!(lines[6].contains(" time.idle="));
+        assert!(lines[7].starts_with(
+            "INFO app exit request spans=request request.id=42 request.status=200 time.elapsed="
+        ));
+        assert!(lines[8].starts_with("INFO app close request spans=request"));
+        assert_eq!(lines.len(), 9);
+
+        drop(writer);
+        let _ = remove_dir_all(&base_dir);
+    }
+}